@@ -0,0 +1,44 @@
+//! Times the conversions between `EtherType`/`IPProtocol` and their raw
+//! values, which every frame goes through.
+//!
+//! Run with `cargo bench --bench conversions`.
+
+extern crate pktparse;
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use pktparse::ethernet::EtherType;
+use pktparse::ip::IPProtocol;
+
+const ITERATIONS: u32 = 10_000_000;
+
+fn bench<F: FnMut(u32)>(name: &str, mut f: F) {
+    let start = Instant::now();
+    for i in 0..ITERATIONS {
+        f(i);
+    }
+    let elapsed = start.elapsed();
+    println!("{:<24} {:>6.2} ns/iter", name, elapsed.as_nanos() as f64 / ITERATIONS as f64);
+}
+
+fn main() {
+    // A mix of known values and ones that fall back to `Other`
+    let ethertypes = [0x0800u16, 0x86dd, 0x8100, 0x88b5, 0x0004, 0x0806];
+    let protocols = [6u8, 17, 1, 58, 0xfd, 43];
+
+    bench("EtherType::from_raw", |i| {
+        black_box(EtherType::from_raw(black_box(ethertypes[i as usize % ethertypes.len()])));
+    });
+    bench("EtherType::to_raw", |i| {
+        let ethertype = EtherType::from_raw(ethertypes[i as usize % ethertypes.len()]);
+        black_box(black_box(ethertype).to_raw());
+    });
+    bench("IPProtocol::from_raw", |i| {
+        black_box(IPProtocol::from_raw(black_box(protocols[i as usize % protocols.len()])));
+    });
+    bench("IPProtocol::to_raw", |i| {
+        let protocol = IPProtocol::from_raw(protocols[i as usize % protocols.len()]);
+        black_box(black_box(protocol).to_raw());
+    });
+}
@@ -0,0 +1,99 @@
+//! Handles parsing of IPsec Authentication Headers
+//!
+//! AH runs over IP as `IPProtocol::AH`, and over IPv6 also as an extension
+//! header; `next_header` gives the protocol following it. Over IPv6,
+//! `ipv6::skip_extension_headers` steps over it to the upper layer.
+
+use alloc::vec::Vec;
+
+use nom::{IResult, be_u8, be_u16, be_u32};
+
+use ip::{protocol, IPProtocol};
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct AuthHeader {
+    pub next_header: IPProtocol,
+    /// Length of the header in 4-byte units, minus 2
+    pub payload_len: u8,
+    /// Security Parameters Index, identifying the security association
+    pub spi: u32,
+    pub sequence: u32,
+    /// Integrity Check Value
+    pub icv: Vec<u8>,
+}
+
+// The ICV fills the rest of the `(payload_len + 2) * 4` byte header, after
+// the 12 fixed bytes
+named!(icv_length<&[u8], (u8, usize)>, map_opt!(
+    be_u8,
+    |l: u8| ((l as usize + 2) * 4).checked_sub(12).map(|len| (l, len))
+));
+
+named!(ah_header<&[u8], AuthHeader>, chain!(
+    next_header: protocol ~
+    payload_len: icv_length ~
+    be_u16 ~
+    spi: be_u32 ~
+    sequence: be_u32 ~
+    icv: take!(payload_len.1),
+    || AuthHeader {
+        next_header,
+        payload_len: payload_len.0,
+        spi,
+        sequence,
+        icv: icv.to_vec(),
+    }
+));
+
+pub fn parse_ah_header(i: &[u8]) -> IResult<&[u8], AuthHeader> {
+    ah_header(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_ah_header, AuthHeader};
+    use ip::IPProtocol;
+    use nom::IResult;
+    use tcp::parse_tcp_header;
+
+    #[test]
+    fn ah_header_before_tcp_works() {
+        let bytes = [0x06, 0x04, 0x00, 0x00, /* TCP, 24 bytes */
+                     0x00, 0x00, 0x10, 0x02, /* SPI */
+                     0x00, 0x00, 0x00, 0x07, /* Sequence number */
+                     0x5a, 0x1c, 0x3e, 0x9f, 0x27, 0x40, 0x8b, 0xd1,
+                     0x66, 0x0e, 0xa3, 0x52, /* HMAC-SHA1-96 ICV */
+                     0xc3, 0x50, 0x00, 0x50, 0x00, 0x00, 0x00, 0x01,
+                     0x00, 0x00, 0x00, 0x00, 0x50, 0x02, 0x72, 0x10,
+                     0x00, 0x00, 0x00, 0x00 /* TCP SYN */];
+        let expectation = AuthHeader {
+            next_header: IPProtocol::TCP,
+            payload_len: 4,
+            spi: 0x1002,
+            sequence: 7,
+            icv: bytes[12..24].to_vec(),
+        };
+        let segment = match parse_ah_header(&bytes) {
+            IResult::Done(rest, header) => {
+                assert_eq!(header, expectation);
+                rest
+            }
+            other => panic!("expected a header, got {:?}", other),
+        };
+        if let IResult::Done(_, tcp) = parse_tcp_header(segment) {
+            assert_eq!(tcp.dest_port, 80);
+            assert!(tcp.flag_syn);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn ah_header_shorter_than_fixed_part_is_an_error() {
+        let bytes = [0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x02];
+        match parse_ah_header(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,437 @@
+//! Handles parsing of ARP packets
+
+use core::fmt;
+
+use alloc::vec::Vec;
+
+use nom::{IResult, be_u8, be_u16};
+
+use ethernet::MacAddress;
+use ipv4::IPv4Address;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum HardwareAddressType {
+    Ethernet,
+    Other(u16),
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ProtocolAddressType {
+    IPv4,
+    Other(u16),
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Operation {
+    Request,
+    Reply,
+    RequestReverse,
+    ReplyReverse,
+    Other(u16),
+}
+/// A hardware address, typed when the hardware type is Ethernet and it is 6
+/// bytes long
+#[derive(Debug, PartialEq, Eq)]
+pub enum HardwareAddress {
+    Ethernet(MacAddress),
+    Other(Vec<u8>),
+}
+/// A protocol address, typed when the protocol type is IPv4 and it is 4
+/// bytes long
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProtocolAddress {
+    IPv4(IPv4Address),
+    Other(Vec<u8>),
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct ArpPacket {
+    pub hw_addr_type: HardwareAddressType,
+    pub proto_addr_type: ProtocolAddressType,
+    pub hw_addr_size: u8,
+    pub proto_addr_size: u8,
+    pub operation: Operation,
+    pub src_mac: HardwareAddress,
+    pub src_addr: ProtocolAddress,
+    pub dest_mac: HardwareAddress,
+    pub dest_addr: ProtocolAddress,
+}
+
+impl From<u16> for HardwareAddressType {
+    fn from(raw: u16) -> Self {
+        match raw {
+            1 => HardwareAddressType::Ethernet,
+            other => HardwareAddressType::Other(other),
+        }
+    }
+}
+
+impl From<HardwareAddressType> for u16 {
+    fn from(t: HardwareAddressType) -> u16 {
+        match t {
+            HardwareAddressType::Ethernet => 1,
+            HardwareAddressType::Other(other) => other,
+        }
+    }
+}
+
+impl From<u16> for ProtocolAddressType {
+    fn from(raw: u16) -> Self {
+        match raw {
+            0x0800 => ProtocolAddressType::IPv4,
+            other => ProtocolAddressType::Other(other),
+        }
+    }
+}
+
+impl From<ProtocolAddressType> for u16 {
+    fn from(t: ProtocolAddressType) -> u16 {
+        match t {
+            ProtocolAddressType::IPv4 => 0x0800,
+            ProtocolAddressType::Other(other) => other,
+        }
+    }
+}
+
+impl From<u16> for Operation {
+    fn from(raw: u16) -> Self {
+        match raw {
+            1 => Operation::Request,
+            2 => Operation::Reply,
+            3 => Operation::RequestReverse,
+            4 => Operation::ReplyReverse,
+            other => Operation::Other(other),
+        }
+    }
+}
+
+impl From<Operation> for u16 {
+    fn from(operation: Operation) -> u16 {
+        match operation {
+            Operation::Request => 1,
+            Operation::Reply => 2,
+            Operation::RequestReverse => 3,
+            Operation::ReplyReverse => 4,
+            Operation::Other(other) => other,
+        }
+    }
+}
+
+impl ArpPacket {
+    /// A gratuitous ARP, or announcement: a request or reply for the
+    /// sender's own address
+    pub fn is_gratuitous(&self) -> bool {
+        match self.operation {
+            Operation::Request | Operation::Reply => self.src_addr == self.dest_addr,
+            _ => false,
+        }
+    }
+
+    /// An ARP probe (RFC 5227): a request with an all-zero sender address,
+    /// sent to check that the target address is unused
+    pub fn is_probe(&self) -> bool {
+        self.operation == Operation::Request && self.src_addr.as_bytes().iter().all(|&b| b == 0)
+    }
+}
+
+impl HardwareAddress {
+    fn as_bytes(&self) -> &[u8] {
+        match *self {
+            HardwareAddress::Ethernet(ref mac) => &mac.0,
+            HardwareAddress::Other(ref raw) => raw,
+        }
+    }
+}
+
+impl ProtocolAddress {
+    fn as_bytes(&self) -> &[u8] {
+        match *self {
+            ProtocolAddress::IPv4(ref addr) => &addr.0,
+            ProtocolAddress::Other(ref raw) => raw,
+        }
+    }
+}
+
+fn fmt_raw_address(raw: &[u8], f: &mut fmt::Formatter) -> fmt::Result {
+    for (n, byte) in raw.iter().enumerate() {
+        write!(f, "{}{:02x}", if n == 0 { "" } else { ":" }, byte)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for ArpPacket {
+    /// Generates an Ethernet/IPv4 packet with one of the four known operations
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<ArpPacket> {
+        let operation = match u.choose_index(4)? {
+            0 => Operation::Request,
+            1 => Operation::Reply,
+            2 => Operation::RequestReverse,
+            _ => Operation::ReplyReverse,
+        };
+        Ok(ArpPacket {
+            hw_addr_type: HardwareAddressType::Ethernet,
+            proto_addr_type: ProtocolAddressType::IPv4,
+            hw_addr_size: 6,
+            proto_addr_size: 4,
+            operation,
+            src_mac: HardwareAddress::Ethernet(u.arbitrary()?),
+            src_addr: ProtocolAddress::IPv4(u.arbitrary()?),
+            dest_mac: HardwareAddress::Ethernet(u.arbitrary()?),
+            dest_addr: ProtocolAddress::IPv4(u.arbitrary()?),
+        })
+    }
+}
+
+impl fmt::Display for HardwareAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HardwareAddress::Ethernet(ref mac) => mac.fmt(f),
+            HardwareAddress::Other(ref raw) => fmt_raw_address(raw, f),
+        }
+    }
+}
+
+impl fmt::Display for ProtocolAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProtocolAddress::IPv4(ref addr) => addr.fmt(f),
+            ProtocolAddress::Other(ref raw) => fmt_raw_address(raw, f),
+        }
+    }
+}
+
+impl fmt::Display for ArpPacket {
+    /// A one-line summary in the style of tcpdump, e.g.
+    /// `ARP who-has 10.10.1.180 tell 10.10.1.135`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.operation {
+            Operation::Request => write!(f, "ARP who-has {} tell {}", self.dest_addr, self.src_addr),
+            Operation::Reply => write!(f, "ARP {} is-at {}", self.src_addr, self.src_mac),
+            Operation::RequestReverse => write!(f, "RARP who-is {} tell {}", self.dest_mac, self.src_mac),
+            Operation::ReplyReverse => write!(f, "RARP {} at {}", self.dest_mac, self.dest_addr),
+            Operation::Other(op) => write!(f, "ARP op={} {} -> {}", op, self.src_addr, self.dest_addr),
+        }
+    }
+}
+
+fn to_hardware_address(hw_addr_type: HardwareAddressType, i: &[u8]) -> HardwareAddress {
+    if hw_addr_type == HardwareAddressType::Ethernet && i.len() == 6 {
+        HardwareAddress::Ethernet(MacAddress(*array_ref![i, 0, 6]))
+    } else {
+        HardwareAddress::Other(i.to_vec())
+    }
+}
+
+fn to_protocol_address(proto_addr_type: ProtocolAddressType, i: &[u8]) -> ProtocolAddress {
+    if proto_addr_type == ProtocolAddressType::IPv4 && i.len() == 4 {
+        ProtocolAddress::IPv4(IPv4Address(*array_ref![i, 0, 4]))
+    } else {
+        ProtocolAddress::Other(i.to_vec())
+    }
+}
+
+named!(arp_packet<&[u8], ArpPacket>, chain!(
+    hw_addr_type: be_u16 ~
+    proto_addr_type: be_u16 ~
+    hw_addr_size: be_u8 ~
+    proto_addr_size: be_u8 ~
+    operation: be_u16 ~
+    src_mac: take!(hw_addr_size) ~
+    src_addr: take!(proto_addr_size) ~
+    dest_mac: take!(hw_addr_size) ~
+    dest_addr: take!(proto_addr_size),
+    || {
+        let hw_addr_type = HardwareAddressType::from(hw_addr_type);
+        let proto_addr_type = ProtocolAddressType::from(proto_addr_type);
+        ArpPacket {
+            hw_addr_type,
+            proto_addr_type,
+            hw_addr_size,
+            proto_addr_size,
+            operation: operation.into(),
+            src_mac: to_hardware_address(hw_addr_type, src_mac),
+            src_addr: to_protocol_address(proto_addr_type, src_addr),
+            dest_mac: to_hardware_address(hw_addr_type, dest_mac),
+            dest_addr: to_protocol_address(proto_addr_type, dest_addr),
+        }
+    }
+));
+
+/// Parses an ARP or RARP packet, reading each address with the size given in the
+/// header rather than assuming Ethernet and IPv4
+pub fn parse_arp_pkt(i: &[u8]) -> IResult<&[u8], ArpPacket> {
+    arp_packet(i)
+}
+
+/// Appends `pkt` to `out` in wire format, the reverse of `parse_arp_pkt`.
+/// The size fields are written as they are, so they should match the
+/// addresses.
+pub fn write_arp_pkt(pkt: &ArpPacket, out: &mut Vec<u8>) {
+    out.extend_from_slice(&u16::from(pkt.hw_addr_type).to_be_bytes());
+    out.extend_from_slice(&u16::from(pkt.proto_addr_type).to_be_bytes());
+    out.push(pkt.hw_addr_size);
+    out.push(pkt.proto_addr_size);
+    out.extend_from_slice(&u16::from(pkt.operation).to_be_bytes());
+    out.extend_from_slice(pkt.src_mac.as_bytes());
+    out.extend_from_slice(pkt.src_addr.as_bytes());
+    out.extend_from_slice(pkt.dest_mac.as_bytes());
+    out.extend_from_slice(pkt.dest_addr.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::IResult;
+    const EMPTY_SLICE: &[u8] = &[];
+
+    #[test]
+    fn arp_packet_works() {
+        let bytes = [0x00, 0x01, /* Hardware type */
+                     0x08, 0x00, /* Protocol type */
+                     0x06, /* Hardware address size */
+                     0x04, /* Protocol address size */
+                     0x00, 0x01, /* Operation */
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b, /* Sender MAC */
+                     0x0a, 0x0a, 0x01, 0x87, /* Sender IP */
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, /* Target MAC */
+                     0x0a, 0x0a, 0x01, 0xb4 /* Target IP */];
+        let expectation = ArpPacket {
+            hw_addr_type: HardwareAddressType::Ethernet,
+            proto_addr_type: ProtocolAddressType::IPv4,
+            hw_addr_size: 6,
+            proto_addr_size: 4,
+            operation: Operation::Request,
+            src_mac: HardwareAddress::Ethernet(MacAddress([0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b])),
+            src_addr: ProtocolAddress::IPv4(IPv4Address([10, 10, 1, 135])),
+            dest_mac: HardwareAddress::Ethernet(MacAddress([0; 6])),
+            dest_addr: ProtocolAddress::IPv4(IPv4Address([10, 10, 1, 180])),
+        };
+        assert_eq!(expectation.to_string(), "ARP who-has 10.10.1.180 tell 10.10.1.135");
+        assert_eq!(parse_arp_pkt(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[test]
+    fn arp_enums_round_trip_through_u16() {
+        for &raw in &[1, 6, 0x20] {
+            assert_eq!(u16::from(HardwareAddressType::from(raw)), raw);
+        }
+        for &raw in &[0x0800, 0x86dd] {
+            assert_eq!(u16::from(ProtocolAddressType::from(raw)), raw);
+        }
+        for raw in 1..6 {
+            assert_eq!(u16::from(Operation::from(raw)), raw);
+        }
+        assert_eq!(u16::from(HardwareAddressType::Ethernet), 1);
+        assert_eq!(u16::from(ProtocolAddressType::IPv4), 0x0800);
+        assert_eq!(u16::from(Operation::Request), 1);
+        assert_eq!(u16::from(Operation::Reply), 2);
+    }
+
+    #[test]
+    fn gratuitous_arp_and_probe_are_detected() {
+        let announcement = [0x00, 0x01, 0x08, 0x00, 0x06, 0x04, 0x00, 0x01,
+                            0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                            0x0a, 0x0a, 0x01, 0x87,
+                            0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                            0x0a, 0x0a, 0x01, 0x87];
+        let probe = [0x00, 0x01, 0x08, 0x00, 0x06, 0x04, 0x00, 0x01,
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x00, 0x00, 0x00, 0x00,
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                     0x0a, 0x0a, 0x01, 0x87];
+        if let IResult::Done(_, packet) = parse_arp_pkt(&announcement) {
+            assert!(packet.is_gratuitous());
+            assert!(!packet.is_probe());
+        } else {
+            panic!();
+        }
+        if let IResult::Done(_, packet) = parse_arp_pkt(&probe) {
+            assert!(!packet.is_gratuitous());
+            assert!(packet.is_probe());
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn write_arp_pkt_round_trips() {
+        let bytes = [0x00, 0x01, 0x08, 0x00, 0x06, 0x04, 0x00, 0x01,
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x0a, 0x0a, 0x01, 0x87,
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                     0x0a, 0x0a, 0x01, 0xb4];
+        if let IResult::Done(_, packet) = parse_arp_pkt(&bytes) {
+            let mut out = Vec::new();
+            write_arp_pkt(&packet, &mut out);
+            assert_eq!(out, &bytes[..]);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn arp_packet_honors_address_sizes() {
+        let bytes = [0x00, 0x20, /* Hardware type: InfiniBand */
+                     0x86, 0xdd, /* Protocol type: IPv6 */
+                     0x02, /* Hardware address size */
+                     0x10, /* Protocol address size */
+                     0x00, 0x02, /* Operation */
+                     0xaa, 0xbb,
+                     0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01,
+                     0xcc, 0xdd,
+                     0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x02];
+        let expectation = ArpPacket {
+            hw_addr_type: HardwareAddressType::Other(0x20),
+            proto_addr_type: ProtocolAddressType::Other(0x86dd),
+            hw_addr_size: 2,
+            proto_addr_size: 16,
+            operation: Operation::Reply,
+            src_mac: HardwareAddress::Other(vec![0xaa, 0xbb]),
+            src_addr: ProtocolAddress::Other(bytes[10..26].to_vec()),
+            dest_mac: HardwareAddress::Other(vec![0xcc, 0xdd]),
+            dest_addr: ProtocolAddress::Other(bytes[28..44].to_vec()),
+        };
+        assert_eq!(parse_arp_pkt(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[test]
+    fn arp_packet_types_addresses_by_type_and_size() {
+        let bytes = [0x00, 0x06, /* Hardware type: IEEE 802 */
+                     0x08, 0x00, /* Protocol type: IPv4 */
+                     0x06, /* Hardware address size */
+                     0x04, /* Protocol address size */
+                     0x00, 0x01, /* Operation */
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x0a, 0x0a, 0x01, 0x87,
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                     0x0a, 0x0a, 0x01, 0xb4];
+        let expectation = ArpPacket {
+            hw_addr_type: HardwareAddressType::Other(6),
+            proto_addr_type: ProtocolAddressType::IPv4,
+            hw_addr_size: 6,
+            proto_addr_size: 4,
+            operation: Operation::Request,
+            src_mac: HardwareAddress::Other(bytes[8..14].to_vec()),
+            src_addr: ProtocolAddress::IPv4(IPv4Address([10, 10, 1, 135])),
+            dest_mac: HardwareAddress::Other(vec![0; 6]),
+            dest_addr: ProtocolAddress::IPv4(IPv4Address([10, 10, 1, 180])),
+        };
+        assert_eq!(parse_arp_pkt(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[test]
+    fn rarp_request_works() {
+        let bytes = [0x00, 0x01, 0x08, 0x00, 0x06, 0x04,
+                     0x00, 0x03, /* Operation: reverse request */
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x00, 0x00, 0x00, 0x00,
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x00, 0x00, 0x00, 0x00];
+        if let IResult::Done(_, packet) = parse_arp_pkt(&bytes) {
+            assert_eq!(packet.operation, Operation::RequestReverse);
+            assert_eq!(packet.dest_mac,
+                       HardwareAddress::Ethernet(MacAddress([0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b])));
+        } else {
+            panic!();
+        }
+        assert_eq!(Operation::from(4), Operation::ReplyReverse);
+    }
+}
@@ -0,0 +1,182 @@
+//! Handles parsing of BGP-4 messages
+//!
+//! BGP runs over TCP on `BGP_PORT`. A segment may hold several messages, or
+//! part of one, so the remaining input after a message is the next one.
+
+use alloc::vec::Vec;
+
+use nom::{IResult, be_u8, be_u16, eof};
+
+use ipv4::IPv4Address;
+use options::for_each_option;
+
+/// The TCP port BGP speakers listen on
+pub const BGP_PORT: u16 = 179;
+/// Length of the message header, the shortest possible message
+pub const BGP_HEADER_LEN: u16 = 19;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BgpHeader {
+    /// Length of the whole message, header included
+    pub length: u16,
+    pub message_type: u8,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub enum BgpMessage {
+    Open(BgpOpen),
+    /// The body of an UPDATE message, not yet decoded
+    Update(Vec<u8>),
+    /// The body of a NOTIFICATION message, not yet decoded
+    Notification(Vec<u8>),
+    Keepalive,
+    /// A message type this crate does not decode, with its body
+    Other {
+        message_type: u8,
+        body: Vec<u8>,
+    },
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct BgpOpen {
+    pub version: u8,
+    /// The sender's AS number, or `AS_TRANS` (23456) when it needs four
+    /// bytes and is carried in a capability instead
+    pub my_as: u16,
+    /// Proposed hold time in seconds
+    pub hold_time: u16,
+    pub bgp_identifier: IPv4Address,
+    pub optional_parameters: Vec<BgpOptionalParameter>,
+}
+/// An optional parameter of an OPEN message; type 2 holds capabilities
+#[derive(Debug, PartialEq, Eq)]
+pub struct BgpOptionalParameter {
+    pub param_type: u8,
+    pub value: Vec<u8>,
+}
+
+named!(bgp_header<&[u8], BgpHeader>, chain!(
+    tag!([0xff; 16]) ~
+    length: map_opt!(be_u16, |l: u16| if l >= BGP_HEADER_LEN { Some(l) } else { None }) ~
+    message_type: be_u8,
+    || BgpHeader { length, message_type }
+));
+
+named!(optional_parameter<&[u8], BgpOptionalParameter>, chain!(
+    param_type: be_u8 ~
+    value: length_bytes!(be_u8),
+    || BgpOptionalParameter { param_type, value: value.to_vec() }
+));
+
+/// Parses optional parameters up to the end of the input
+fn optional_parameters(i: &[u8]) -> IResult<&[u8], Vec<BgpOptionalParameter>> {
+    let mut parameters = Vec::new();
+    for_each_option(i, optional_parameter, |parameter, rest| {
+        parameters.push(parameter);
+        Ok(rest)
+    }).map(|()| parameters)
+}
+
+named!(open<&[u8], BgpOpen>, chain!(
+    version: be_u8 ~
+    my_as: be_u16 ~
+    hold_time: be_u16 ~
+    bgp_identifier: map!(take!(4), |i: &[u8]| IPv4Address(*array_ref![i, 0, 4])) ~
+    optional_parameters: flat_map!(length_bytes!(be_u8), optional_parameters) ~
+    eof,
+    || BgpOpen { version, my_as, hold_time, bgp_identifier, optional_parameters }
+));
+
+fn to_bgp_message(message_type: u8, body: &[u8]) -> Option<BgpMessage> {
+    match message_type {
+        1 => match open(body) {
+            IResult::Done(_, open) => Some(BgpMessage::Open(open)),
+            _ => None,
+        },
+        2 => Some(BgpMessage::Update(body.to_vec())),
+        3 => Some(BgpMessage::Notification(body.to_vec())),
+        4 if body.is_empty() => Some(BgpMessage::Keepalive),
+        4 => None,
+        _ => Some(BgpMessage::Other { message_type, body: body.to_vec() }),
+    }
+}
+
+named!(bgp_message<&[u8], BgpMessage>, map_opt!(
+    chain!(
+        header: bgp_header ~
+        body: take!(header.length - BGP_HEADER_LEN),
+        || (header.message_type, body)
+    ),
+    |(message_type, body): (u8, &[u8])| to_bgp_message(message_type, body)
+));
+
+pub fn parse_bgp_header(i: &[u8]) -> IResult<&[u8], BgpHeader> {
+    bgp_header(i)
+}
+
+/// Parses one whole message, its header included
+pub fn parse_bgp_message(i: &[u8]) -> IResult<&[u8], BgpMessage> {
+    bgp_message(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::IResult;
+    const EMPTY_SLICE: &[u8] = &[];
+
+    #[test]
+    fn bgp_open_works() {
+        let bytes = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, /* Marker */
+                     0x00, 0x25, 0x01, /* Length 37, OPEN */
+                     0x04, 0xfd, 0xe8, 0x00, 0xb4, /* Version 4, AS 65000, hold time 180 */
+                     0x0a, 0x00, 0x00, 0x01, /* BGP identifier */
+                     0x08, /* Optional parameters length */
+                     0x02, 0x06, 0x01, 0x04, 0x00, 0x01, 0x00, 0x01 /* Multiprotocol IPv4 unicast */];
+        let expectation = BgpMessage::Open(BgpOpen {
+            version: 4,
+            my_as: 65000,
+            hold_time: 180,
+            bgp_identifier: IPv4Address([10, 0, 0, 1]),
+            optional_parameters: vec![BgpOptionalParameter {
+                param_type: 2,
+                value: vec![0x01, 0x04, 0x00, 0x01, 0x00, 0x01],
+            }],
+        });
+        assert_eq!(parse_bgp_header(&bytes),
+                   IResult::Done(&bytes[19..], BgpHeader { length: 37, message_type: 1 }));
+        assert_eq!(parse_bgp_message(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[test]
+    fn bgp_keepalives_back_to_back_work() {
+        let mut bytes = Vec::new();
+        for _ in 0..2 {
+            bytes.extend_from_slice(&[0xff; 16]);
+            bytes.extend_from_slice(&[0x00, 0x13, 0x04]);
+        }
+        match parse_bgp_message(&bytes) {
+            IResult::Done(rest, BgpMessage::Keepalive) => {
+                assert_eq!(parse_bgp_message(rest), IResult::Done(EMPTY_SLICE, BgpMessage::Keepalive));
+            }
+            other => panic!("expected a keepalive, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn bgp_header_with_bad_marker_or_length_is_an_error() {
+        let mut bytes = [0xff; 19];
+        bytes[16] = 0x00;
+        bytes[17] = 0x12;
+        bytes[18] = 0x04;
+        match parse_bgp_header(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+        bytes[0] = 0x00;
+        bytes[17] = 0x13;
+        match parse_bgp_header(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+}
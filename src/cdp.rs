@@ -0,0 +1,233 @@
+//! Handles parsing of Cisco Discovery Protocol packets
+//!
+//! CDP is carried in 802.3 frames sent to `CDP_MULTICAST`, behind an LLC
+//! header with a SNAP header of `CDP_OUI` and `CDP_PROTOCOL_ID`; see
+//! `ethernet::parse_llc_snap`. The TLVs run to the end of the input, so it
+//! should be trimmed to the 802.3 length field.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use nom::{IResult, Err, ErrorKind, be_u8, be_u16, be_u32, eof};
+
+use ethernet::MacAddress;
+use ipv4::IPv4Address;
+use options::for_each_option;
+
+/// The multicast address CDP packets are sent to
+pub const CDP_MULTICAST: MacAddress = MacAddress([0x01, 0x00, 0x0c, 0xcc, 0xcc, 0xcc]);
+/// The SNAP OUI of CDP frames
+pub const CDP_OUI: [u8; 3] = [0x00, 0x00, 0x0c];
+/// The SNAP protocol ID of CDP frames
+pub const CDP_PROTOCOL_ID: u16 = 0x2000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CdpPacket {
+    pub version: u8,
+    /// Seconds the receiver may keep this information
+    pub ttl: u8,
+    pub checksum: u16,
+    pub tlvs: Vec<CdpTlv>,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub enum CdpTlv {
+    DeviceId(String),
+    Addresses(Vec<CdpAddress>),
+    PortId(String),
+    /// Capability bits, e.g. `0x01` for a router and `0x08` for a switch
+    Capabilities(u32),
+    SoftwareVersion(String),
+    Platform(String),
+    /// A TLV type this crate does not decode, with its value bytes
+    Unknown {
+        tlv_type: u16,
+        value: Vec<u8>,
+    },
+}
+/// A network layer address of the device
+#[derive(Debug, PartialEq, Eq)]
+pub struct CdpAddress {
+    /// 1 for an NLPID protocol, 2 for an 802.2 protocol
+    pub protocol_type: u8,
+    pub protocol: Vec<u8>,
+    pub address: Vec<u8>,
+}
+
+/// Upper bound on TLVs in one packet, matching `lldp::MAX_LLDP_TLVS`
+pub const MAX_CDP_TLVS: usize = 256;
+
+/// The NLPID identifying IP
+const NLPID_IP: u8 = 0xcc;
+
+impl CdpAddress {
+    /// The address as IPv4, when its protocol is IP
+    pub fn ipv4(&self) -> Option<IPv4Address> {
+        if self.protocol_type == 1 && self.protocol == [NLPID_IP] && self.address.len() == 4 {
+            Some(IPv4Address(*array_ref![self.address, 0, 4]))
+        } else {
+            None
+        }
+    }
+}
+
+named!(cdp_address<&[u8], CdpAddress>, chain!(
+    protocol_type: be_u8 ~
+    protocol: length_bytes!(be_u8) ~
+    address: length_bytes!(be_u16),
+    || CdpAddress { protocol_type, protocol: protocol.to_vec(), address: address.to_vec() }
+));
+
+/// Length of the shortest address entry, with a one-byte protocol and an
+/// empty address
+const MIN_CDP_ADDRESS_LEN: usize = 5;
+
+fn addresses(i: &[u8]) -> IResult<&[u8], Vec<CdpAddress>> {
+    let (left, count) = try_parse!(i, be_u32);
+    // `count!` allocates for the whole count up front, so it must not be
+    // trusted beyond what the input could hold
+    if count as usize > left.len() / MIN_CDP_ADDRESS_LEN {
+        return IResult::Error(Err::Position(ErrorKind::Count, i));
+    }
+    chain!(left,
+        addresses: count!(cdp_address, count as usize) ~
+        eof,
+        || addresses
+    )
+}
+
+fn to_string(value: &[u8]) -> String {
+    String::from_utf8_lossy(value).into_owned()
+}
+
+fn to_cdp_tlv(tlv_type: u16, value: &[u8]) -> Option<CdpTlv> {
+    match tlv_type {
+        1 => Some(CdpTlv::DeviceId(to_string(value))),
+        2 => match addresses(value) {
+            IResult::Done(_, addresses) => Some(CdpTlv::Addresses(addresses)),
+            _ => None,
+        },
+        3 => Some(CdpTlv::PortId(to_string(value))),
+        4 if value.len() == 4 => Some(CdpTlv::Capabilities(u32::from_be_bytes(*array_ref![value, 0, 4]))),
+        4 => None,
+        5 => Some(CdpTlv::SoftwareVersion(to_string(value))),
+        6 => Some(CdpTlv::Platform(to_string(value))),
+        _ => Some(CdpTlv::Unknown { tlv_type, value: value.to_vec() }),
+    }
+}
+
+// The TLV length covers the type and length fields themselves
+named!(cdp_tlv<&[u8], CdpTlv>, map_opt!(
+    chain!(
+        tlv_type: be_u16 ~
+        length: map_opt!(be_u16, |l: u16| l.checked_sub(4)) ~
+        value: take!(length),
+        || (tlv_type, value)
+    ),
+    |(tlv_type, value): (u16, &[u8])| to_cdp_tlv(tlv_type, value)
+));
+
+/// Parses TLVs up to the end of the input
+fn cdp_tlvs(i: &[u8]) -> IResult<&[u8], Vec<CdpTlv>> {
+    let mut tlvs = Vec::new();
+    for_each_option(i, cdp_tlv, |tlv, rest| {
+        if tlvs.len() == MAX_CDP_TLVS {
+            return Err(ErrorKind::ManyMN);
+        }
+        tlvs.push(tlv);
+        Ok(rest)
+    }).map(|()| tlvs)
+}
+
+named!(cdp_packet<&[u8], CdpPacket>, chain!(
+    version: be_u8 ~
+    ttl: be_u8 ~
+    checksum: be_u16 ~
+    tlvs: cdp_tlvs,
+    || CdpPacket { version, ttl, checksum, tlvs }
+));
+
+pub fn parse_cdp_packet(i: &[u8]) -> IResult<&[u8], CdpPacket> {
+    cdp_packet(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethernet::{parse_ethernet_frame_8023, Ethernet8023Frame};
+    use nom::IResult;
+
+    #[test]
+    fn cdp_frame_works() {
+        let bytes = [0x01, 0x00, 0x0c, 0xcc, 0xcc, 0xcc, /* Destination */
+                     0x00, 0x1b, 0x54, 0x3a, 0x81, 0x02, /* Source */
+                     0x00, 0x52, /* Length */
+                     0xaa, 0xaa, 0x03, 0x00, 0x00, 0x0c, 0x20, 0x00, /* LLC, SNAP */
+                     0x02, 0xb4, 0x6c, 0x1f, /* Version 2, TTL 180, checksum */
+                     0x00, 0x01, 0x00, 0x08, b's', b'w', b'0', b'1', /* Device ID */
+                     0x00, 0x02, 0x00, 0x11, 0x00, 0x00, 0x00, 0x01, /* Addresses */
+                     0x01, 0x01, 0xcc, 0x00, 0x04, 0x0a, 0x00, 0x00, 0x01,
+                     0x00, 0x03, 0x00, 0x0a, b'F', b'a', b'0', b'/', b'1', b'3', /* Port ID */
+                     0x00, 0x04, 0x00, 0x08, 0x00, 0x00, 0x00, 0x28, /* Capabilities */
+                     0x00, 0x05, 0x00, 0x0a, b'I', b'O', b'S', b' ', b'1', b'2', /* Software */
+                     0x00, 0x06, 0x00, 0x0c, b'W', b'S', b'-', b'C', b'2', b'9', b'6', b'0', /* Platform */
+                     0x00, 0x0b, 0x00, 0x05, 0x01 /* Duplex */];
+        let cdp = match parse_ethernet_frame_8023(&bytes) {
+            IResult::Done(rest, Ethernet8023Frame::Ieee8023(ref frame)) => {
+                assert_eq!(frame.dest_mac, CDP_MULTICAST);
+                let snap = frame.llc.snap.as_ref().unwrap();
+                assert_eq!((snap.oui, snap.protocol_id), (CDP_OUI, CDP_PROTOCOL_ID));
+                assert_eq!(rest.len(), frame.length as usize - 8);
+                rest
+            }
+            other => panic!("expected an 802.3 frame, got {:?}", other),
+        };
+        let expectation = CdpPacket {
+            version: 2,
+            ttl: 180,
+            checksum: 0x6c1f,
+            tlvs: vec![
+                CdpTlv::DeviceId("sw01".into()),
+                CdpTlv::Addresses(vec![CdpAddress {
+                    protocol_type: 1,
+                    protocol: vec![0xcc],
+                    address: vec![10, 0, 0, 1],
+                }]),
+                CdpTlv::PortId("Fa0/13".into()),
+                CdpTlv::Capabilities(0x28),
+                CdpTlv::SoftwareVersion("IOS 12".into()),
+                CdpTlv::Platform("WS-C2960".into()),
+                CdpTlv::Unknown { tlv_type: 11, value: vec![0x01] },
+            ],
+        };
+        match parse_cdp_packet(cdp) {
+            IResult::Done(rest, packet) => {
+                assert!(rest.is_empty());
+                if let CdpTlv::Addresses(ref addresses) = packet.tlvs[1] {
+                    assert_eq!(addresses[0].ipv4(), Some(IPv4Address([10, 0, 0, 1])));
+                }
+                assert_eq!(packet, expectation);
+            }
+            other => panic!("expected a packet, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn cdp_tlv_shorter_than_its_header_is_an_error() {
+        let bytes = [0x02, 0xb4, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02];
+        match parse_cdp_packet(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn cdp_address_count_beyond_the_tlv_is_an_error() {
+        let bytes = [0x02, 0xb4, 0x00, 0x00,
+                     0x00, 0x02, 0x00, 0x0c, 0xff, 0xff, 0xff, 0xff, /* Addresses, count 2^32 - 1 */
+                     0x01, 0x01, 0xcc, 0x00];
+        match parse_cdp_packet(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+}
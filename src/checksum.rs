@@ -0,0 +1,106 @@
+//! Computes the Internet checksum (RFC 1071) used by IPv4, ICMP, TCP and UDP
+
+/// The ones-complement sum of `data` taken as big-endian 16-bit words, with
+/// an odd trailing byte padded with zero. The checksum field of a header is
+/// the complement of this sum, and a header with a correct checksum sums to
+/// `0xffff`.
+pub fn ones_complement_sum(data: &[u8]) -> u16 {
+    ChecksumBuilder::new().write(data).sum()
+}
+
+/// Accumulates the ones-complement sum over several slices, such as a
+/// pseudo-header, a header and its payload. The slices are summed as if they
+/// were concatenated, so a slice of odd length is paired with the first byte
+/// of the next one.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ChecksumBuilder {
+    sum: u64,
+    /// The high byte of a word whose low byte is in the next slice
+    odd_byte: Option<u8>,
+}
+
+impl ChecksumBuilder {
+    pub fn new() -> ChecksumBuilder {
+        ChecksumBuilder::default()
+    }
+
+    pub fn write(mut self, data: &[u8]) -> ChecksumBuilder {
+        let mut data = data;
+        if let Some(high) = self.odd_byte {
+            match data.split_first() {
+                Some((&low, rest)) => {
+                    self.sum += u64::from(u16::from_be_bytes([high, low]));
+                    self.odd_byte = None;
+                    data = rest;
+                }
+                None => return self,
+            }
+        }
+        let mut words = data.chunks_exact(2);
+        for word in &mut words {
+            self.sum += u64::from(u16::from_be_bytes([word[0], word[1]]));
+        }
+        self.odd_byte = words.remainder().first().cloned();
+        self
+    }
+
+    pub fn write_u16(self, value: u16) -> ChecksumBuilder {
+        self.write(&value.to_be_bytes())
+    }
+
+    /// The folded ones-complement sum of everything written so far
+    pub fn sum(&self) -> u16 {
+        let mut sum = self.sum;
+        if let Some(high) = self.odd_byte {
+            sum += u64::from(high) << 8;
+        }
+        while sum > 0xffff {
+            sum = (sum & 0xffff) + (sum >> 16);
+        }
+        sum as u16
+    }
+
+    /// The value to store in a checksum field, the complement of `sum`
+    pub fn finish(&self) -> u16 {
+        !self.sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ones_complement_sum, ChecksumBuilder};
+
+    #[test]
+    fn rfc1071_example_sum() {
+        // RFC 1071 section 3
+        let bytes = [0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7];
+        assert_eq!(ones_complement_sum(&bytes), 0xddf2);
+        assert_eq!(ChecksumBuilder::new().write(&bytes).finish(), 0x220d);
+    }
+
+    #[test]
+    fn split_slices_sum_like_their_concatenation() {
+        let bytes = [0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7];
+        let builder = ChecksumBuilder::new()
+            .write(&bytes[..3])
+            .write(&[])
+            .write(&bytes[3..5])
+            .write(&bytes[5..]);
+        assert_eq!(builder.sum(), 0xddf2);
+    }
+
+    #[test]
+    fn odd_tail_is_padded_with_zero() {
+        assert_eq!(ones_complement_sum(&[0x12, 0x34, 0x56]), 0x6834);
+        assert_eq!(ones_complement_sum(&[]), 0);
+    }
+
+    #[test]
+    fn ipv4_header_with_valid_checksum_sums_to_ffff() {
+        let header = [0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11,
+                      0xb8, 0x61, 0xc0, 0xa8, 0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7];
+        assert_eq!(ones_complement_sum(&header), 0xffff);
+        let without_checksum = ChecksumBuilder::new().write(&header[..10]).write(&header[12..]);
+        assert_eq!(without_checksum.finish(), 0xb861);
+    }
+}
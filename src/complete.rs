@@ -0,0 +1,131 @@
+//! Complete (non-streaming) variants of the top-level parsers
+//!
+//! The parsers elsewhere in the crate are streaming: a truncated buffer
+//! yields `IResult::Incomplete` so the caller can wait for more data. The
+//! functions here share their names and signatures but treat the input as
+//! the whole packet, turning `Incomplete` into an `Error` with
+//! `ErrorKind::Complete`.
+
+use alloc::vec::Vec;
+
+use nom::IResult;
+
+use ah::{self, AuthHeader};
+use arp::{self, ArpPacket};
+use bgp::{self, BgpHeader, BgpMessage};
+use cdp::{self, CdpPacket};
+use dhcp::{self, DhcpPacket};
+use dhcpv6::{self, Dhcpv6Message};
+use dns::{self, DnsHeader, DnsMessage};
+use eapol::{self, EapolFrame};
+use esp::{self, EspHeader};
+use ethernet::{self, EthernetFrame, Ethernet8023Frame, LlcHeader, VlanEthernetFrame};
+use gtp::{self, GtpU};
+use http::{self, HttpRequest};
+use icmp::{self, IcmpHeader};
+use icmpv6::{self, Icmpv6Header, NdpOption};
+use ieee80211::{self, Ieee80211Header, RadiotapHeader};
+use igmp::{self, IgmpMessage};
+use ipv4::{self, IPv4Header};
+use ipv6::{self, IPv6Header, Ipv6FragmentHeader};
+use lacp::{self, LacpPdu};
+use lldp::{self, LldpTlv};
+use modbus::{self, ModbusAdu};
+use mpls::{self, MplsLabel};
+use ospf::{self, OspfHeader, OspfPacket};
+use ptp::{self, PtpHeader, PtpMessage};
+use rtp::{self, RtpHeader};
+use sctp::{self, SctpChunk, SctpHeader};
+use sll::{self, SllHeader};
+use someip::{self, SomeIpHeader, SomeIpMessage};
+use stp::{self, Bpdu};
+use tcp::{self, TcpHeader};
+use tls::{self, TlsHandshakeHeader, TlsRecordHeader};
+use udp::{self, UdpHeader, UdpLiteHeader};
+
+macro_rules! complete_parser {
+    ($name:ident, $parser:path, $out:ty) => (
+        pub fn $name(i: &[u8]) -> IResult<&[u8], $out> {
+            complete!(i, $parser)
+        }
+    )
+}
+
+complete_parser!(parse_ah_header, ah::parse_ah_header, AuthHeader);
+complete_parser!(parse_arp_pkt, arp::parse_arp_pkt, ArpPacket);
+complete_parser!(parse_bgp_header, bgp::parse_bgp_header, BgpHeader);
+complete_parser!(parse_bgp_message, bgp::parse_bgp_message, BgpMessage);
+complete_parser!(parse_cdp_packet, cdp::parse_cdp_packet, CdpPacket);
+complete_parser!(parse_dhcp_packet, dhcp::parse_dhcp_packet, DhcpPacket);
+complete_parser!(parse_dhcpv6_message, dhcpv6::parse_dhcpv6_message, Dhcpv6Message);
+complete_parser!(parse_dns_header, dns::parse_dns_header, DnsHeader);
+complete_parser!(parse_dns_message, dns::parse_dns_message, DnsMessage);
+complete_parser!(parse_eapol_frame, eapol::parse_eapol_frame, EapolFrame);
+complete_parser!(parse_esp_header, esp::parse_esp_header, EspHeader);
+complete_parser!(parse_ethernet_frame, ethernet::parse_ethernet_frame, EthernetFrame);
+complete_parser!(parse_vlan_ethernet_frame, ethernet::parse_vlan_ethernet_frame, VlanEthernetFrame);
+complete_parser!(parse_llc_snap, ethernet::parse_llc_snap, LlcHeader);
+complete_parser!(parse_ethernet_frame_8023, ethernet::parse_ethernet_frame_8023, Ethernet8023Frame);
+complete_parser!(parse_gtpu_header, gtp::parse_gtpu_header, GtpU);
+complete_parser!(parse_http_request, http::parse_http_request, HttpRequest);
+complete_parser!(parse_icmp_header, icmp::parse_icmp_header, IcmpHeader);
+complete_parser!(parse_icmpv6_header, icmpv6::parse_icmpv6_header, Icmpv6Header);
+complete_parser!(parse_ndp_options, icmpv6::parse_ndp_options, Vec<NdpOption>);
+complete_parser!(parse_radiotap_header, ieee80211::parse_radiotap_header, RadiotapHeader);
+complete_parser!(parse_ieee80211_header, ieee80211::parse_ieee80211_header, Ieee80211Header);
+complete_parser!(parse_igmp_message, igmp::parse_igmp_message, IgmpMessage);
+complete_parser!(parse_ipv4_header, ipv4::parse_ipv4_header, IPv4Header);
+complete_parser!(parse_ipv6_header, ipv6::parse_ipv6_header, IPv6Header);
+complete_parser!(parse_ipv6_fragment_header, ipv6::parse_ipv6_fragment_header, Ipv6FragmentHeader);
+complete_parser!(parse_lacp_pdu, lacp::parse_lacp_pdu, LacpPdu);
+complete_parser!(parse_lldp_tlvs, lldp::parse_lldp_tlvs, Vec<LldpTlv>);
+complete_parser!(parse_modbus_request, modbus::parse_modbus_request, ModbusAdu);
+complete_parser!(parse_modbus_response, modbus::parse_modbus_response, ModbusAdu);
+complete_parser!(parse_mpls_stack, mpls::parse_mpls_stack, Vec<MplsLabel>);
+complete_parser!(parse_ospf_header, ospf::parse_ospf_header, OspfHeader);
+complete_parser!(parse_ospf_packet, ospf::parse_ospf_packet, OspfPacket);
+complete_parser!(parse_ptp_header, ptp::parse_ptp_header, PtpHeader);
+complete_parser!(parse_ptp_message, ptp::parse_ptp_message, PtpMessage);
+complete_parser!(parse_rtp_header, rtp::parse_rtp_header, RtpHeader);
+complete_parser!(parse_sctp_header, sctp::parse_sctp_header, SctpHeader);
+complete_parser!(parse_sctp_chunks, sctp::parse_sctp_chunks, Vec<SctpChunk>);
+complete_parser!(parse_sll_header, sll::parse_sll_header, SllHeader);
+complete_parser!(parse_someip_header, someip::parse_someip_header, SomeIpHeader);
+complete_parser!(parse_someip_message, someip::parse_someip_message, SomeIpMessage);
+complete_parser!(parse_bpdu, stp::parse_bpdu, Bpdu);
+complete_parser!(parse_tcp_header, tcp::parse_tcp_header, TcpHeader);
+complete_parser!(parse_tcp_header_strict, tcp::parse_tcp_header_strict, TcpHeader);
+complete_parser!(parse_tls_record_header, tls::parse_tls_record_header, TlsRecordHeader);
+complete_parser!(parse_tls_handshake_header, tls::parse_tls_handshake_header, TlsHandshakeHeader);
+complete_parser!(parse_udp_header, udp::parse_udp_header, UdpHeader);
+complete_parser!(parse_udplite_header, udp::parse_udplite_header, UdpLiteHeader);
+
+pub fn parse_tcp_header_raw(i: &[u8]) -> IResult<&[u8], (TcpHeader, &[u8])> {
+    complete!(i, tcp::parse_tcp_header_raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_ethernet_frame;
+    use ethernet;
+    use nom::{IResult, Err, ErrorKind};
+
+    #[test]
+    fn truncated_ethernet_frame_is_an_error() {
+        let bytes = [0x00, 0x23, 0x54, 0x07, 0x93, 0x6c, 0x00, 0x1b];
+        match ethernet::parse_ethernet_frame(&bytes) {
+            IResult::Incomplete(_) => {}
+            other => panic!("expected Incomplete when streaming, got {:?}", other),
+        }
+        assert_eq!(parse_ethernet_frame(&bytes),
+                   IResult::Error(Err::Position(ErrorKind::Complete, &bytes[..])));
+    }
+
+    #[test]
+    fn whole_ethernet_frame_parses() {
+        let bytes = [0x00, 0x23, 0x54, 0x07, 0x93, 0x6c,
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x08, 0x00];
+        assert_eq!(parse_ethernet_frame(&bytes), ethernet::parse_ethernet_frame(&bytes));
+    }
+}
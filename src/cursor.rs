@@ -0,0 +1,112 @@
+//! Cursor-style variants of the top-level parsers
+//!
+//! Each function parses from the front of a borrowed slice and, on success,
+//! advances the slice past what it consumed, so that layers can be parsed in
+//! turn from a single cursor:
+//!
+//! ```
+//! # use pktparse::cursor::{parse_ethernet_frame, parse_ipv4_header};
+//! # fn f(mut buf: &[u8]) -> Result<(), pktparse::error::PktError> {
+//! let eth = parse_ethernet_frame(&mut buf)?;
+//! let ip = parse_ipv4_header(&mut buf)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! On error the cursor is left where it was.
+
+use alloc::vec::Vec;
+
+use ah::AuthHeader;
+use arp::ArpPacket;
+use bgp::{BgpHeader, BgpMessage};
+use cdp::CdpPacket;
+use dhcp::DhcpPacket;
+use dhcpv6::Dhcpv6Message;
+use dns::{DnsHeader, DnsMessage};
+use eapol::EapolFrame;
+use error::PktError;
+use esp::EspHeader;
+use ethernet::{EthernetFrame, Ethernet8023Frame, LlcHeader, VlanEthernetFrame};
+use gtp::GtpU;
+use http::HttpRequest;
+use icmp::IcmpHeader;
+use icmpv6::{Icmpv6Header, NdpOption};
+use ieee80211::{Ieee80211Header, RadiotapHeader};
+use igmp::IgmpMessage;
+use ipv4::IPv4Header;
+use ipv6::{IPv6Header, Ipv6FragmentHeader};
+use lacp::LacpPdu;
+use lldp::LldpTlv;
+use modbus::ModbusAdu;
+use mpls::MplsLabel;
+use ospf::{OspfHeader, OspfPacket};
+use result;
+use ptp::{PtpHeader, PtpMessage};
+use rtp::RtpHeader;
+use sctp::{SctpChunk, SctpHeader};
+use sll::SllHeader;
+use someip::{SomeIpHeader, SomeIpMessage};
+use stp::Bpdu;
+use tcp::TcpHeader;
+use tls::{TlsHandshakeHeader, TlsRecordHeader};
+use udp::{UdpHeader, UdpLiteHeader};
+
+macro_rules! cursor_parser {
+    ($name:ident, $out:ty) => (
+        pub fn $name(cursor: &mut &[u8]) -> Result<$out, PktError> {
+            let (rest, value) = result::$name(cursor)?;
+            *cursor = rest;
+            Ok(value)
+        }
+    )
+}
+
+cursor_parser!(parse_ah_header, AuthHeader);
+cursor_parser!(parse_arp_pkt, ArpPacket);
+cursor_parser!(parse_bgp_header, BgpHeader);
+cursor_parser!(parse_bgp_message, BgpMessage);
+cursor_parser!(parse_cdp_packet, CdpPacket);
+cursor_parser!(parse_dhcp_packet, DhcpPacket);
+cursor_parser!(parse_dhcpv6_message, Dhcpv6Message);
+cursor_parser!(parse_dns_header, DnsHeader);
+cursor_parser!(parse_dns_message, DnsMessage);
+cursor_parser!(parse_eapol_frame, EapolFrame);
+cursor_parser!(parse_esp_header, EspHeader);
+cursor_parser!(parse_ethernet_frame, EthernetFrame);
+cursor_parser!(parse_vlan_ethernet_frame, VlanEthernetFrame);
+cursor_parser!(parse_ethernet_frame_8023, Ethernet8023Frame);
+cursor_parser!(parse_llc_snap, LlcHeader);
+cursor_parser!(parse_gtpu_header, GtpU);
+cursor_parser!(parse_http_request, HttpRequest);
+cursor_parser!(parse_icmp_header, IcmpHeader);
+cursor_parser!(parse_icmpv6_header, Icmpv6Header);
+cursor_parser!(parse_ndp_options, Vec<NdpOption>);
+cursor_parser!(parse_radiotap_header, RadiotapHeader);
+cursor_parser!(parse_ieee80211_header, Ieee80211Header);
+cursor_parser!(parse_igmp_message, IgmpMessage);
+cursor_parser!(parse_ipv4_header, IPv4Header);
+cursor_parser!(parse_ipv6_header, IPv6Header);
+cursor_parser!(parse_ipv6_fragment_header, Ipv6FragmentHeader);
+cursor_parser!(parse_lacp_pdu, LacpPdu);
+cursor_parser!(parse_lldp_tlvs, Vec<LldpTlv>);
+cursor_parser!(parse_modbus_request, ModbusAdu);
+cursor_parser!(parse_modbus_response, ModbusAdu);
+cursor_parser!(parse_mpls_stack, Vec<MplsLabel>);
+cursor_parser!(parse_ospf_header, OspfHeader);
+cursor_parser!(parse_ospf_packet, OspfPacket);
+cursor_parser!(parse_ptp_header, PtpHeader);
+cursor_parser!(parse_ptp_message, PtpMessage);
+cursor_parser!(parse_rtp_header, RtpHeader);
+cursor_parser!(parse_sctp_header, SctpHeader);
+cursor_parser!(parse_sctp_chunks, Vec<SctpChunk>);
+cursor_parser!(parse_sll_header, SllHeader);
+cursor_parser!(parse_someip_header, SomeIpHeader);
+cursor_parser!(parse_someip_message, SomeIpMessage);
+cursor_parser!(parse_bpdu, Bpdu);
+cursor_parser!(parse_tcp_header, TcpHeader);
+cursor_parser!(parse_tcp_header_strict, TcpHeader);
+cursor_parser!(parse_tls_record_header, TlsRecordHeader);
+cursor_parser!(parse_tls_handshake_header, TlsHandshakeHeader);
+cursor_parser!(parse_udp_header, UdpHeader);
+cursor_parser!(parse_udplite_header, UdpLiteHeader);
@@ -0,0 +1,223 @@
+//! Handles parsing of DHCP (BOOTP) packets
+
+use alloc::vec::Vec;
+
+use nom::{IResult, be_u8, be_u16, be_u32};
+
+use ipv4::IPv4Address;
+use options::for_each_option;
+
+pub const MAGIC_COOKIE: [u8; 4] = [0x63, 0x82, 0x53, 0x63];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DhcpMessageType {
+    Discover,
+    Offer,
+    Request,
+    Decline,
+    Ack,
+    Nak,
+    Release,
+    Inform,
+    Other(u8),
+}
+#[derive(Debug, PartialEq, Eq)]
+pub enum DhcpOption {
+    Pad,
+    MessageType(DhcpMessageType),
+    RequestedIpAddress(IPv4Address),
+    End,
+    Unknown {
+        code: u8,
+        data: Vec<u8>,
+    },
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct DhcpPacket {
+    pub op: u8,
+    pub htype: u8,
+    pub hlen: u8,
+    pub hops: u8,
+    pub xid: u32,
+    pub secs: u16,
+    pub flags: u16,
+    pub ciaddr: IPv4Address,
+    pub yiaddr: IPv4Address,
+    pub siaddr: IPv4Address,
+    pub giaddr: IPv4Address,
+    /// Client hardware address, of which the first `hlen` bytes are used
+    pub chaddr: [u8; 16],
+    pub options: Vec<DhcpOption>,
+}
+
+impl DhcpPacket {
+    /// The broadcast bit asks the server to broadcast its reply
+    pub fn broadcast(&self) -> bool {
+        self.flags & 0x8000 == 0x8000
+    }
+}
+
+impl From<u8> for DhcpMessageType {
+    fn from(raw: u8) -> Self {
+        match raw {
+            1 => DhcpMessageType::Discover,
+            2 => DhcpMessageType::Offer,
+            3 => DhcpMessageType::Request,
+            4 => DhcpMessageType::Decline,
+            5 => DhcpMessageType::Ack,
+            6 => DhcpMessageType::Nak,
+            7 => DhcpMessageType::Release,
+            8 => DhcpMessageType::Inform,
+            other => DhcpMessageType::Other(other),
+        }
+    }
+}
+
+fn to_ipv4_address(i: &[u8]) -> IPv4Address {
+    IPv4Address(*array_ref![i, 0, 4])
+}
+
+fn to_dhcp_option(code: u8, data: &[u8]) -> DhcpOption {
+    match (code, data.len()) {
+        (53, 1) => DhcpOption::MessageType(DhcpMessageType::from(data[0])),
+        (50, 4) => DhcpOption::RequestedIpAddress(to_ipv4_address(data)),
+        _ => DhcpOption::Unknown { code, data: data.to_vec() },
+    }
+}
+
+named!(address<&[u8], IPv4Address>, map!(take!(4), to_ipv4_address));
+named!(hardware_address<&[u8], [u8; 16]>, map!(take!(16), |i: &[u8]| *array_ref![i, 0, 16]));
+
+named!(dhcp_option<&[u8], DhcpOption>, switch!(be_u8,
+    0 => value!(DhcpOption::Pad) |
+    255 => value!(DhcpOption::End) |
+    code => map!(length_bytes!(be_u8), |data| to_dhcp_option(code, data))
+));
+
+/// Parses the options following the magic cookie, up to and including the
+/// end option. Anything after the end option is padding.
+fn dhcp_options(i: &[u8]) -> IResult<&[u8], Vec<DhcpOption>> {
+    let mut options = Vec::new();
+    for_each_option(i, dhcp_option, |option, rest| {
+        let next = if option == DhcpOption::End { &rest[rest.len()..] } else { rest };
+        options.push(option);
+        Ok(next)
+    }).map(|()| options)
+}
+
+named!(dhcp_packet<&[u8], DhcpPacket>, chain!(
+    op: be_u8 ~
+    htype: be_u8 ~
+    hlen: be_u8 ~
+    hops: be_u8 ~
+    xid: be_u32 ~
+    secs: be_u16 ~
+    flags: be_u16 ~
+    ciaddr: address ~
+    yiaddr: address ~
+    siaddr: address ~
+    giaddr: address ~
+    chaddr: hardware_address ~
+    take!(192) ~ // sname and file
+    tag!(MAGIC_COOKIE) ~
+    options: dhcp_options,
+    || DhcpPacket {
+        op,
+        htype,
+        hlen,
+        hops,
+        xid,
+        secs,
+        flags,
+        ciaddr,
+        yiaddr,
+        siaddr,
+        giaddr,
+        chaddr,
+        options,
+    }
+));
+
+/// Parses a DHCP packet from a UDP payload. The options run to the end of
+/// the input.
+pub fn parse_dhcp_packet(i: &[u8]) -> IResult<&[u8], DhcpPacket> {
+    dhcp_packet(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ipv4::IPv4Address;
+    use nom::IResult;
+    const EMPTY_SLICE: &[u8] = &[];
+
+    fn discover() -> Vec<u8> {
+        let mut bytes = vec![0x01, 0x01, 0x06, 0x00, /* Op, htype, hlen, hops */
+                             0x39, 0x03, 0xf3, 0x26, /* Transaction ID */
+                             0x00, 0x00, 0x80, 0x00, /* Secs, flags */
+                             0x00, 0x00, 0x00, 0x00, /* Client IP */
+                             0x00, 0x00, 0x00, 0x00, /* Your IP */
+                             0x00, 0x00, 0x00, 0x00, /* Server IP */
+                             0x00, 0x00, 0x00, 0x00, /* Gateway IP */
+                             0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42, 0x00, 0x00,
+                             0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00 /* Client hardware address */];
+        bytes.extend_from_slice(&[0; 192]); /* Server name and boot file */
+        bytes.extend_from_slice(&MAGIC_COOKIE);
+        bytes.extend_from_slice(&[0x35, 0x01, 0x01, /* Message type: discover */
+                                  0x32, 0x04, 0xc0, 0xa8, 0x00, 0x0a, /* Requested IP */
+                                  0x37, 0x02, 0x01, 0x03, /* Parameter request list */
+                                  0xff, /* End */
+                                  0x00, 0x00, 0x00 /* Padding */]);
+        bytes
+    }
+
+    #[test]
+    fn dhcp_discover_works() {
+        let bytes = discover();
+        let expectation = DhcpPacket {
+            op: 1,
+            htype: 1,
+            hlen: 6,
+            hops: 0,
+            xid: 0x3903f326,
+            secs: 0,
+            flags: 0x8000,
+            ciaddr: IPv4Address([0, 0, 0, 0]),
+            yiaddr: IPv4Address([0, 0, 0, 0]),
+            siaddr: IPv4Address([0, 0, 0, 0]),
+            giaddr: IPv4Address([0, 0, 0, 0]),
+            chaddr: [0x00, 0x0b, 0x82, 0x01, 0xfc, 0x42, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            options: vec![
+                DhcpOption::MessageType(DhcpMessageType::Discover),
+                DhcpOption::RequestedIpAddress(IPv4Address([192, 168, 0, 10])),
+                DhcpOption::Unknown { code: 55, data: vec![1, 3] },
+                DhcpOption::End,
+            ],
+        };
+        let parsed = parse_dhcp_packet(&bytes);
+        assert_eq!(parsed, IResult::Done(EMPTY_SLICE, expectation));
+        if let IResult::Done(_, packet) = parsed {
+            assert!(packet.broadcast());
+        }
+    }
+
+    #[test]
+    fn dhcp_bad_magic_cookie_fails() {
+        let mut bytes = discover();
+        bytes[236] = 0x00;
+        match parse_dhcp_packet(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn dhcp_truncated_option_fails() {
+        let mut bytes = discover();
+        bytes.truncate(242);
+        match parse_dhcp_packet(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+}
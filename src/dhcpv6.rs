@@ -0,0 +1,192 @@
+//! Handles parsing of DHCPv6 messages
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use nom::{IResult, Err, ErrorKind, be_u8, be_u16};
+
+use options::for_each_option;
+
+/// The UDP port clients listen on
+pub const DHCPV6_CLIENT_PORT: u16 = 546;
+/// The UDP port servers and relay agents listen on
+pub const DHCPV6_SERVER_PORT: u16 = 547;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Dhcpv6MessageType {
+    Solicit,
+    Advertise,
+    Request,
+    Confirm,
+    Renew,
+    Rebind,
+    Reply,
+    Release,
+    Decline,
+    Reconfigure,
+    InformationRequest,
+    RelayForward,
+    RelayReply,
+    Other(u8),
+}
+#[derive(Debug, PartialEq, Eq)]
+pub enum Dhcpv6Option {
+    /// The client's DUID
+    ClientId(Vec<u8>),
+    /// The server's DUID
+    ServerId(Vec<u8>),
+    /// Identity Association for Non-temporary Addresses
+    IaNa {
+        iaid: u32,
+        t1: u32,
+        t2: u32,
+        options: Vec<Dhcpv6Option>,
+    },
+    StatusCode {
+        code: u16,
+        message: String,
+    },
+    /// An option code this crate does not decode, with its data
+    Unknown {
+        code: u16,
+        data: Vec<u8>,
+    },
+}
+/// A message exchanged between a client and a server. Relay agent messages
+/// have a different layout and are not parsed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Dhcpv6Message {
+    pub message_type: Dhcpv6MessageType,
+    /// The 24-bit transaction ID
+    pub transaction_id: u32,
+    pub options: Vec<Dhcpv6Option>,
+}
+
+impl From<u8> for Dhcpv6MessageType {
+    fn from(raw: u8) -> Self {
+        match raw {
+            1 => Dhcpv6MessageType::Solicit,
+            2 => Dhcpv6MessageType::Advertise,
+            3 => Dhcpv6MessageType::Request,
+            4 => Dhcpv6MessageType::Confirm,
+            5 => Dhcpv6MessageType::Renew,
+            6 => Dhcpv6MessageType::Rebind,
+            7 => Dhcpv6MessageType::Reply,
+            8 => Dhcpv6MessageType::Release,
+            9 => Dhcpv6MessageType::Decline,
+            10 => Dhcpv6MessageType::Reconfigure,
+            11 => Dhcpv6MessageType::InformationRequest,
+            12 => Dhcpv6MessageType::RelayForward,
+            13 => Dhcpv6MessageType::RelayReply,
+            other => Dhcpv6MessageType::Other(other),
+        }
+    }
+}
+
+fn read_u32(i: &[u8]) -> u32 {
+    u32::from_be_bytes(*array_ref![i, 0, 4])
+}
+
+// An IA_NA is only decoded at the top level, so that nested options cannot
+// recurse without bound
+fn to_dhcpv6_option(code: u16, data: &[u8], nested: bool) -> Option<Dhcpv6Option> {
+    match code {
+        1 => Some(Dhcpv6Option::ClientId(data.to_vec())),
+        2 => Some(Dhcpv6Option::ServerId(data.to_vec())),
+        3 if !nested && data.len() >= 12 => match dhcpv6_options(&data[12..], true) {
+            IResult::Done(_, options) => Some(Dhcpv6Option::IaNa {
+                iaid: read_u32(data),
+                t1: read_u32(&data[4..]),
+                t2: read_u32(&data[8..]),
+                options,
+            }),
+            _ => None,
+        },
+        13 if data.len() >= 2 => Some(Dhcpv6Option::StatusCode {
+            code: (data[0] as u16) << 8 | data[1] as u16,
+            message: String::from_utf8_lossy(&data[2..]).into_owned(),
+        }),
+        3 if !nested => None,
+        13 => None,
+        _ => Some(Dhcpv6Option::Unknown { code, data: data.to_vec() }),
+    }
+}
+
+fn dhcpv6_option(i: &[u8], nested: bool) -> IResult<&[u8], Dhcpv6Option> {
+    map_opt!(i,
+        chain!(
+            code: be_u16 ~
+            data: length_bytes!(be_u16),
+            || (code, data)
+        ),
+        |(code, data): (u16, &[u8])| to_dhcpv6_option(code, data, nested))
+}
+
+/// Parses options up to the end of the input
+fn dhcpv6_options(i: &[u8], nested: bool) -> IResult<&[u8], Vec<Dhcpv6Option>> {
+    let mut options = Vec::new();
+    for_each_option(i, |i| dhcpv6_option(i, nested), |option, rest| {
+        options.push(option);
+        Ok(rest)
+    }).map(|()| options)
+}
+
+named!(transaction_id<&[u8], u32>,
+    map!(take!(3), |i: &[u8]| (i[0] as u32) << 16 | (i[1] as u32) << 8 | i[2] as u32));
+
+/// Parses a DHCPv6 message from a UDP payload. The options run to the end
+/// of the input. Relay-forward and relay-reply messages are rejected.
+pub fn parse_dhcpv6_message(i: &[u8]) -> IResult<&[u8], Dhcpv6Message> {
+    let (left, message_type) = try_parse!(i, map!(be_u8, Dhcpv6MessageType::from));
+    if let Dhcpv6MessageType::RelayForward | Dhcpv6MessageType::RelayReply = message_type {
+        return IResult::Error(Err::Position(ErrorKind::Switch, i));
+    }
+    chain!(left,
+        transaction_id: transaction_id ~
+        options: apply!(dhcpv6_options, false),
+        || Dhcpv6Message { message_type, transaction_id, options })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::IResult;
+    const EMPTY_SLICE: &[u8] = &[];
+
+    #[test]
+    fn dhcpv6_solicit_works() {
+        let bytes = [0x01, 0x10, 0x08, 0x74, /* Solicit, transaction ID */
+                     0x00, 0x01, 0x00, 0x0e, /* Client ID */
+                     0x00, 0x01, 0x00, 0x01, 0x1c, 0x39, 0xcf, 0x88,
+                     0x08, 0x00, 0x27, 0xfe, 0x8f, 0x95, /* DUID-LLT */
+                     0x00, 0x08, 0x00, 0x02, 0x00, 0x00, /* Elapsed time */
+                     0x00, 0x03, 0x00, 0x12, /* IA_NA */
+                     0x27, 0xfe, 0x8f, 0x95, /* IAID */
+                     0x00, 0x00, 0x0e, 0x10, 0x00, 0x00, 0x15, 0x18, /* T1 3600s, T2 5400s */
+                     0x00, 0x0d, 0x00, 0x02, 0x00, 0x00 /* Status code: success */];
+        let expectation = Dhcpv6Message {
+            message_type: Dhcpv6MessageType::Solicit,
+            transaction_id: 0x100874,
+            options: vec![
+                Dhcpv6Option::ClientId(bytes[8..22].to_vec()),
+                Dhcpv6Option::Unknown { code: 8, data: vec![0x00, 0x00] },
+                Dhcpv6Option::IaNa {
+                    iaid: 0x27fe8f95,
+                    t1: 3600,
+                    t2: 5400,
+                    options: vec![Dhcpv6Option::StatusCode { code: 0, message: String::new() }],
+                },
+            ],
+        };
+        assert_eq!(parse_dhcpv6_message(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[test]
+    fn dhcpv6_option_overrunning_message_is_an_error() {
+        let bytes = [0x07, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x10, 0x00];
+        match parse_dhcpv6_message(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+}
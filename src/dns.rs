@@ -0,0 +1,238 @@
+//! Handles parsing of DNS messages
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use nom::{IResult, Err, ErrorKind, Needed, be_u16, be_u32};
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DnsHeader {
+    pub id: u16,
+    /// True for a response, false for a query
+    pub qr: bool,
+    pub opcode: u8,
+    pub aa: bool,
+    pub tc: bool,
+    pub rd: bool,
+    pub ra: bool,
+    pub rcode: u8,
+    pub qdcount: u16,
+    pub ancount: u16,
+    pub nscount: u16,
+    pub arcount: u16,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct DnsQuestion {
+    pub name: String,
+    pub qtype: u16,
+    pub qclass: u16,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct DnsResourceRecord {
+    pub name: String,
+    pub rtype: u16,
+    pub class: u16,
+    pub ttl: u32,
+    pub data: Vec<u8>,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct DnsMessage {
+    pub header: DnsHeader,
+    pub questions: Vec<DnsQuestion>,
+    pub answers: Vec<DnsResourceRecord>,
+}
+
+/// Upper bound on compression pointers followed for one name, so a
+/// pointer loop cannot hang the parser
+const MAX_POINTERS: usize = 64;
+
+named!(flags<&[u8], (u8, u8, u8, u8, u8, u8, u8, u8)>,
+    bits!(tuple!(
+        take_bits!(u8, 1),
+        take_bits!(u8, 4),
+        take_bits!(u8, 1),
+        take_bits!(u8, 1),
+        take_bits!(u8, 1),
+        take_bits!(u8, 1),
+        take_bits!(u8, 3),
+        take_bits!(u8, 4))));
+
+named!(dns_header<&[u8], DnsHeader>, chain!(
+    id: be_u16 ~
+    flags: flags ~
+    qdcount: be_u16 ~
+    ancount: be_u16 ~
+    nscount: be_u16 ~
+    arcount: be_u16,
+    || DnsHeader {
+        id,
+        qr: flags.0 == 1,
+        opcode: flags.1,
+        aa: flags.2 == 1,
+        tc: flags.3 == 1,
+        rd: flags.4 == 1,
+        ra: flags.5 == 1,
+        rcode: flags.7,
+        qdcount,
+        ancount,
+        nscount,
+        arcount,
+    }
+));
+
+/// Parses a possibly compressed domain name starting at `i`. Compression
+/// pointers are offsets from the start of `message`.
+fn dns_name<'a>(message: &'a [u8], i: &'a [u8]) -> IResult<&'a [u8], String> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut pos = i;
+    // Where parsing resumes once the name is read, set at the first pointer
+    let mut resume = None;
+    let mut pointers = 0;
+    loop {
+        let len = match pos.first() {
+            Some(&len) => len as usize,
+            None => return IResult::Incomplete(Needed::Unknown),
+        };
+        match len & 0xc0 {
+            0x00 if len == 0 => {
+                let left = resume.unwrap_or(&pos[1..]);
+                return IResult::Done(left, labels.join("."));
+            }
+            0x00 => {
+                if pos.len() < 1 + len {
+                    return IResult::Incomplete(Needed::Unknown);
+                }
+                labels.push(String::from_utf8_lossy(&pos[1..1 + len]).into_owned());
+                pos = &pos[1 + len..];
+            }
+            0xc0 => {
+                if pos.len() < 2 {
+                    return IResult::Incomplete(Needed::Unknown);
+                }
+                let offset = ((len & 0x3f) << 8) | pos[1] as usize;
+                pointers += 1;
+                if pointers > MAX_POINTERS || offset >= message.len() {
+                    return IResult::Error(Err::Position(ErrorKind::Custom(0), pos));
+                }
+                if resume.is_none() {
+                    resume = Some(&pos[2..]);
+                }
+                pos = &message[offset..];
+            }
+            _ => return IResult::Error(Err::Position(ErrorKind::Custom(0), pos)),
+        }
+    }
+}
+
+fn dns_question<'a>(message: &'a [u8], i: &'a [u8]) -> IResult<&'a [u8], DnsQuestion> {
+    chain!(i,
+        name: apply!(dns_name, message) ~
+        qtype: be_u16 ~
+        qclass: be_u16,
+        || DnsQuestion { name, qtype, qclass })
+}
+
+fn dns_resource_record<'a>(message: &'a [u8], i: &'a [u8]) -> IResult<&'a [u8], DnsResourceRecord> {
+    chain!(i,
+        name: apply!(dns_name, message) ~
+        rtype: be_u16 ~
+        class: be_u16 ~
+        ttl: be_u32 ~
+        data: length_bytes!(be_u16),
+        || DnsResourceRecord { name, rtype, class, ttl, data: data.to_vec() })
+}
+
+pub fn parse_dns_header(i: &[u8]) -> IResult<&[u8], DnsHeader> {
+    dns_header(i)
+}
+
+/// Parses a DNS message: the header, the question section and the answer
+/// section. `i` must start at the beginning of the message, since name
+/// compression pointers are relative to it.
+pub fn parse_dns_message(i: &[u8]) -> IResult<&[u8], DnsMessage> {
+    chain!(i,
+        header: dns_header ~
+        questions: count!(apply!(dns_question, i), header.qdcount as usize) ~
+        answers: count!(apply!(dns_resource_record, i), header.ancount as usize),
+        || DnsMessage { header, questions, answers })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::IResult;
+    const EMPTY_SLICE: &[u8] = &[];
+
+    #[test]
+    fn dns_query_works() {
+        let bytes = [0x1a, 0x2b, /* ID */
+                     0x01, 0x00, /* Flags: standard query, recursion desired */
+                     0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, /* Counts */
+                     0x07, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, /* example */
+                     0x03, 0x63, 0x6f, 0x6d, 0x00, /* com */
+                     0x00, 0x01, /* Type A */
+                     0x00, 0x01 /* Class IN */];
+        let expectation = DnsMessage {
+            header: DnsHeader {
+                id: 0x1a2b,
+                qr: false,
+                opcode: 0,
+                aa: false,
+                tc: false,
+                rd: true,
+                ra: false,
+                rcode: 0,
+                qdcount: 1,
+                ancount: 0,
+                nscount: 0,
+                arcount: 0,
+            },
+            questions: vec![DnsQuestion {
+                name: "example.com".to_string(),
+                qtype: 1,
+                qclass: 1,
+            }],
+            answers: vec![],
+        };
+        assert_eq!(parse_dns_message(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[test]
+    fn dns_response_with_compressed_name_works() {
+        let bytes = [0x1a, 0x2b, /* ID */
+                     0x81, 0x80, /* Flags: response, recursion desired and available */
+                     0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, /* Counts */
+                     0x03, 0x77, 0x77, 0x77, /* www */
+                     0x07, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, /* example */
+                     0x03, 0x63, 0x6f, 0x6d, 0x00, /* com */
+                     0x00, 0x01, 0x00, 0x01, /* Type A, class IN */
+                     0xc0, 0x0c, /* Pointer to the question name */
+                     0x00, 0x01, 0x00, 0x01, /* Type A, class IN */
+                     0x00, 0x00, 0x0e, 0x10, /* TTL */
+                     0x00, 0x04, 0x5d, 0xb8, 0xd8, 0x22 /* Address */];
+        if let IResult::Done(remaining, message) = parse_dns_message(&bytes) {
+            assert_eq!(remaining, EMPTY_SLICE);
+            assert!(message.header.qr);
+            assert!(message.header.ra);
+            assert_eq!(message.questions[0].name, "www.example.com");
+            assert_eq!(message.answers, vec![DnsResourceRecord {
+                name: "www.example.com".to_string(),
+                rtype: 1,
+                class: 1,
+                ttl: 3600,
+                data: vec![0x5d, 0xb8, 0xd8, 0x22],
+            }]);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn dns_name_rejects_pointer_loop() {
+        let bytes = [0xc0, 0x00];
+        match dns_name(&bytes, &bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+}
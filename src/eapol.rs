@@ -0,0 +1,207 @@
+//! Handles parsing of EAPOL (802.1X) frames
+//!
+//! EAPOL is the payload of an Ethernet frame with `EtherType::EAPOL`. Its
+//! EAP-Packet frames carry an EAP packet (RFC 3748), which is parsed too;
+//! the bodies of EAPOL-Key frames and of EAP methods are left raw.
+
+use alloc::vec::Vec;
+
+use nom::{IResult, Err, ErrorKind, be_u8, be_u16};
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct EapolFrame {
+    /// 1, 2 or 3, for 802.1X-2001, -2004 and -2010
+    pub version: u8,
+    /// Length of the body, which excludes any Ethernet padding
+    pub length: u16,
+    pub packet: EapolPacket,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub enum EapolPacket {
+    Eap(EapPacket),
+    Start,
+    Logoff,
+    /// The body of an EAPOL-Key frame, e.g. a WPA handshake message
+    Key(Vec<u8>),
+    /// A packet type this crate does not decode, with its body
+    Other {
+        packet_type: u8,
+        body: Vec<u8>,
+    },
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EapCode {
+    Request,
+    Response,
+    Success,
+    Failure,
+    Other(u8),
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EapType {
+    Identity,
+    Notification,
+    Nak,
+    Md5Challenge,
+    Tls,
+    Ttls,
+    Peap,
+    MsChapV2,
+    Other(u8),
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct EapPacket {
+    pub code: EapCode,
+    pub identifier: u8,
+    /// Length of the EAP packet, header included
+    pub length: u16,
+    /// Set for requests and responses only
+    pub eap_type: Option<EapType>,
+    /// The type data following `eap_type`, e.g. the identity in an
+    /// Identity response
+    pub data: Vec<u8>,
+}
+
+impl From<u8> for EapCode {
+    fn from(raw: u8) -> Self {
+        match raw {
+            1 => EapCode::Request,
+            2 => EapCode::Response,
+            3 => EapCode::Success,
+            4 => EapCode::Failure,
+            other => EapCode::Other(other),
+        }
+    }
+}
+
+impl From<u8> for EapType {
+    fn from(raw: u8) -> Self {
+        match raw {
+            1 => EapType::Identity,
+            2 => EapType::Notification,
+            3 => EapType::Nak,
+            4 => EapType::Md5Challenge,
+            13 => EapType::Tls,
+            21 => EapType::Ttls,
+            25 => EapType::Peap,
+            26 => EapType::MsChapV2,
+            other => EapType::Other(other),
+        }
+    }
+}
+
+/// Parses an EAP packet, which must fit in `i`
+fn eap_packet(i: &[u8]) -> IResult<&[u8], EapPacket> {
+    let (left, (code, identifier, length)) = try_parse!(i, tuple!(be_u8, be_u8, be_u16));
+    let code = EapCode::from(code);
+    let data = match (length as usize).checked_sub(4) {
+        Some(len) if len <= left.len() => &left[..len],
+        _ => return IResult::Error(Err::Position(ErrorKind::LengthValue, i)),
+    };
+    let (eap_type, data) = match code {
+        EapCode::Request | EapCode::Response => match data.split_first() {
+            Some((&t, data)) => (Some(EapType::from(t)), data),
+            None => return IResult::Error(Err::Position(ErrorKind::LengthValue, i)),
+        },
+        _ => (None, data),
+    };
+    IResult::Done(&left[length as usize - 4..],
+                  EapPacket { code, identifier, length, eap_type, data: data.to_vec() })
+}
+
+fn to_eapol_packet(packet_type: u8, body: &[u8]) -> Option<EapolPacket> {
+    match packet_type {
+        0 => match eap_packet(body) {
+            IResult::Done(_, eap) => Some(EapolPacket::Eap(eap)),
+            _ => None,
+        },
+        1 => Some(EapolPacket::Start),
+        2 => Some(EapolPacket::Logoff),
+        3 => Some(EapolPacket::Key(body.to_vec())),
+        _ => Some(EapolPacket::Other { packet_type, body: body.to_vec() }),
+    }
+}
+
+named!(eapol_frame<&[u8], EapolFrame>, map_opt!(
+    chain!(
+        version: be_u8 ~
+        packet_type: be_u8 ~
+        body: length_bytes!(be_u16),
+        || (version, packet_type, body)
+    ),
+    |(version, packet_type, body): (u8, u8, &[u8])| {
+        to_eapol_packet(packet_type, body)
+            .map(|packet| EapolFrame { version, length: body.len() as u16, packet })
+    }
+));
+
+/// Parses an EAPOL frame, leaving any Ethernet padding after its body in
+/// the remaining input
+pub fn parse_eapol_frame(i: &[u8]) -> IResult<&[u8], EapolFrame> {
+    eapol_frame(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethernet::{parse_ethernet_frame, EtherType};
+    use nom::IResult;
+
+    #[test]
+    fn eapol_start_works() {
+        let mut bytes = vec![0x01, 0x80, 0xc2, 0x00, 0x00, 0x03, /* Destination */
+                             0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b, /* Source */
+                             0x88, 0x8e, /* EAPOL */
+                             0x02, 0x01, 0x00, 0x00 /* 802.1X-2004, Start, length 0 */];
+        bytes.extend_from_slice(&[0; 42]);
+        let eapol = match parse_ethernet_frame(&bytes) {
+            IResult::Done(rest, frame) => {
+                assert_eq!(frame.ethertype, EtherType::EAPOL);
+                rest
+            }
+            other => panic!("expected a frame, got {:?}", other),
+        };
+        let expectation = EapolFrame { version: 2, length: 0, packet: EapolPacket::Start };
+        assert_eq!(parse_eapol_frame(eapol), IResult::Done(&[0; 42][..], expectation));
+    }
+
+    #[test]
+    fn eap_request_identity_works() {
+        let bytes = [0x02, 0x00, 0x00, 0x05, /* 802.1X-2004, EAP-Packet, length 5 */
+                     0x01, 0x01, 0x00, 0x05, 0x01, /* Request, id 1, length 5, Identity */
+                     0x00, 0x00];
+        let expectation = EapolFrame {
+            version: 2,
+            length: 5,
+            packet: EapolPacket::Eap(EapPacket {
+                code: EapCode::Request,
+                identifier: 1,
+                length: 5,
+                eap_type: Some(EapType::Identity),
+                data: vec![],
+            }),
+        };
+        assert_eq!(parse_eapol_frame(&bytes), IResult::Done(&[0, 0][..], expectation));
+    }
+
+    #[test]
+    fn eap_success_has_no_type_and_overlong_eap_is_an_error() {
+        let bytes = [0x01, 0x00, 0x00, 0x04, 0x03, 0x01, 0x00, 0x04];
+        match parse_eapol_frame(&bytes) {
+            IResult::Done(_, frame) => assert_eq!(frame.packet, EapolPacket::Eap(EapPacket {
+                code: EapCode::Success,
+                identifier: 1,
+                length: 4,
+                eap_type: None,
+                data: vec![],
+            })),
+            other => panic!("expected a frame, got {:?}", other),
+        }
+
+        let bytes = [0x01, 0x00, 0x00, 0x05, 0x02, 0x01, 0x00, 0x09, 0x01];
+        match parse_eapol_frame(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Crate-level error type for the `Result`-returning parsers
+
+use core::error::Error;
+use core::fmt;
+
+use nom::{self, ErrorKind, IResult, Needed};
+
+use ip::{IP_VERSION_MISMATCH, IPV4_IHL_TOO_SHORT};
+
+/// The protocol layer a parse error occurred in
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Layer {
+    Ethernet,
+    Vlan,
+    Radiotap,
+    Ieee80211,
+    Arp,
+    Eapol,
+    Cdp,
+    Lacp,
+    Lldp,
+    Ptp,
+    Mpls,
+    Ipv4,
+    Ipv6,
+    Ah,
+    Esp,
+    Icmp,
+    Icmpv6,
+    Igmp,
+    Tcp,
+    Udp,
+    Ospf,
+    Gtp,
+    Sctp,
+    Sll,
+    Stp,
+    Dns,
+    Dhcp,
+    Dhcpv6,
+    Rtp,
+    Tls,
+    Bgp,
+    Http,
+    Modbus,
+    SomeIp,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PktError {
+    /// The input ended early. Holds the number of bytes needed if known,
+    /// otherwise 0.
+    Incomplete(usize),
+    InvalidHeader {
+        layer: Layer,
+        reason: &'static str,
+    },
+    /// An IP protocol number with no parser in this crate. The header
+    /// parsers no longer return this; unknown numbers parse as
+    /// `IPProtocol::Other`.
+    UnsupportedProtocol(u8),
+    /// An ethertype with no parser in this crate. The header parsers no
+    /// longer return this; unknown values parse as `EtherType::Other`.
+    /// `FrameIterator` returns it for a frame whose length it cannot find.
+    UnsupportedEtherType(u16),
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl fmt::Display for PktError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PktError::Incomplete(0) => write!(f, "incomplete input"),
+            PktError::Incomplete(n) => write!(f, "incomplete input, {} bytes needed", n),
+            PktError::InvalidHeader { layer, reason } => write!(f, "invalid {} header: {}", layer, reason),
+            PktError::UnsupportedProtocol(p) => write!(f, "unsupported IP protocol {}", p),
+            PktError::UnsupportedEtherType(t) => write!(f, "unsupported ethertype 0x{:04x}", t),
+        }
+    }
+}
+
+impl Error for PktError {}
+
+fn reason(kind: &ErrorKind) -> &'static str {
+    match *kind {
+        ErrorKind::MapOpt | ErrorKind::Switch => "unrecognized field value",
+        ErrorKind::LengthValue => "length field overruns the header",
+        ErrorKind::Tag => "unexpected constant",
+        ErrorKind::ManyMN => "too many repeated elements",
+        ErrorKind::Complete => "truncated header",
+        ErrorKind::Custom(IP_VERSION_MISMATCH) => "IP version does not match the parser",
+        ErrorKind::Custom(IPV4_IHL_TOO_SHORT) => "IHL is shorter than the fixed header",
+        _ => "malformed header",
+    }
+}
+
+/// The error kind, whether or not a position was recorded with it
+fn error_kind(e: &nom::Err<&[u8]>) -> ErrorKind {
+    match *e {
+        nom::Err::Code(ref kind) |
+        nom::Err::Node(ref kind, _) |
+        nom::Err::Position(ref kind, _) |
+        nom::Err::NodePosition(ref kind, _, _) => kind.clone(),
+    }
+}
+
+/// Converts the result of a nom parser into a `PktError` based result
+pub fn from_iresult<T>(layer: Layer, result: IResult<&[u8], T>) -> Result<(&[u8], T), PktError> {
+    match result {
+        IResult::Done(rest, value) => Ok((rest, value)),
+        IResult::Incomplete(Needed::Size(n)) => Err(PktError::Incomplete(n)),
+        IResult::Incomplete(Needed::Unknown) => Err(PktError::Incomplete(0)),
+        IResult::Error(e) => {
+            Err(PktError::InvalidHeader { layer, reason: reason(&error_kind(&e)) })
+        }
+    }
+}
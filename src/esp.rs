@@ -0,0 +1,40 @@
+//! Handles parsing of IPsec ESP headers
+//!
+//! ESP runs directly over IP as `IPProtocol::ESP`. Everything after the
+//! header, the padding and trailer included, is encrypted.
+
+use nom::{IResult, be_u32};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct EspHeader {
+    /// Security Parameters Index, identifying the security association
+    pub spi: u32,
+    pub sequence: u32,
+}
+
+named!(esp_header<&[u8], EspHeader>, chain!(
+    spi: be_u32 ~
+    sequence: be_u32,
+    || EspHeader { spi, sequence }
+));
+
+/// Parses the SPI and sequence number, leaving the encrypted payload in the
+/// remaining input
+pub fn parse_esp_header(i: &[u8]) -> IResult<&[u8], EspHeader> {
+    esp_header(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_esp_header, EspHeader};
+    use nom::IResult;
+
+    #[test]
+    fn esp_header_works() {
+        let bytes = [0x00, 0x00, 0x10, 0x01, /* SPI */
+                     0x00, 0x00, 0x00, 0x2a, /* Sequence number */
+                     0x9e, 0x4b, 0x31, 0xd7, 0x02, 0xc6 /* Encrypted payload */];
+        let expectation = EspHeader { spi: 0x1001, sequence: 42 };
+        assert_eq!(parse_esp_header(&bytes), IResult::Done(&bytes[8..], expectation));
+    }
+}
@@ -1,55 +1,570 @@
 //! Handles parsing of Ethernet headers
 
-use nom::IResult;
+use core::error::Error;
+use core::fmt;
+use core::str::FromStr;
 
-#[derive(Debug, PartialEq, Eq)]
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use nom::{IResult, be_u8, be_u16};
+#[cfg(feature = "alloc")]
+use nom::{Err, ErrorKind};
+
+use error::{from_iresult, Layer, PktError};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct MacAddress(pub [u8; 6]);
-#[derive(Debug, PartialEq, Eq)]
+/// An EtherType this crate has a name for, with its value as the
+/// discriminant so that `as u16` is free
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[repr(u16)]
+pub enum KnownEtherType {
+    IPv4 = 0x0800,
+    ARP = 0x0806,
+    RARP = 0x8035,
+    IPv6 = 0x86DD,
+    MPLSuni = 0x8847,
+    MPLSmulti = 0x8848,
+    VLAN = 0x8100,
+    SlowProtocols = 0x8809,
+    EAPOL = 0x888E,
+    QinQ = 0x88A8,
+    LLDP = 0x88CC,
+    PTP = 0x88F7,
+    VLANdouble = 0x9100,
+}
+/// The EtherType field of a frame. Values without a name are kept as
+/// `Other` rather than rejected; build one from a number with `from_raw`,
+/// so that `Other` never holds the value of a `KnownEtherType`.
+#[derive(PartialEq, Eq, Clone, Copy, Hash)]
 pub enum EtherType {
-    IPv4,
-    ARP,
-    IPv6,
-    VLAN,
+    Known(KnownEtherType),
+    Other(u16),
+}
+/// Largest value of the EtherType field that is an 802.3 frame length
+pub const LANMAX: u16 = 0x05DC;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SnapHeader {
+    pub oui: [u8; 3],
+    pub protocol_id: u16,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct LlcHeader {
+    pub dsap: u8,
+    pub ssap: u8,
+    /// One byte for unnumbered frames, two (big-endian) for information
+    /// and supervisory frames
+    pub control: u16,
+    /// Present when DSAP and SSAP are 0xAA with an unnumbered information
+    /// control field
+    pub snap: Option<SnapHeader>,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct Ieee8023Frame {
+    pub source_mac: MacAddress,
+    pub dest_mac: MacAddress,
+    pub length: u16,
+    pub llc: LlcHeader,
 }
 #[derive(Debug, PartialEq, Eq)]
+pub enum Ethernet8023Frame {
+    EthernetII(EthernetFrame),
+    Ieee8023(Ieee8023Frame),
+}
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct EthernetFrame {
     pub source_mac: MacAddress,
     pub dest_mac: MacAddress,
     pub ethertype: EtherType,
 }
+/// The Tag Control Information of an 802.1Q tag
+#[derive(Debug, PartialEq, Eq)]
+pub struct VlanTag {
+    /// Priority Code Point
+    pub pcp: u8,
+    /// Drop Eligible Indicator
+    pub dei: bool,
+    pub vid: u16,
+}
+#[cfg(feature = "alloc")]
+#[derive(Debug, PartialEq, Eq)]
+pub struct VlanEthernetFrame {
+    pub source_mac: MacAddress,
+    pub dest_mac: MacAddress,
+    /// VLAN tags from the outermost inwards
+    pub tags: Vec<VlanTag>,
+    /// EtherType of the payload after the innermost tag
+    pub ethertype: EtherType,
+}
+
+/// Maximum number of stacked VLAN tags accepted by `parse_vlan_ethernet_frame`
+pub const MAX_VLAN_DEPTH: usize = 3;
+
+
+impl MacAddress {
+    pub fn is_broadcast(&self) -> bool {
+        self.0 == [0xff; 6]
+    }
+
+    /// True when the group bit (lowest bit of the first octet) is set,
+    /// which includes the broadcast address
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] & 0x01 == 0x01
+    }
+
+    pub fn is_unicast(&self) -> bool {
+        !self.is_multicast()
+    }
+
+    /// True when the second-lowest bit of the first octet is set
+    pub fn is_locally_administered(&self) -> bool {
+        self.0[0] & 0x02 == 0x02
+    }
+}
+
+impl fmt::Display for MacAddress {
+    /// Formats as lowercase colon-separated octets, e.g. `9c:5c:8e:90:ca:fc`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let m = &self.0;
+        write!(f, "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}", m[0], m[1], m[2], m[3], m[4], m[5])
+    }
+}
+
+impl fmt::Display for EthernetFrame {
+    /// A one-line summary, e.g. `00:1b:21:0f:91:9b -> 00:23:54:07:93:6c IPv4`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} -> {} {:?}", self.source_mac, self.dest_mac, self.ethertype)
+    }
+}
+
+impl fmt::UpperHex for MacAddress {
+    /// Formats as uppercase dash-separated octets, e.g. `9C-5C-8E-90-CA-FC`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let m = &self.0;
+        write!(f, "{:02X}-{:02X}-{:02X}-{:02X}-{:02X}-{:02X}", m[0], m[1], m[2], m[3], m[4], m[5])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseMacAddressError {
+    /// The string does not hold six separated octets
+    InvalidLength,
+    /// An octet is not a two-digit hexadecimal number
+    InvalidDigit,
+}
+
+impl fmt::Display for ParseMacAddressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseMacAddressError::InvalidLength => {
+                write!(f, "MAC address must have six colon- or dash-separated octets")
+            }
+            ParseMacAddressError::InvalidDigit => {
+                write!(f, "MAC address octet is not a two-digit hexadecimal number")
+            }
+        }
+    }
+}
+
+impl Error for ParseMacAddressError {}
+
+impl FromStr for MacAddress {
+    type Err = ParseMacAddressError;
+
+    /// Parses `aa:bb:cc:dd:ee:ff` or `aa-bb-cc-dd-ee-ff`
+    fn from_str(s: &str) -> Result<MacAddress, ParseMacAddressError> {
+        let separator = if s.contains('-') { '-' } else { ':' };
+        let mut mac = [0u8; 6];
+        let mut octets = s.split(separator);
+        for byte in mac.iter_mut() {
+            let octet = octets.next().ok_or(ParseMacAddressError::InvalidLength)?;
+            if !octet.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(ParseMacAddressError::InvalidDigit);
+            }
+            if octet.len() != 2 {
+                return Err(ParseMacAddressError::InvalidLength);
+            }
+            *byte = u8::from_str_radix(octet, 16).map_err(|_| ParseMacAddressError::InvalidDigit)?;
+        }
+        if octets.next().is_some() {
+            return Err(ParseMacAddressError::InvalidLength);
+        }
+        Ok(MacAddress(mac))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for MacAddress {
+    /// Writes `9c:5c:8e:90:ca:fc` for human-readable formats and raw octets otherwise
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for MacAddress {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<MacAddress, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = alloc::string::String::deserialize(deserializer)?;
+            s.parse().map_err(serde::de::Error::custom)
+        } else {
+            <[u8; 6]>::deserialize(deserializer).map(MacAddress)
+        }
+    }
+}
+
+impl KnownEtherType {
+    /// The conventional short name, e.g. `"IPv4"`
+    pub fn name(&self) -> &'static str {
+        match *self {
+            KnownEtherType::IPv4 => "IPv4",
+            KnownEtherType::ARP => "ARP",
+            KnownEtherType::RARP => "RARP",
+            KnownEtherType::IPv6 => "IPv6",
+            KnownEtherType::MPLSuni => "MPLS",
+            KnownEtherType::MPLSmulti => "MPLS multicast",
+            KnownEtherType::VLAN => "802.1Q",
+            KnownEtherType::SlowProtocols => "Slow Protocols",
+            KnownEtherType::EAPOL => "EAPOL",
+            KnownEtherType::QinQ => "802.1ad",
+            KnownEtherType::LLDP => "LLDP",
+            KnownEtherType::PTP => "PTP",
+            KnownEtherType::VLANdouble => "802.1Q double tag",
+        }
+    }
+}
+
+// Lets the known values be written, and matched on, as before the split
+#[allow(non_upper_case_globals)]
+impl EtherType {
+    pub const IPv4: EtherType = EtherType::Known(KnownEtherType::IPv4);
+    pub const ARP: EtherType = EtherType::Known(KnownEtherType::ARP);
+    pub const RARP: EtherType = EtherType::Known(KnownEtherType::RARP);
+    pub const IPv6: EtherType = EtherType::Known(KnownEtherType::IPv6);
+    pub const MPLSuni: EtherType = EtherType::Known(KnownEtherType::MPLSuni);
+    pub const MPLSmulti: EtherType = EtherType::Known(KnownEtherType::MPLSmulti);
+    pub const VLAN: EtherType = EtherType::Known(KnownEtherType::VLAN);
+    pub const SlowProtocols: EtherType = EtherType::Known(KnownEtherType::SlowProtocols);
+    pub const EAPOL: EtherType = EtherType::Known(KnownEtherType::EAPOL);
+    pub const QinQ: EtherType = EtherType::Known(KnownEtherType::QinQ);
+    pub const LLDP: EtherType = EtherType::Known(KnownEtherType::LLDP);
+    pub const PTP: EtherType = EtherType::Known(KnownEtherType::PTP);
+    pub const VLANdouble: EtherType = EtherType::Known(KnownEtherType::VLANdouble);
+
+    /// Classifies an EtherType value
+    pub const fn from_raw(raw: u16) -> EtherType {
+        let known = match raw {
+            0x0800 => KnownEtherType::IPv4,
+            0x0806 => KnownEtherType::ARP,
+            0x8035 => KnownEtherType::RARP,
+            0x8100 => KnownEtherType::VLAN,
+            0x86DD => KnownEtherType::IPv6,
+            0x8809 => KnownEtherType::SlowProtocols,
+            0x8847 => KnownEtherType::MPLSuni,
+            0x8848 => KnownEtherType::MPLSmulti,
+            0x888E => KnownEtherType::EAPOL,
+            0x88A8 => KnownEtherType::QinQ,
+            0x88CC => KnownEtherType::LLDP,
+            0x88F7 => KnownEtherType::PTP,
+            0x9100 => KnownEtherType::VLANdouble,
+            _ => return EtherType::Other(raw),
+        };
+        EtherType::Known(known)
+    }
+
+    /// The EtherType value; for a known one, read straight from the
+    /// discriminant
+    pub const fn to_raw(&self) -> u16 {
+        match *self {
+            EtherType::Known(known) => known as u16,
+            EtherType::Other(raw) => raw,
+        }
+    }
+
+    /// The conventional short name, e.g. `"IPv4"`, or `"Unknown"`
+    pub fn name(&self) -> &'static str {
+        match *self {
+            EtherType::Known(ref known) => known.name(),
+            EtherType::Other(_) => "Unknown",
+        }
+    }
+
+    /// Whether this is the TPID of a VLAN tag, meaning the real ethertype
+    /// follows the tag and `parse_vlan_ethernet_frame` should be used
+    pub fn is_vlan(&self) -> bool {
+        matches!(*self, EtherType::VLAN | EtherType::QinQ | EtherType::VLANdouble)
+    }
+}
+
+impl From<u16> for EtherType {
+    fn from(raw: u16) -> EtherType {
+        EtherType::from_raw(raw)
+    }
+}
+
+impl From<KnownEtherType> for EtherType {
+    fn from(known: KnownEtherType) -> EtherType {
+        EtherType::Known(known)
+    }
+}
+
+impl fmt::Debug for EtherType {
+    /// Writes a known value by name, e.g. `IPv4`, and any other as
+    /// `Other(0x88b5)`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EtherType::Known(ref known) => fmt::Debug::fmt(known, f),
+            EtherType::Other(raw) => write!(f, "Other(0x{:04x})", raw),
+        }
+    }
+}
+
+impl PartialOrd for EtherType {
+    fn partial_cmp(&self, other: &EtherType) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for EtherType {
+    /// Orders by value, as the discriminants did before `Other` existed
+    fn cmp(&self, other: &EtherType) -> core::cmp::Ordering {
+        self.to_raw().cmp(&other.to_raw())
+    }
+}
 
-fn to_ethertype(i: u16) -> Option<EtherType> {
-    match i {
-        0x0800 => Some(EtherType::IPv4),
-        0x0806 => Some(EtherType::ARP),
-        0x8100 => Some(EtherType::VLAN),
-        0x86DD => Some(EtherType::IPv6),
-        _ => None,
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for EtherType {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<EtherType> {
+        if u.arbitrary()? {
+            Ok(EtherType::Known(u.arbitrary()?))
+        } else {
+            Ok(EtherType::from_raw(u.arbitrary()?))
+        }
+    }
+}
+
+impl From<EtherType> for u16 {
+    fn from(e: EtherType) -> u16 {
+        e.to_raw()
     }
 }
 
 fn to_mac_address(i: &[u8]) -> MacAddress {
-    MacAddress(array_ref![i, 0, 6].clone())
+    MacAddress(*array_ref![i, 0, 6])
 }
 
-named!(mac_address<&[u8], MacAddress>, map!(take!(6), to_mac_address));
-named!(ethertype<&[u8], EtherType>, map_opt!(u16!(true), to_ethertype));
+named!(pub mac_address<&[u8], MacAddress>, map!(take!(6), to_mac_address));
+named!(pub ethertype<&[u8], EtherType>, map!(be_u16, EtherType::from_raw));
 named!(ethernet_frame<&[u8], EthernetFrame>, chain!(
     dest_mac: mac_address ~
     src_mac: mac_address ~
     et: ethertype,
-    || EthernetFrame{source_mac: src_mac, dest_mac: dest_mac, ethertype: et}
+    || EthernetFrame{source_mac: src_mac, dest_mac, ethertype: et}
 ));
 
 pub fn parse_ethernet_frame(i: &[u8]) -> IResult<&[u8], EthernetFrame> {
     ethernet_frame(i)
 }
 
+named!(vlan_tag<&[u8], VlanTag>, map!(
+    bits!(tuple!(take_bits!(u8, 3), take_bits!(u8, 1), take_bits!(u16, 12))),
+    |(pcp, dei, vid): (u8, u8, u16)| VlanTag { pcp, dei: dei == 1, vid }
+));
+
+/// Parses an Ethernet frame carrying one or more 802.1Q/802.1ad VLAN tags.
+/// Frames with more than `MAX_VLAN_DEPTH` tags are rejected.
+#[cfg(feature = "alloc")]
+pub fn parse_vlan_ethernet_frame(i: &[u8]) -> IResult<&[u8], VlanEthernetFrame> {
+    parse_vlan_ethernet_frame_max_depth(i, MAX_VLAN_DEPTH)
+}
+
+/// Like `parse_vlan_ethernet_frame`, but rejecting frames with more than
+/// `max_depth` tags
+#[cfg(feature = "alloc")]
+pub fn parse_vlan_ethernet_frame_max_depth(i: &[u8], max_depth: usize)
+                                           -> IResult<&[u8], VlanEthernetFrame> {
+    let (mut left, (dest_mac, source_mac)) = try_parse!(i, pair!(mac_address, mac_address));
+    let mut tags = Vec::new();
+    loop {
+        let (rest, et) = try_parse!(left, ethertype);
+        match et {
+            et if et.is_vlan() => {
+                if tags.len() == max_depth {
+                    return IResult::Error(Err::Position(ErrorKind::ManyMN, left));
+                }
+                let (rest, tag) = try_parse!(rest, vlan_tag);
+                tags.push(tag);
+                left = rest;
+            }
+            ethertype => {
+                return IResult::Done(rest, VlanEthernetFrame { source_mac, dest_mac, tags, ethertype });
+            }
+        }
+    }
+}
+
+named!(snap_header<&[u8], SnapHeader>, chain!(
+    oui: take!(3) ~
+    protocol_id: be_u16,
+    || SnapHeader { oui: *array_ref![oui, 0, 3], protocol_id }
+));
+
+named!(llc_snap<&[u8], LlcHeader>, chain!(
+    dsap: be_u8 ~
+    ssap: be_u8 ~
+    control: be_u8 ~
+    control_ext: cond!(control & 0x03 != 0x03, be_u8) ~
+    snap: cond!(dsap == 0xAA && ssap == 0xAA && control == 0x03, snap_header),
+    || LlcHeader {
+        dsap,
+        ssap,
+        control: match control_ext {
+            Some(ext) => (control as u16) << 8 | ext as u16,
+            None => control as u16,
+        },
+        snap,
+    }
+));
+
+named!(ieee8023_frame<&[u8], Ieee8023Frame>, chain!(
+    dest_mac: mac_address ~
+    source_mac: mac_address ~
+    length: map_opt!(be_u16, |l: u16| if l <= LANMAX { Some(l) } else { None }) ~
+    llc: llc_snap,
+    || Ieee8023Frame { source_mac, dest_mac, length, llc }
+));
+
+/// Parses an 802.2 LLC header, followed by a SNAP header when present
+pub fn parse_llc_snap(i: &[u8]) -> IResult<&[u8], LlcHeader> {
+    llc_snap(i)
+}
+
+/// Parses either an Ethernet II frame or, when the EtherType field holds a
+/// length of at most `LANMAX`, an 802.3 frame with its LLC/SNAP header
+pub fn parse_ethernet_frame_8023(i: &[u8]) -> IResult<&[u8], Ethernet8023Frame> {
+    alt!(i,
+         map!(ieee8023_frame, Ethernet8023Frame::Ieee8023) |
+         map!(ethernet_frame, Ethernet8023Frame::EthernetII))
+}
+
+/// Shortest Ethernet frame, without the FCS; shorter frames are padded
+const MIN_FRAME_LEN: usize = 60;
+
+/// Iterator over Ethernet frames stored back to back in one buffer. Each item
+/// is a frame and its payload, which for a VLAN-tagged frame starts with the
+/// tags. The frame's length is taken from the length field of the payload
+/// after any VLAN tags: the IPv4 or IPv6 length, the ARP address sizes, or
+/// an 802.3 length. Other ethertypes give no length, so they are reported
+/// as `PktError::UnsupportedEtherType`. `PktError::Incomplete` counts the
+/// bytes needed after the Ethernet header. Iteration stops after the first
+/// error.
+///
+/// Padding cannot be told apart from the next frame, so the caller says
+/// how short frames are stored: `new` expects them padded to
+/// `MIN_FRAME_LEN` as on the wire, and `unpadded` expects them stored
+/// without padding.
+pub struct FrameIterator<'a> {
+    remaining: &'a [u8],
+    padded: bool,
+}
+
+impl<'a> FrameIterator<'a> {
+    pub fn new(buffer: &'a [u8]) -> FrameIterator<'a> {
+        FrameIterator { remaining: buffer, padded: true }
+    }
+
+    pub fn unpadded(buffer: &'a [u8]) -> FrameIterator<'a> {
+        FrameIterator { remaining: buffer, padded: false }
+    }
+
+    fn next_frame(&mut self) -> Result<(EthernetFrame, &'a [u8]), PktError> {
+        let input = self.remaining;
+        let (rest, frame) = from_iresult(Layer::Ethernet, ethernet_frame(input))?;
+        let mut ethertype = frame.ethertype;
+        let mut tags_len = 0;
+        while ethertype.is_vlan() {
+            if tags_len == 4 * MAX_VLAN_DEPTH {
+                return Err(PktError::InvalidHeader { layer: Layer::Vlan, reason: "too many VLAN tags" });
+            }
+            if rest.len() < tags_len + 4 {
+                return Err(PktError::Incomplete(tags_len + 4));
+            }
+            ethertype = EtherType::from((rest[tags_len + 2] as u16) << 8 | rest[tags_len + 3] as u16);
+            tags_len += 4;
+        }
+        let inner = &rest[tags_len..];
+        let needed = match ethertype {
+            EtherType::IPv4 => 4,
+            EtherType::IPv6 | EtherType::ARP => 6,
+            _ => 0,
+        };
+        if inner.len() < needed {
+            return Err(PktError::Incomplete(tags_len + needed));
+        }
+        let inner_len = match ethertype {
+            EtherType::IPv4 => {
+                let total_len = (inner[2] as usize) << 8 | inner[3] as usize;
+                let header_len = (inner[0] & 0x0f) as usize * 4;
+                if header_len < 20 || total_len < header_len {
+                    return Err(PktError::InvalidHeader {
+                        layer: Layer::Ipv4,
+                        reason: "total length is shorter than the header",
+                    });
+                }
+                total_len
+            }
+            EtherType::IPv6 => 40 + ((inner[4] as usize) << 8 | inner[5] as usize),
+            EtherType::ARP => 8 + 2 * (inner[4] as usize + inner[5] as usize),
+            EtherType::Other(len) if len <= LANMAX => len as usize,
+            _ => return Err(PktError::UnsupportedEtherType(u16::from(ethertype))),
+        };
+        let payload_len = tags_len + inner_len;
+        if rest.len() < payload_len {
+            return Err(PktError::Incomplete(payload_len));
+        }
+        let mut frame_len = input.len() - rest.len() + payload_len;
+        if self.padded {
+            frame_len = frame_len.max(MIN_FRAME_LEN.min(input.len()));
+        }
+        self.remaining = &input[frame_len..];
+        Ok((frame, &rest[..payload_len]))
+    }
+}
+
+impl<'a> Iterator for FrameIterator<'a> {
+    type Item = Result<(EthernetFrame, &'a [u8]), PktError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining.is_empty() {
+            return None;
+        }
+        let item = self.next_frame();
+        if item.is_err() {
+            self.remaining = &[];
+        }
+        Some(item)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{mac_address, ethertype, ethernet_frame, MacAddress, EtherType, EthernetFrame};
+    use super::{mac_address, ethertype, ethernet_frame, MacAddress, EtherType, KnownEtherType, EthernetFrame,
+                ParseMacAddressError, parse_ethernet_frame_8023, Ethernet8023Frame, Ieee8023Frame,
+                LlcHeader, SnapHeader, vlan_tag, VlanTag, FrameIterator};
+    #[cfg(feature = "alloc")]
+    use super::{parse_vlan_ethernet_frame, VlanEthernetFrame, parse_vlan_ethernet_frame_max_depth};
+    use error::PktError;
     use nom::IResult;
-    const EMPTY_SLICE: &'static [u8] = &[];
+    const EMPTY_SLICE: &[u8] = &[];
     #[test]
     fn mac_address_works() {
         let bytes = [0x9c, 0x5c, 0x8e, 0x90, 0xca, 0xfc];
@@ -68,8 +583,11 @@ mod tests {
 
     mk_ethertype_test!(ethertype_gets_ipv4_correct, [0x08, 0x00], EtherType::IPv4);
     mk_ethertype_test!(ethertype_gets_arp_correct, [0x08, 0x06], EtherType::ARP);
+    mk_ethertype_test!(ethertype_gets_rarp_correct, [0x80, 0x35], EtherType::RARP);
     mk_ethertype_test!(ethertype_gets_ipv6_correct, [0x86, 0xDD], EtherType::IPv6);
     mk_ethertype_test!(ethertype_gets_vlan_correct, [0x81, 0x00], EtherType::VLAN);
+    mk_ethertype_test!(ethertype_gets_qinq_correct, [0x88, 0xA8], EtherType::QinQ);
+    mk_ethertype_test!(ethertype_gets_vlandouble_correct, [0x91, 0x00], EtherType::VLANdouble);
 
     #[test]
     fn ethernet_frame_works() {
@@ -82,6 +600,389 @@ mod tests {
             dest_mac: MacAddress([0x00, 0x23, 0x54, 0x07, 0x93, 0x6c]),
             ethertype: EtherType::IPv4,
         };
+        assert_eq!(expectation.to_string(), "00:1b:21:0f:91:9b -> 00:23:54:07:93:6c IPv4");
         assert_eq!(ethernet_frame(&bytes), IResult::Done(EMPTY_SLICE, expectation));
     }
+
+    #[test]
+    fn ethertype_round_trips_through_u16() {
+        for &raw in &[0x0800, 0x0806, 0x8035, 0x8100, 0x86DD, 0x8847, 0x8848, 0x8809, 0x888E, 0x88A8, 0x88CC, 0x88F7, 0x9100] {
+            let ethertype = EtherType::from_raw(raw);
+            assert!(match ethertype { EtherType::Known(_) => true, EtherType::Other(_) => false });
+            assert_eq!(ethertype.to_raw(), raw);
+            assert_eq!(u16::from(ethertype), raw);
+        }
+    }
+
+    #[test]
+    fn ethertype_casts_to_its_value() {
+        const IPV6: u16 = EtherType::IPv6.to_raw();
+        assert_eq!(IPV6, 0x86DD);
+        assert_eq!(KnownEtherType::VLANdouble as u16, 0x9100);
+        assert_eq!(EtherType::from(0x9100), EtherType::VLANdouble);
+    }
+
+    #[test]
+    fn unknown_ethertype_is_kept_as_other() {
+        for &raw in &[0x0000, 0x0004, 0x88b5, 0xffff] {
+            let ethertype = EtherType::from_raw(raw);
+            assert_eq!(ethertype, EtherType::Other(raw));
+            assert_eq!(ethertype.to_raw(), raw);
+            assert!(!ethertype.is_vlan());
+        }
+        assert_eq!(format!("{:?}", EtherType::Other(0x88b5)), "Other(0x88b5)");
+
+        let bytes = [0x00, 0x23, 0x54, 0x07, 0x93, 0x6c,
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x88, 0xb5];
+        match ethernet_frame(&bytes) {
+            IResult::Done(_, frame) => assert_eq!(frame.ethertype, EtherType::Other(0x88b5)),
+            other => panic!("expected a frame, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ethertype_is_vlan() {
+        for &raw in &[0x8100, 0x88A8, 0x9100] {
+            assert!(EtherType::from_raw(raw).is_vlan());
+        }
+        assert!(!EtherType::IPv4.is_vlan());
+    }
+
+    #[test]
+    fn ethertype_names() {
+        assert_eq!(EtherType::IPv4.name(), "IPv4");
+        assert_eq!(EtherType::ARP.name(), "ARP");
+        assert_eq!(EtherType::QinQ.name(), "802.1ad");
+        assert_eq!(EtherType::Other(0x88b5).name(), "Unknown");
+    }
+
+    #[test]
+    fn mac_address_formats() {
+        let mac = MacAddress([0x9c, 0x5c, 0x8e, 0x90, 0xca, 0xfc]);
+        assert_eq!(mac.to_string(), "9c:5c:8e:90:ca:fc");
+        assert_eq!(format!("{:X}", mac), "9C-5C-8E-90-CA-FC");
+        assert_eq!(MacAddress([0, 0x1b, 0x21, 0x0f, 0x91, 0x9b]).to_string(), "00:1b:21:0f:91:9b");
+    }
+
+    #[test]
+    fn mac_address_from_str_works() {
+        let mac = MacAddress([0x9c, 0x5c, 0x8e, 0x90, 0xca, 0xfc]);
+        assert_eq!("9c:5c:8e:90:ca:fc".parse(), Ok(MacAddress([0x9c, 0x5c, 0x8e, 0x90, 0xca, 0xfc])));
+        assert_eq!("9C-5C-8E-90-CA-FC".parse(), Ok(MacAddress([0x9c, 0x5c, 0x8e, 0x90, 0xca, 0xfc])));
+        assert_eq!(mac.to_string().parse(), Ok(mac));
+    }
+
+    #[test]
+    fn mac_address_from_str_rejects_bad_input() {
+        assert_eq!("9c:5c:8e:90:ca".parse::<MacAddress>(), Err(ParseMacAddressError::InvalidLength));
+        assert_eq!("9c:5c:8e:90:ca:fc:00".parse::<MacAddress>(), Err(ParseMacAddressError::InvalidLength));
+        assert_eq!("9c:5c:8e:90:ca:fg".parse::<MacAddress>(), Err(ParseMacAddressError::InvalidDigit));
+        assert_eq!("9c:5c:8e:90:ca:+f".parse::<MacAddress>(), Err(ParseMacAddressError::InvalidDigit));
+    }
+
+    #[test]
+    fn mac_address_classification() {
+        let broadcast = MacAddress([0xff; 6]);
+        assert!(broadcast.is_broadcast());
+        assert!(broadcast.is_multicast());
+        assert!(!broadcast.is_unicast());
+
+        let multicast = MacAddress([0x01, 0x00, 0x5e, 0x00, 0x00, 0xfb]);
+        assert!(!multicast.is_broadcast());
+        assert!(multicast.is_multicast());
+        assert!(!multicast.is_unicast());
+        assert!(!multicast.is_locally_administered());
+
+        let unicast = MacAddress([0x9c, 0x5c, 0x8e, 0x90, 0xca, 0xfc]);
+        assert!(!unicast.is_broadcast());
+        assert!(!unicast.is_multicast());
+        assert!(unicast.is_unicast());
+        assert!(!unicast.is_locally_administered());
+
+        assert!(MacAddress([0x02, 0x42, 0xac, 0x11, 0x00, 0x02]).is_locally_administered());
+    }
+
+    #[test]
+    fn ethernet_frame_8023_parses_stp_bpdu() {
+        let bytes = [0x01, 0x80, 0xc2, 0x00, 0x00, 0x00, /* dest MAC */
+                     0x00, 0x1c, 0x0e, 0x87, 0x85, 0x04, /* src MAC */
+                     0x00, 0x26, /* Length */
+                     0x42, 0x42, 0x03, /* LLC */
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x1c, 0x0e, 0x87, 0x78,
+                     0x00, 0x00, 0x00, 0x00, 0x04, 0x80, 0x00, 0x00, 0x1c, 0x0e, 0x87, 0x85,
+                     0x00, 0x80, 0x04, 0x01, 0x00, 0x14, 0x00, 0x02, 0x00, 0x0f, 0x00 /* BPDU */];
+        let expectation = Ethernet8023Frame::Ieee8023(Ieee8023Frame {
+            source_mac: MacAddress([0x00, 0x1c, 0x0e, 0x87, 0x85, 0x04]),
+            dest_mac: MacAddress([0x01, 0x80, 0xc2, 0x00, 0x00, 0x00]),
+            length: 38,
+            llc: LlcHeader {
+                dsap: 0x42,
+                ssap: 0x42,
+                control: 0x03,
+                snap: None,
+            },
+        });
+        assert_eq!(parse_ethernet_frame_8023(&bytes), IResult::Done(&bytes[17..], expectation));
+    }
+
+    #[test]
+    fn ethernet_frame_8023_parses_snap() {
+        let bytes = [0x01, 0x00, 0x0c, 0xcc, 0xcc, 0xcc, /* dest MAC */
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b, /* src MAC */
+                     0x01, 0x7a, /* Length */
+                     0xaa, 0xaa, 0x03, /* LLC */
+                     0x00, 0x00, 0x0c, 0x20, 0x00 /* SNAP */];
+        let expectation = Ethernet8023Frame::Ieee8023(Ieee8023Frame {
+            source_mac: MacAddress([0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b]),
+            dest_mac: MacAddress([0x01, 0x00, 0x0c, 0xcc, 0xcc, 0xcc]),
+            length: 378,
+            llc: LlcHeader {
+                dsap: 0xaa,
+                ssap: 0xaa,
+                control: 0x03,
+                snap: Some(SnapHeader {
+                    oui: [0x00, 0x00, 0x0c],
+                    protocol_id: 0x2000,
+                }),
+            },
+        });
+        assert_eq!(parse_ethernet_frame_8023(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[test]
+    fn ethernet_frame_8023_parses_ethernet_ii() {
+        let bytes = [0x00, 0x23, 0x54, 0x07, 0x93, 0x6c,
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x08, 0x00];
+        let expectation = Ethernet8023Frame::EthernetII(EthernetFrame {
+            source_mac: MacAddress([0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b]),
+            dest_mac: MacAddress([0x00, 0x23, 0x54, 0x07, 0x93, 0x6c]),
+            ethertype: EtherType::IPv4,
+        });
+        assert_eq!(parse_ethernet_frame_8023(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn vlan_ethernet_frame_parses_double_tag() {
+        let bytes = [0x00, 0x23, 0x54, 0x07, 0x93, 0x6c, /* dest MAC */
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b, /* src MAC */
+                     0x88, 0xa8, 0x00, 0x64, /* Outer tag, VID 100 */
+                     0x81, 0x00, 0x00, 0xc8, /* Inner tag, VID 200 */
+                     0x08, 0x00, /* Ethertype */
+                     0x45, 0x00];
+        let expectation = VlanEthernetFrame {
+            source_mac: MacAddress([0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b]),
+            dest_mac: MacAddress([0x00, 0x23, 0x54, 0x07, 0x93, 0x6c]),
+            tags: vec![VlanTag { pcp: 0, dei: false, vid: 100 },
+                       VlanTag { pcp: 0, dei: false, vid: 200 }],
+            ethertype: EtherType::IPv4,
+        };
+        assert_eq!(parse_vlan_ethernet_frame(&bytes), IResult::Done(&bytes[22..], expectation));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn vlan_ethernet_frame_rejects_deep_nesting() {
+        let bytes = [0x00, 0x23, 0x54, 0x07, 0x93, 0x6c,
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x88, 0xa8, 0x00, 0x01,
+                     0x91, 0x00, 0x00, 0x02,
+                     0x81, 0x00, 0x00, 0x03,
+                     0x81, 0x00, 0x00, 0x04,
+                     0x08, 0x00];
+        match parse_vlan_ethernet_frame(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn vlan_ethernet_frame_max_depth_is_configurable() {
+        let bytes = [0x00, 0x23, 0x54, 0x07, 0x93, 0x6c,
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x88, 0xa8, 0x00, 0x01,
+                     0x81, 0x00, 0x00, 0x02,
+                     0x08, 0x00];
+        match parse_vlan_ethernet_frame_max_depth(&bytes, 1) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+        match parse_vlan_ethernet_frame_max_depth(&bytes, 2) {
+            IResult::Done(_, frame) => assert_eq!(frame.tags.len(), 2),
+            other => panic!("expected a frame, got {:?}", other),
+        }
+        match parse_vlan_ethernet_frame_max_depth(&bytes[..16], 8) {
+            IResult::Incomplete(_) => {}
+            other => panic!("expected incomplete input, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn vlan_tag_splits_tci() {
+        assert_eq!(vlan_tag(&[0x04, 0xd2]),
+                   IResult::Done(EMPTY_SLICE, VlanTag { pcp: 0, dei: false, vid: 1234 }));
+        assert_eq!(vlan_tag(&[0xb0, 0x64]),
+                   IResult::Done(EMPTY_SLICE, VlanTag { pcp: 5, dei: true, vid: 100 }));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn vlan_ethernet_frame_parses_priority() {
+        let bytes = [0x00, 0x23, 0x54, 0x07, 0x93, 0x6c,
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x81, 0x00, 0xa0, 0x0a, /* PCP 5, VID 10 */
+                     0x86, 0xdd];
+        let expectation = VlanEthernetFrame {
+            source_mac: MacAddress([0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b]),
+            dest_mac: MacAddress([0x00, 0x23, 0x54, 0x07, 0x93, 0x6c]),
+            tags: vec![VlanTag { pcp: 5, dei: false, vid: 10 }],
+            ethertype: EtherType::IPv6,
+        };
+        assert_eq!(parse_vlan_ethernet_frame(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn mac_address_serde_is_human_readable_only_for_json() {
+        let mac = MacAddress([0x9c, 0x5c, 0x8e, 0x90, 0xca, 0xfc]);
+        let json = ::serde_json::to_string(&mac).unwrap();
+        assert_eq!(json, "\"9c:5c:8e:90:ca:fc\"");
+        assert_eq!(::serde_json::from_str::<MacAddress>(&json).unwrap(), mac);
+
+        let encoded = ::bincode::serialize(&mac).unwrap();
+        assert_eq!(encoded, mac.0);
+        assert_eq!(::bincode::deserialize::<MacAddress>(&encoded).unwrap(), mac);
+    }
+
+    fn ipv4_frame(ip_len: u16, padding: usize) -> Vec<u8> {
+        let mut frame = vec![0x00, 0x23, 0x54, 0x07, 0x93, 0x6c,
+                             0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                             0x08, 0x00,
+                             0x45, 0x00];
+        frame.extend_from_slice(&ip_len.to_be_bytes());
+        frame.resize(14 + ip_len as usize + padding, 0);
+        frame
+    }
+
+    #[test]
+    fn frame_iterator_splits_concatenated_frames() {
+        let first = ipv4_frame(28, 18); /* Padded to the 60-byte minimum */
+        let second = ipv4_frame(48, 0);
+        let mut buffer = first.clone();
+        buffer.extend_from_slice(&second);
+
+        let mut frames = FrameIterator::new(&buffer);
+        let (frame, payload) = frames.next().unwrap().unwrap();
+        assert_eq!(frame.ethertype, EtherType::IPv4);
+        assert_eq!(payload, &first[14..42]);
+        let (_, payload) = frames.next().unwrap().unwrap();
+        assert_eq!(payload, &second[14..]);
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    fn frame_iterator_splits_unpadded_short_frames() {
+        let first = ipv4_frame(28, 0);
+        let second = ipv4_frame(20, 0);
+        let mut buffer = first.clone();
+        buffer.extend_from_slice(&second);
+        buffer.extend_from_slice(&first);
+
+        let mut frames = FrameIterator::unpadded(&buffer);
+        let (_, payload) = frames.next().unwrap().unwrap();
+        assert_eq!(payload, &first[14..]);
+        let (_, payload) = frames.next().unwrap().unwrap();
+        assert_eq!(payload, &second[14..]);
+        let (_, payload) = frames.next().unwrap().unwrap();
+        assert_eq!(payload, &first[14..]);
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    fn frame_iterator_keeps_zero_mac_after_unpadded_short_frame() {
+        let first = ipv4_frame(20, 0);
+        let mut second = ipv4_frame(28, 0);
+        second[..6].copy_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        let mut buffer = first.clone();
+        buffer.extend_from_slice(&second);
+
+        let mut frames = FrameIterator::unpadded(&buffer);
+        let (_, payload) = frames.next().unwrap().unwrap();
+        assert_eq!(payload, &first[14..]);
+        let (frame, payload) = frames.next().unwrap().unwrap();
+        assert_eq!(frame.dest_mac.0, [0x00, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(payload, &second[14..]);
+        assert!(frames.next().is_none());
+
+        let mut padded = first.clone();
+        padded.resize(60, 0);
+        padded.extend_from_slice(&second);
+        let mut frames = FrameIterator::new(&padded);
+        let (_, payload) = frames.next().unwrap().unwrap();
+        assert_eq!(payload, &first[14..]);
+        let (frame, payload) = frames.next().unwrap().unwrap();
+        assert_eq!(frame.dest_mac.0, [0x00, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(payload, &second[14..]);
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    fn frame_iterator_follows_vlan_tags() {
+        let inner = ipv4_frame(28, 0);
+        let mut first = inner[..12].to_vec();
+        first.extend_from_slice(&[0x81, 0x00, 0x00, 0x64]);
+        first.extend_from_slice(&inner[12..]);
+        let mut buffer = first.clone();
+        buffer.extend_from_slice(&inner);
+
+        let mut frames = FrameIterator::unpadded(&buffer);
+        let (frame, payload) = frames.next().unwrap().unwrap();
+        assert_eq!(frame.ethertype, EtherType::VLAN);
+        assert_eq!(payload, &first[14..]);
+        let (_, payload) = frames.next().unwrap().unwrap();
+        assert_eq!(payload, &inner[14..]);
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    fn frame_iterator_rejects_ethertype_without_length() {
+        let mut buffer = ipv4_frame(28, 0);
+        buffer[12..14].copy_from_slice(&[0x88, 0xb5]);
+        buffer.extend_from_slice(&ipv4_frame(28, 0));
+        let mut frames = FrameIterator::new(&buffer);
+        assert_eq!(frames.next().map(|f| f.map(|_| ())), Some(Err(PktError::UnsupportedEtherType(0x88b5))));
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    fn frame_iterator_reports_bytes_needed_for_truncated_header() {
+        let mut buffer = ipv4_frame(28, 0);
+        buffer.truncate(16);
+        let mut frames = FrameIterator::new(&buffer);
+        assert_eq!(frames.next().map(|f| f.map(|_| ())), Some(Err(PktError::Incomplete(4))));
+    }
+
+    #[test]
+    fn frame_iterator_rejects_ipv4_total_length_below_header() {
+        let mut buffer = ipv4_frame(60, 0);
+        buffer[17] = 19;
+        let mut frames = FrameIterator::new(&buffer);
+        match frames.next() {
+            Some(Err(PktError::InvalidHeader { .. })) => {}
+            other => panic!("expected an invalid header, got {:?}", other),
+        }
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    fn frame_iterator_stops_at_truncated_frame() {
+        let mut buffer = ipv4_frame(48, 0);
+        buffer.truncate(40);
+        let mut frames = FrameIterator::new(&buffer);
+        assert_eq!(frames.next().map(|f| f.map(|_| ())), Some(Err(PktError::Incomplete(48))));
+        assert!(frames.next().is_none());
+    }
 }
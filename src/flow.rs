@@ -0,0 +1,134 @@
+//! Handles keying of packets by their transport flow
+
+use core::net::IpAddr;
+
+use ip::IPProtocol;
+use ipv4::{IPv4Address, IPv4Header};
+use tcp::TcpHeader;
+
+/// The 5-tuple identifying one direction of a transport flow, suitable as a
+/// `HashMap` or `BTreeMap` key
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub struct FlowKey {
+    pub source_addr: IPv4Address,
+    pub dest_addr: IPv4Address,
+    pub source_port: u16,
+    pub dest_port: u16,
+    pub protocol: IPProtocol,
+}
+
+impl FlowKey {
+    pub fn from_tcp_ipv4(ip: &IPv4Header, tcp: &TcpHeader) -> FlowKey {
+        FlowKey {
+            source_addr: ip.source_addr,
+            dest_addr: ip.dest_addr,
+            source_port: tcp.source_port,
+            dest_port: tcp.dest_port,
+            protocol: ip.protocol,
+        }
+    }
+
+    /// The key of the opposite direction of the same flow
+    pub fn reversed(&self) -> FlowKey {
+        FlowKey {
+            source_addr: self.dest_addr,
+            dest_addr: self.source_addr,
+            source_port: self.dest_port,
+            dest_port: self.source_port,
+            protocol: self.protocol,
+        }
+    }
+}
+
+/// Like `FlowKey`, but for IPv4 or IPv6 and any IP protocol; the ports are
+/// `None` unless the protocol is TCP or UDP. See `PacketHeaders::five_tuple`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub struct FiveTuple {
+    pub src_ip: IpAddr,
+    pub dst_ip: IpAddr,
+    pub src_port: Option<u16>,
+    pub dst_port: Option<u16>,
+    pub protocol: IPProtocol,
+}
+
+impl FiveTuple {
+    /// The tuple of the opposite direction of the same flow
+    pub fn reversed(&self) -> FiveTuple {
+        FiveTuple {
+            src_ip: self.dst_ip,
+            dst_ip: self.src_ip,
+            src_port: self.dst_port,
+            dst_port: self.src_port,
+            protocol: self.protocol,
+        }
+    }
+
+    /// The same tuple for both directions of a flow: this one or its
+    /// reverse, whichever has the lower source endpoint
+    pub fn normalized(&self) -> FiveTuple {
+        if (self.src_ip, self.src_port) <= (self.dst_ip, self.dst_port) {
+            *self
+        } else {
+            self.reversed()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::FlowKey;
+    use ip::IPProtocol;
+    use ipv4::{IPv4Address, IPv4Header};
+    use tcp::TcpHeader;
+
+    fn ipv4_header(source: [u8; 4], dest: [u8; 4]) -> IPv4Header {
+        IPv4Header {
+            version: 4,
+            ihl: 20,
+            tos: 0,
+            length: 40,
+            id: 0,
+            flags: 0b010,
+            fragment_offset: 0,
+            ttl: 64,
+            protocol: IPProtocol::TCP,
+            chksum: 0,
+            source_addr: IPv4Address(source),
+            dest_addr: IPv4Address(dest),
+            #[cfg(feature = "alloc")]
+            options: vec![],
+        }
+    }
+
+    fn tcp_header(source_port: u16, dest_port: u16) -> TcpHeader {
+        TcpHeader {
+            source_port,
+            dest_port,
+            data_offset: 5,
+            flag_ack: true,
+            window: 65535,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn both_directions_of_a_flow_are_distinct_keys() {
+        let client = FlowKey::from_tcp_ipv4(&ipv4_header([10, 0, 0, 1], [10, 0, 0, 2]),
+                                            &tcp_header(50871, 80));
+        let server = FlowKey::from_tcp_ipv4(&ipv4_header([10, 0, 0, 2], [10, 0, 0, 1]),
+                                            &tcp_header(80, 50871));
+        assert_eq!(client.reversed(), server);
+
+        let mut bytes = HashMap::new();
+        *bytes.entry(client).or_insert(0) += 120;
+        *bytes.entry(server).or_insert(0) += 1460;
+        *bytes.entry(client).or_insert(0) += 60;
+
+        assert_eq!(bytes.len(), 2);
+        assert_eq!(bytes[&client], 180);
+        assert_eq!(bytes[&server], 1460);
+        assert_eq!(bytes[&server.reversed()], 180);
+    }
+}
@@ -0,0 +1,172 @@
+//! Handles parsing of GTP-U headers
+//!
+//! GTP-U runs over UDP port 2152; a G-PDU (`message_type` 255) carries an
+//! inner IP packet in the input following the header.
+
+use alloc::vec::Vec;
+
+use nom::{IResult, Err, ErrorKind, be_u8, be_u16, be_u32};
+
+/// The UDP port GTP-U is carried on
+pub const GTPU_PORT: u16 = 2152;
+/// Message type of a G-PDU, which encapsulates a user packet
+pub const GTPU_G_PDU: u8 = 255;
+/// Upper bound on chained extension headers in one GTP-U header
+pub const MAX_GTPU_EXTENSIONS: usize = 16;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct GtpExtensionHeader {
+    pub extension_type: u8,
+    /// The extension content, without its length and next-type bytes
+    pub content: Vec<u8>,
+}
+/// A GTP-U header. The sequence number, N-PDU number and extension headers
+/// are only on the wire when at least one of the E, S and PN flags is set;
+/// a field is then meaningful when its own flag is set.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GtpU {
+    pub version: u8,
+    /// 1 for GTP, 0 for GTP'
+    pub protocol_type: u8,
+    pub flag_e: bool,
+    pub flag_s: bool,
+    pub flag_pn: bool,
+    pub message_type: u8,
+    /// Length of everything after the first 8 bytes of the header
+    pub length: u16,
+    pub teid: u32,
+    pub sequence_number: Option<u16>,
+    pub n_pdu: Option<u8>,
+    pub extensions: Vec<GtpExtensionHeader>,
+}
+
+named!(gtp_flags<&[u8], (u8, u8, u8, u8, u8, u8)>,
+    bits!(tuple!(
+        take_bits!(u8, 3),
+        take_bits!(u8, 1),
+        take_bits!(u8, 1),
+        take_bits!(u8, 1),
+        take_bits!(u8, 1),
+        take_bits!(u8, 1))));
+
+// The extension length counts 4-byte units, including the length byte and
+// the next extension type byte
+named!(gtp_extension<&[u8], (Vec<u8>, u8)>, chain!(
+    len: map_opt!(be_u8, |l: u8| (l as usize * 4).checked_sub(2)) ~
+    content: take!(len) ~
+    next_type: be_u8,
+    || (content.to_vec(), next_type)
+));
+
+pub fn parse_gtpu_header(i: &[u8]) -> IResult<&[u8], GtpU> {
+    let (mut left, (flags, message_type, length, teid)) = try_parse!(i, chain!(
+        flags: gtp_flags ~
+        message_type: be_u8 ~
+        length: be_u16 ~
+        teid: be_u32,
+        || (flags, message_type, length, teid)
+    ));
+    let mut header = GtpU {
+        version: flags.0,
+        protocol_type: flags.1,
+        flag_e: flags.3 == 1,
+        flag_s: flags.4 == 1,
+        flag_pn: flags.5 == 1,
+        message_type,
+        length,
+        teid,
+        sequence_number: None,
+        n_pdu: None,
+        extensions: Vec::new(),
+    };
+    if !(header.flag_e || header.flag_s || header.flag_pn) {
+        return IResult::Done(left, header);
+    }
+
+    let (rest, (sequence_number, n_pdu, mut next_type)) = try_parse!(left, tuple!(be_u16, be_u8, be_u8));
+    header.sequence_number = Some(sequence_number);
+    header.n_pdu = Some(n_pdu);
+    left = rest;
+    while header.flag_e && next_type != 0 {
+        if header.extensions.len() == MAX_GTPU_EXTENSIONS {
+            return IResult::Error(Err::Position(ErrorKind::ManyMN, left));
+        }
+        let (rest, (content, next)) = try_parse!(left, gtp_extension);
+        header.extensions.push(GtpExtensionHeader { extension_type: next_type, content });
+        next_type = next;
+        left = rest;
+    }
+    IResult::Done(left, header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ip::IPProtocol;
+    use ipv4::parse_ipv4_header;
+    use nom::IResult;
+
+    #[test]
+    fn gtpu_g_pdu_with_ipv4_works() {
+        let bytes = [0x32, 0xff, 0x00, 0x20, /* Version 1, GTP, S flag, G-PDU, 32 bytes */
+                     0x00, 0x00, 0x00, 0x42, /* TEID */
+                     0x01, 0x2c, 0x00, 0x00, /* Sequence 300, N-PDU, no extension */
+                     0x45, 0x00, 0x00, 0x1c, 0x1a, 0xe6, 0x00, 0x00,
+                     0x40, 0x01, 0x22, 0xed, 0x0a, 0x2d, 0x00, 0x02,
+                     0x0a, 0x2d, 0x00, 0x01, /* Inner IPv4 header */
+                     0x08, 0x00, 0xf7, 0xff, 0x00, 0x00, 0x00, 0x00 /* ICMP echo */];
+        let expectation = GtpU {
+            version: 1,
+            protocol_type: 1,
+            flag_e: false,
+            flag_s: true,
+            flag_pn: false,
+            message_type: GTPU_G_PDU,
+            length: 32,
+            teid: 0x42,
+            sequence_number: Some(300),
+            n_pdu: Some(0),
+            extensions: vec![],
+        };
+        let inner = match parse_gtpu_header(&bytes) {
+            IResult::Done(inner, header) => {
+                assert_eq!(header, expectation);
+                inner
+            }
+            _ => panic!(),
+        };
+        if let IResult::Done(_, header) = parse_ipv4_header(inner) {
+            assert_eq!(header.protocol, IPProtocol::ICMP);
+            assert_eq!(header.dest_addr.to_string(), "10.45.0.1");
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn gtpu_extension_headers_are_chained() {
+        let bytes = [0x34, 0xff, 0x00, 0x08, 0x00, 0x00, 0x00, 0x01,
+                     0x00, 0x00, 0x00, 0x85, /* PDU session container follows */
+                     0x01, 0x10, 0x05, 0x00, /* QFI 5, no further extension */
+                     0x45];
+        if let IResult::Done(rest, header) = parse_gtpu_header(&bytes) {
+            assert!(header.flag_e);
+            assert_eq!(header.extensions,
+                       vec![GtpExtensionHeader { extension_type: 0x85, content: vec![0x10, 0x05] }]);
+            assert_eq!(rest, &[0x45]);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn gtpu_minimal_header_has_no_optional_fields() {
+        let bytes = [0x30, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x07];
+        if let IResult::Done(_, header) = parse_gtpu_header(&bytes) {
+            assert_eq!(header.teid, 7);
+            assert_eq!(header.sequence_number, None);
+        } else {
+            panic!();
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Handles parsing of HTTP/1.x request heads
+//!
+//! The request line and header fields are parsed from the start of a TCP
+//! payload, leaving any message body in the remaining input. A payload that
+//! ends before the blank line closing the header is `Incomplete`, so more
+//! segments can be appended and the parse retried. A payload that cannot
+//! start a request, or whose head outgrows `MAX_HTTP_HEAD_LEN`, is an error
+//! as soon as that is known rather than waiting for a line end.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use nom::{IResult, Err, ErrorKind, Needed};
+
+/// Upper bound on header fields in one request
+pub const MAX_HTTP_HEADERS: usize = 100;
+/// Upper bound on the length of the request line and header fields,
+/// line endings included
+pub const MAX_HTTP_HEAD_LEN: usize = 8192;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub target: String,
+    /// E.g. `"HTTP/1.1"`; `None` for an HTTP/0.9 simple request, which is
+    /// just a method and target and has no header
+    pub version: Option<String>,
+    /// Header fields in the order received, with the value's surrounding
+    /// whitespace removed
+    pub headers: Vec<(String, String)>,
+}
+
+impl HttpRequest {
+    /// The value of the first header field named `name`, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|&(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Splits off one line, ending in CRLF or a bare LF, returning it without
+/// the line ending. The line ending must be within the first `limit` bytes.
+fn line(i: &[u8], limit: usize) -> IResult<&[u8], &[u8]> {
+    match i.iter().take(limit).position(|&b| b == b'\n') {
+        Some(end) => {
+            let line = &i[..end];
+            IResult::Done(&i[end + 1..], line.strip_suffix(b"\r").unwrap_or(line))
+        }
+        None if i.len() >= limit => IResult::Error(Err::Position(ErrorKind::TakeUntil, i)),
+        None => IResult::Incomplete(Needed::Unknown),
+    }
+}
+
+fn is_token_byte(b: &u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(b)
+}
+
+fn is_token(s: &str) -> bool {
+    !s.is_empty() && s.as_bytes().iter().all(is_token_byte)
+}
+
+/// Whether the start of a request line without its line ending so far could
+/// still begin a request, i.e. whether its method is a token
+fn could_be_request_line(i: &[u8]) -> bool {
+    let method = i.split(|&b| b == b' ').next().unwrap_or(i);
+    method.iter().all(is_token_byte) && (method.len() == i.len() || !method.is_empty())
+}
+
+fn request_line(i: &[u8]) -> Option<(&str, &str, Option<&str>)> {
+    let line = core::str::from_utf8(i).ok()?;
+    let mut parts = line.split(' ');
+    let method = parts.next().filter(|m| is_token(m))?;
+    let target = parts.next().filter(|t| !t.is_empty())?;
+    match (parts.next(), parts.next()) {
+        (Some(version), None) if version.starts_with("HTTP/1.") => Some((method, target, Some(version))),
+        (None, None) if method == "GET" => Some((method, target, None)),
+        _ => None,
+    }
+}
+
+fn header_field(i: &[u8]) -> Option<(String, String)> {
+    let line = core::str::from_utf8(i).ok()?;
+    let colon = line.find(':')?;
+    let name = &line[..colon];
+    if !is_token(name) {
+        return None;
+    }
+    Some((String::from(name), String::from(line[colon + 1..].trim())))
+}
+
+/// Parses a request line and, unless it is an HTTP/0.9 request, the header
+/// fields up to and including the blank line that ends them
+pub fn parse_http_request(i: &[u8]) -> IResult<&[u8], HttpRequest> {
+    let (mut left, first_line) = match line(i, MAX_HTTP_HEAD_LEN) {
+        IResult::Incomplete(_) if !could_be_request_line(i) => {
+            return IResult::Error(Err::Position(ErrorKind::MapOpt, i));
+        }
+        IResult::Incomplete(needed) => return IResult::Incomplete(needed),
+        IResult::Error(e) => return IResult::Error(e),
+        IResult::Done(rest, first_line) => (rest, first_line),
+    };
+    let (method, target, version) = match request_line(first_line) {
+        Some(parts) => parts,
+        None => return IResult::Error(Err::Position(ErrorKind::MapOpt, i)),
+    };
+    let mut request = HttpRequest {
+        method: String::from(method),
+        target: String::from(target),
+        version: version.map(String::from),
+        headers: Vec::new(),
+    };
+    if request.version.is_none() {
+        return IResult::Done(left, request);
+    }
+    loop {
+        let (rest, field) = try_parse!(left, apply!(line, MAX_HTTP_HEAD_LEN - (i.len() - left.len())));
+        if field.is_empty() {
+            return IResult::Done(rest, request);
+        }
+        if request.headers.len() == MAX_HTTP_HEADERS {
+            return IResult::Error(Err::Position(ErrorKind::ManyMN, left));
+        }
+        match header_field(field) {
+            Some(header) => request.headers.push(header),
+            None => return IResult::Error(Err::Position(ErrorKind::MapOpt, left)),
+        }
+        left = rest;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::{IResult, Needed};
+
+    #[test]
+    fn http_request_works() {
+        let bytes = b"POST /api/v1/items HTTP/1.1\r\n\
+                      Host: example.com\r\n\
+                      Content-Type: application/json\r\n\
+                      Content-Length:  7 \r\n\
+                      \r\n\
+                      {\"a\":1}";
+        let expectation = HttpRequest {
+            method: "POST".into(),
+            target: "/api/v1/items".into(),
+            version: Some("HTTP/1.1".into()),
+            headers: vec![("Host".into(), "example.com".into()),
+                          ("Content-Type".into(), "application/json".into()),
+                          ("Content-Length".into(), "7".into())],
+        };
+        assert_eq!(expectation.header("content-length"), Some("7"));
+        assert_eq!(parse_http_request(bytes), IResult::Done(&b"{\"a\":1}"[..], expectation));
+    }
+
+    #[test]
+    fn partial_http_request_is_incomplete() {
+        assert_eq!(parse_http_request(b"GET / HTTP/1.1\r\nHost: exam"),
+                   IResult::Incomplete(Needed::Unknown));
+        assert_eq!(parse_http_request(b"GET / HTTP/1.1\r\nHost: example.com\r\n"),
+                   IResult::Incomplete(Needed::Unknown));
+    }
+
+    #[test]
+    fn non_http_payload_is_an_error() {
+        for payload in &[&b"SSH-2.0-OpenSSH_9.6\r\n"[..], &b"\x16\x03\x01\x02\x00\x01\x00"[..],
+                         &b"GET/ HTTP"[..], &b" GET"[..],
+                         &b"GET / HTTP/1.1\r\nno colon\r\n\r\n"[..]] {
+            match parse_http_request(payload) {
+                IResult::Error(_) => {}
+                other => panic!("expected an error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn tls_record_without_a_line_feed_is_an_error() {
+        // A ClientHello record header and the start of its handshake
+        let hello = [0x16, 0x03, 0x01, 0x00, 0xc8, 0x01, 0x00, 0x00, 0xc4, 0x03, 0x03];
+        match parse_http_request(&hello) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn partial_method_is_incomplete() {
+        for payload in &[&b"GE"[..], &b"GET"[..], &b"GET /ind"[..], &b""[..]] {
+            assert_eq!(parse_http_request(payload), IResult::Incomplete(Needed::Unknown));
+        }
+    }
+
+    #[test]
+    fn oversized_head_is_an_error() {
+        let mut bytes = b"GET / HTTP/1.1\r\nCookie: ".to_vec();
+        bytes.resize(MAX_HTTP_HEAD_LEN, b'a');
+        match parse_http_request(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+        assert_eq!(parse_http_request(&bytes[..MAX_HTTP_HEAD_LEN - 1]), IResult::Incomplete(Needed::Unknown));
+
+        let mut bytes = b"GET /".to_vec();
+        bytes.resize(MAX_HTTP_HEAD_LEN + 10, b'a');
+        match parse_http_request(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,550 @@
+//! Handles parsing of ICMP headers
+
+use core::fmt;
+
+use alloc::vec::Vec;
+
+use nom::{IResult, Err, ErrorKind, be_u8, be_u16, rest};
+
+use ipv4::{IPv4Address, IPv4Header, parse_ipv4_header};
+use mpls::{MplsLabel, parse_mpls_stack};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Unreachable {
+    DestinationNetworkUnreachable,
+    DestinationHostUnreachable,
+    DestinationProtocolUnreachable,
+    DestinationPortUnreachable,
+    FragmentationRequired,
+    SourceRouteFailed,
+    DestinationNetworkUnknown,
+    DestinationHostUnknown,
+    SourceHostIsolated,
+    NetworkAdministrativelyProhibited,
+    HostAdministrativelyProhibited,
+    NetworkUnreachableForTos,
+    HostUnreachableForTos,
+    CommunicationAdministrativelyProhibited,
+    HostPrecedenceViolation,
+    PrecedenceCutoffInEffect,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Redirect {
+    Network,
+    Host,
+    TosAndNetwork,
+    TosAndHost,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimeExceeded {
+    TTL,
+    FragmentReassembly,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParameterProblem {
+    Pointer,
+    MissingRequiredOption,
+    BadLength,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IcmpCode {
+    EchoReply,
+    DestinationUnreachable(Unreachable),
+    SourceQuench,
+    Redirect(Redirect),
+    EchoRequest,
+    RouterAdvertisement,
+    RouterSolicitation,
+    TimeExceeded(TimeExceeded),
+    ParameterProblem(ParameterProblem),
+    Timestamp,
+    TimestampReply,
+    /// Any other type/code pair, packed as `type << 8 | code`
+    Other(u16),
+}
+/// The original datagram's payload quoted by error messages. RFC 792 only
+/// requires its first 8 bytes, but routers following RFC 1812 quote as much
+/// as fits, so this holds everything after the quoted IPv4 header.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IcmpPayloadPacket(pub Vec<u8>);
+
+impl IcmpExtensionObject {
+    /// The label stack of an MPLS Label Stack object (class 1, c-type 1)
+    pub fn mpls_label_stack(&self) -> Option<Vec<MplsLabel>> {
+        match (self.class_num, self.c_type, parse_mpls_stack(&self.payload)) {
+            (1, 1, IResult::Done(_, labels)) => Some(labels),
+            _ => None,
+        }
+    }
+}
+
+impl IcmpPayloadPacket {
+    /// The first 8 bytes of the quoted payload, which every error message
+    /// carries. `None` if fewer are held, which the parsers never produce
+    /// but a hand-built or deserialized value may.
+    pub fn first_eight(&self) -> Option<[u8; 8]> {
+        self.0.get(..8).map(|first| *array_ref![first, 0, 8])
+    }
+}
+/// An object of an RFC 4884 extension structure
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IcmpExtensionObject {
+    pub class_num: u8,
+    pub c_type: u8,
+    pub payload: Vec<u8>,
+}
+/// The RFC 4884 extension structure following the quoted datagram, such as
+/// the MPLS label stack reported by routers to traceroute
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IcmpExtensions {
+    pub version: u8,
+    pub checksum: u16,
+    pub objects: Vec<IcmpExtensionObject>,
+}
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IcmpData {
+    Echo {
+        identifier: u16,
+        sequence: u16,
+    },
+    DestinationUnreachable {
+        next_hop_mtu: u16,
+        header: IPv4Header,
+        data: IcmpPayloadPacket,
+        extensions: Option<IcmpExtensions>,
+    },
+    Redirect {
+        gateway: IPv4Address,
+        header: IPv4Header,
+        data: IcmpPayloadPacket,
+    },
+    TimeExceeded {
+        header: IPv4Header,
+        data: IcmpPayloadPacket,
+        extensions: Option<IcmpExtensions>,
+    },
+    ParameterProblem {
+        pointer: u8,
+        header: IPv4Header,
+        data: IcmpPayloadPacket,
+        extensions: Option<IcmpExtensions>,
+    },
+    /// The 4 bytes following the checksum of any other message type
+    Other([u8; 4]),
+}
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IcmpHeader {
+    pub code: IcmpCode,
+    pub checksum: u16,
+    pub data: IcmpData,
+}
+
+impl IcmpHeader {
+    /// The type field as sent, whether or not `code` recognized it
+    pub fn type_byte(&self) -> u8 {
+        (u16::from(self.code) >> 8) as u8
+    }
+
+    /// The code field as sent, whether or not `code` recognized it
+    pub fn code_byte(&self) -> u8 {
+        u16::from(self.code) as u8
+    }
+}
+
+impl fmt::Display for IcmpHeader {
+    /// A one-line summary, e.g. `ICMP EchoRequest id=1 seq=7`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ICMP {:?}", self.code)?;
+        match self.data {
+            IcmpData::Echo { identifier, sequence } => write!(f, " id={} seq={}", identifier, sequence),
+            IcmpData::Redirect { gateway, .. } => write!(f, " gateway={}", gateway),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn to_icmp_code(t: u8, c: u8) -> IcmpCode {
+    let code = match (t, c) {
+        (0, 0) => Some(IcmpCode::EchoReply),
+        (3, _) => to_unreachable(c).map(IcmpCode::DestinationUnreachable),
+        (4, 0) => Some(IcmpCode::SourceQuench),
+        (5, 0) => Some(IcmpCode::Redirect(Redirect::Network)),
+        (5, 1) => Some(IcmpCode::Redirect(Redirect::Host)),
+        (5, 2) => Some(IcmpCode::Redirect(Redirect::TosAndNetwork)),
+        (5, 3) => Some(IcmpCode::Redirect(Redirect::TosAndHost)),
+        (8, 0) => Some(IcmpCode::EchoRequest),
+        (9, 0) => Some(IcmpCode::RouterAdvertisement),
+        (10, 0) => Some(IcmpCode::RouterSolicitation),
+        (11, 0) => Some(IcmpCode::TimeExceeded(TimeExceeded::TTL)),
+        (11, 1) => Some(IcmpCode::TimeExceeded(TimeExceeded::FragmentReassembly)),
+        (12, 0) => Some(IcmpCode::ParameterProblem(ParameterProblem::Pointer)),
+        (12, 1) => Some(IcmpCode::ParameterProblem(ParameterProblem::MissingRequiredOption)),
+        (12, 2) => Some(IcmpCode::ParameterProblem(ParameterProblem::BadLength)),
+        (13, 0) => Some(IcmpCode::Timestamp),
+        (14, 0) => Some(IcmpCode::TimestampReply),
+        _ => None,
+    };
+    code.unwrap_or(IcmpCode::Other((t as u16) << 8 | c as u16))
+}
+
+fn to_unreachable(c: u8) -> Option<Unreachable> {
+    match c {
+        0 => Some(Unreachable::DestinationNetworkUnreachable),
+        1 => Some(Unreachable::DestinationHostUnreachable),
+        2 => Some(Unreachable::DestinationProtocolUnreachable),
+        3 => Some(Unreachable::DestinationPortUnreachable),
+        4 => Some(Unreachable::FragmentationRequired),
+        5 => Some(Unreachable::SourceRouteFailed),
+        6 => Some(Unreachable::DestinationNetworkUnknown),
+        7 => Some(Unreachable::DestinationHostUnknown),
+        8 => Some(Unreachable::SourceHostIsolated),
+        9 => Some(Unreachable::NetworkAdministrativelyProhibited),
+        10 => Some(Unreachable::HostAdministrativelyProhibited),
+        11 => Some(Unreachable::NetworkUnreachableForTos),
+        12 => Some(Unreachable::HostUnreachableForTos),
+        13 => Some(Unreachable::CommunicationAdministrativelyProhibited),
+        14 => Some(Unreachable::HostPrecedenceViolation),
+        15 => Some(Unreachable::PrecedenceCutoffInEffect),
+        _ => None,
+    }
+}
+
+impl From<IcmpCode> for u16 {
+    /// Packs the type and code as `type << 8 | code`, like `IcmpCode::Other`
+    fn from(code: IcmpCode) -> u16 {
+        let (t, c) = match code {
+            IcmpCode::EchoReply => (0, 0),
+            IcmpCode::DestinationUnreachable(u) => (3, from_unreachable(u)),
+            IcmpCode::SourceQuench => (4, 0),
+            IcmpCode::Redirect(Redirect::Network) => (5, 0),
+            IcmpCode::Redirect(Redirect::Host) => (5, 1),
+            IcmpCode::Redirect(Redirect::TosAndNetwork) => (5, 2),
+            IcmpCode::Redirect(Redirect::TosAndHost) => (5, 3),
+            IcmpCode::EchoRequest => (8, 0),
+            IcmpCode::RouterAdvertisement => (9, 0),
+            IcmpCode::RouterSolicitation => (10, 0),
+            IcmpCode::TimeExceeded(TimeExceeded::TTL) => (11, 0),
+            IcmpCode::TimeExceeded(TimeExceeded::FragmentReassembly) => (11, 1),
+            IcmpCode::ParameterProblem(ParameterProblem::Pointer) => (12, 0),
+            IcmpCode::ParameterProblem(ParameterProblem::MissingRequiredOption) => (12, 1),
+            IcmpCode::ParameterProblem(ParameterProblem::BadLength) => (12, 2),
+            IcmpCode::Timestamp => (13, 0),
+            IcmpCode::TimestampReply => (14, 0),
+            IcmpCode::Other(packed) => return packed,
+        };
+        (t as u16) << 8 | c as u16
+    }
+}
+
+fn from_unreachable(u: Unreachable) -> u8 {
+    match u {
+        Unreachable::DestinationNetworkUnreachable => 0,
+        Unreachable::DestinationHostUnreachable => 1,
+        Unreachable::DestinationProtocolUnreachable => 2,
+        Unreachable::DestinationPortUnreachable => 3,
+        Unreachable::FragmentationRequired => 4,
+        Unreachable::SourceRouteFailed => 5,
+        Unreachable::DestinationNetworkUnknown => 6,
+        Unreachable::DestinationHostUnknown => 7,
+        Unreachable::SourceHostIsolated => 8,
+        Unreachable::NetworkAdministrativelyProhibited => 9,
+        Unreachable::HostAdministrativelyProhibited => 10,
+        Unreachable::NetworkUnreachableForTos => 11,
+        Unreachable::HostUnreachableForTos => 12,
+        Unreachable::CommunicationAdministrativelyProhibited => 13,
+        Unreachable::HostPrecedenceViolation => 14,
+        Unreachable::PrecedenceCutoffInEffect => 15,
+    }
+}
+
+// At least 8 bytes must be quoted, so `first_eight` is always `Some`
+named!(payload_packet<&[u8], IcmpPayloadPacket>, chain!(
+    first: take!(8) ~
+    more: rest,
+    || {
+        let mut data = first.to_vec();
+        data.extend_from_slice(more);
+        IcmpPayloadPacket(data)
+    }
+));
+
+named!(extension_object<&[u8], IcmpExtensionObject>, chain!(
+    length: map_opt!(be_u16, |l: u16| (l as usize).checked_sub(4)) ~
+    class_num: be_u8 ~
+    c_type: be_u8 ~
+    payload: take!(length),
+    || IcmpExtensionObject { class_num, c_type, payload: payload.to_vec() }
+));
+
+named!(extensions<&[u8], IcmpExtensions>, chain!(
+    version: bits!(pair!(take_bits!(u8, 4), take_bits!(u16, 12))) ~
+    checksum: be_u16 ~
+    objects: many0!(extension_object),
+    || IcmpExtensions { version: version.0, checksum, objects }
+));
+
+/// Parses the quoted datagram and, when `length` (in 32-bit words, the
+/// quoted IPv4 header included) is set as per RFC 4884, the extension
+/// structure following it
+fn quotation(i: &[u8], length: u8)
+             -> IResult<&[u8], (IPv4Header, IcmpPayloadPacket, Option<IcmpExtensions>)> {
+    let (left, header) = try_parse!(i, parse_ipv4_header);
+    if length == 0 {
+        let (left, data) = try_parse!(left, payload_packet);
+        return IResult::Done(left, (header, data, None));
+    }
+    let data_len = match (length as usize * 4).checked_sub(header.ihl as usize) {
+        Some(len) if len >= 8 => len,
+        _ => return IResult::Error(Err::Position(ErrorKind::LengthValue, i)),
+    };
+    let (left, data) = try_parse!(left, take!(data_len));
+    let (left, extensions) = try_parse!(left, extensions);
+    IResult::Done(left, (header, IcmpPayloadPacket(data.to_vec()), Some(extensions)))
+}
+
+named!(unreachable<&[u8], IcmpData>, chain!(
+    be_u8 ~
+    length: be_u8 ~
+    next_hop_mtu: be_u16 ~
+    quotation: apply!(quotation, length),
+    || {
+        let (header, data, extensions) = quotation;
+        IcmpData::DestinationUnreachable { next_hop_mtu, header, data, extensions }
+    }
+));
+
+named!(redirect<&[u8], IcmpData>, chain!(
+    gateway: map!(take!(4), |i: &[u8]| IPv4Address(*array_ref![i, 0, 4])) ~
+    header: parse_ipv4_header ~
+    data: payload_packet,
+    || IcmpData::Redirect { gateway, header, data }
+));
+
+named!(time_exceeded<&[u8], IcmpData>, chain!(
+    be_u8 ~
+    length: be_u8 ~
+    be_u16 ~
+    quotation: apply!(quotation, length),
+    || {
+        let (header, data, extensions) = quotation;
+        IcmpData::TimeExceeded { header, data, extensions }
+    }
+));
+
+named!(parameter_problem<&[u8], IcmpData>, chain!(
+    pointer: be_u8 ~
+    length: be_u8 ~
+    be_u16 ~
+    quotation: apply!(quotation, length),
+    || {
+        let (header, data, extensions) = quotation;
+        IcmpData::ParameterProblem { pointer, header, data, extensions }
+    }
+));
+
+fn parse_icmp_data(i: &[u8], msg_type: u8) -> IResult<&[u8], IcmpData> {
+    match msg_type {
+        0 | 8 => chain!(i,
+            identifier: be_u16 ~
+            sequence: be_u16,
+            || IcmpData::Echo { identifier, sequence }),
+        3 => unreachable(i),
+        5 => redirect(i),
+        11 => time_exceeded(i),
+        12 => parameter_problem(i),
+        _ => map!(i, take!(4), |i: &[u8]| IcmpData::Other(*array_ref![i, 0, 4])),
+    }
+}
+
+named!(icmp_header<&[u8], IcmpHeader>, chain!(
+    msg_type: be_u8 ~
+    code: be_u8 ~
+    checksum: be_u16 ~
+    data: apply!(parse_icmp_data, msg_type),
+    || IcmpHeader {
+        code: to_icmp_code(msg_type, code),
+        checksum,
+        data,
+    }
+));
+
+pub fn parse_icmp_header(i: &[u8]) -> IResult<&[u8], IcmpHeader> {
+    icmp_header(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ip::IPProtocol;
+    use nom::IResult;
+    const EMPTY_SLICE: &[u8] = &[];
+
+    #[test]
+    fn icmp_echo_request_works() {
+        let bytes = [0x08, 0x00, /* Type and code */
+                     0x4d, 0x5a, /* Checksum */
+                     0x00, 0x01, /* Identifier */
+                     0x00, 0x07, /* Sequence number */
+                     0x61, 0x62, 0x63, 0x64 /* Data */];
+        let expectation = IcmpHeader {
+            code: IcmpCode::EchoRequest,
+            checksum: 0x4d5a,
+            data: IcmpData::Echo {
+                identifier: 1,
+                sequence: 7,
+            },
+        };
+        assert_eq!(expectation.to_string(), "ICMP EchoRequest id=1 seq=7");
+        assert_eq!(icmp_header(&bytes), IResult::Done(&b"abcd"[..], expectation));
+    }
+
+    #[test]
+    fn icmp_port_unreachable_works() {
+        let bytes = [0x03, 0x03, 0x12, 0x34, /* Type, code and checksum */
+                     0x00, 0x00, 0x00, 0x00, /* Unused and next-hop MTU */
+                     0x45, 0x00, 0x00, 0x24, 0x1a, 0xe6, 0x00, 0x00,
+                     0x40, 0x11, 0x22, 0xed, 0x0a, 0x0a, 0x01, 0x87,
+                     0x0a, 0x0a, 0x01, 0xb4, /* Original IPv4 header */
+                     0xc3, 0x50, 0x00, 0x35, 0x00, 0x10, 0x8b, 0x4e /* Original UDP header */];
+        if let IResult::Done(remaining, header) = icmp_header(&bytes) {
+            assert_eq!(remaining, EMPTY_SLICE);
+            assert_eq!(header.code,
+                       IcmpCode::DestinationUnreachable(Unreachable::DestinationPortUnreachable));
+            if let IcmpData::DestinationUnreachable { next_hop_mtu, header, data, extensions } = header.data {
+                assert_eq!(extensions, None);
+                assert_eq!(next_hop_mtu, 0);
+                assert_eq!(header.protocol, IPProtocol::UDP);
+                assert_eq!(data.first_eight(), Some([0xc3, 0x50, 0x00, 0x35, 0x00, 0x10, 0x8b, 0x4e]));
+                assert_eq!(data, IcmpPayloadPacket(vec![0xc3, 0x50, 0x00, 0x35, 0x00, 0x10, 0x8b, 0x4e]));
+            } else {
+                panic!();
+            }
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn icmp_unreachable_keeps_long_quotation() {
+        let mut bytes = vec![0x03, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                             0x45, 0x00, 0x00, 0x54, 0x1a, 0xe6, 0x00, 0x00,
+                             0x40, 0x01, 0x22, 0xed, 0x0a, 0x0a, 0x01, 0x87,
+                             0x0a, 0x0a, 0x01, 0xb4];
+        let quoted: Vec<u8> = (0..64).collect();
+        bytes.extend_from_slice(&quoted);
+        if let IResult::Done(remaining, header) = icmp_header(&bytes) {
+            assert_eq!(remaining, EMPTY_SLICE);
+            if let IcmpData::DestinationUnreachable { data, .. } = header.data {
+                assert_eq!(data.0, quoted);
+                assert_eq!(data.first_eight(), Some([0, 1, 2, 3, 4, 5, 6, 7]));
+            } else {
+                panic!();
+            }
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn icmp_time_exceeded_with_mpls_extension_works() {
+        let mut bytes = vec![0x0b, 0x00, 0x00, 0x00, /* TTL exceeded */
+                             0x00, 0x20, 0x00, 0x00, /* Quotation of 32 words */
+                             0x45, 0x00, 0x00, 0x3c, 0x1a, 0xe6, 0x00, 0x00,
+                             0x01, 0x11, 0x22, 0xed, 0x0a, 0x0a, 0x01, 0x87,
+                             0x0a, 0x0a, 0x01, 0xb4];
+        bytes.extend_from_slice(&[0u8; 108]); /* Quoted payload, padded to 128 bytes */
+        bytes.extend_from_slice(&[0x20, 0x00, 0x00, 0x00, /* Version 2 */
+                                  0x00, 0x08, 0x01, 0x01, /* MPLS label stack object */
+                                  0x04, 0xe2, 0x01, 0x01 /* Label 20000, S, TTL 1 */]);
+        let data = match icmp_header(&bytes) {
+            IResult::Done(remaining, header) => {
+                assert_eq!(remaining, EMPTY_SLICE);
+                assert_eq!(header.code, IcmpCode::TimeExceeded(TimeExceeded::TTL));
+                header.data
+            }
+            other => panic!("expected a header, got {:?}", other),
+        };
+        if let IcmpData::TimeExceeded { data, extensions: Some(extensions), .. } = data {
+            assert_eq!(data.0.len(), 108);
+            assert_eq!(extensions.version, 2);
+            assert_eq!(extensions.objects.len(), 1);
+            let labels = extensions.objects[0].mpls_label_stack().unwrap();
+            assert_eq!(labels, vec![MplsLabel {
+                label: 20000,
+                traffic_class: 0,
+                bottom_of_stack: true,
+                ttl: 1,
+            }]);
+        } else {
+            panic!();
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn icmp_unreachable_serde_round_trip() {
+        let bytes = [0x03, 0x03, 0x12, 0x34, 0x00, 0x00, 0x00, 0x00,
+                     0x45, 0x00, 0x00, 0x24, 0x1a, 0xe6, 0x00, 0x00,
+                     0x40, 0x11, 0x22, 0xed, 0x0a, 0x0a, 0x01, 0x87,
+                     0x0a, 0x0a, 0x01, 0xb4,
+                     0xc3, 0x50, 0x00, 0x35, 0x00, 0x10, 0x8b, 0x4e];
+        if let IResult::Done(_, header) = icmp_header(&bytes) {
+            let json = ::serde_json::to_string(&header).unwrap();
+            let decoded: IcmpHeader = ::serde_json::from_str(&json).unwrap();
+            assert_eq!(decoded, header);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn icmp_unknown_type_is_other() {
+        let bytes = [0x2a, 0x01, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04];
+        let expectation = IcmpHeader {
+            code: IcmpCode::Other(0x2a01),
+            checksum: 0,
+            data: IcmpData::Other([1, 2, 3, 4]),
+        };
+        assert_eq!(icmp_header(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[test]
+    fn icmp_type_and_code_bytes() {
+        let echo = IcmpHeader {
+            code: IcmpCode::EchoRequest,
+            checksum: 0,
+            data: IcmpData::Echo { identifier: 1, sequence: 7 },
+        };
+        assert_eq!((echo.type_byte(), echo.code_byte()), (8, 0));
+
+        let unreachable = IcmpHeader {
+            code: IcmpCode::DestinationUnreachable(Unreachable::DestinationHostUnreachable),
+            ..echo
+        };
+        assert_eq!((unreachable.type_byte(), unreachable.code_byte()), (3, 1));
+
+        let other = IcmpHeader { code: IcmpCode::Other(0x2a01), ..unreachable };
+        assert_eq!((other.type_byte(), other.code_byte()), (42, 1));
+
+        for t in 0..=255 {
+            for c in 0..=255 {
+                assert_eq!(u16::from(to_icmp_code(t, c)), (t as u16) << 8 | c as u16);
+            }
+        }
+    }
+    #[test]
+    fn short_payload_has_no_first_eight() {
+        assert_eq!(IcmpPayloadPacket(vec![]).first_eight(), None);
+        assert_eq!(IcmpPayloadPacket(vec![1, 2, 3, 4, 5, 6, 7]).first_eight(), None);
+    }
+}
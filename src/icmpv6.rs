@@ -0,0 +1,431 @@
+//! Handles parsing of ICMPv6 headers
+
+use alloc::vec::Vec;
+
+use nom::{IResult, be_u8, be_u16, be_u32};
+
+use ipv6::IPv6Address;
+
+// ICMPv6 Header Format (RFC 4443)
+//
+//    0                   1                   2                   3
+//    0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |     Type      |     Code      |          Checksum             |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |                                                               |
+//   +                         Message Body                          +
+//   |                                                               |
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DestinationUnreachableCode {
+    NoRoute,
+    AdministrativelyProhibited,
+    BeyondScope,
+    AddressUnreachable,
+    PortUnreachable,
+    SourceAddressFailedPolicy,
+    RejectRoute,
+    Other(u8),
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimeExceededCode {
+    HopLimitExceeded,
+    FragmentReassemblyTimeExceeded,
+    Other(u8),
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParameterProblemCode {
+    ErroneousHeaderField,
+    UnrecognizedNextHeader,
+    UnrecognizedOption,
+    Other(u8),
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Icmpv6Code {
+    DestinationUnreachable(DestinationUnreachableCode),
+    PacketTooBig,
+    TimeExceeded(TimeExceededCode),
+    ParameterProblem(ParameterProblemCode),
+    EchoRequest,
+    EchoReply,
+    RouterSolicitation,
+    RouterAdvertisement,
+    NeighborSolicitation,
+    NeighborAdvertisement,
+    Redirect,
+    /// Any other type/code pair, packed as `type << 8 | code`
+    Other(u16),
+}
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Icmpv6Data {
+    /// Destination Unreachable and Time Exceeded carry 4 unused bytes
+    Unused,
+    PacketTooBig {
+        mtu: u32,
+    },
+    ParameterProblem {
+        pointer: u32,
+    },
+    Echo {
+        identifier: u16,
+        sequence: u16,
+    },
+    RouterSolicitation,
+    RouterAdvertisement {
+        cur_hop_limit: u8,
+        managed: bool,
+        other_config: bool,
+        router_lifetime: u16,
+        reachable_time: u32,
+        retrans_timer: u32,
+    },
+    NeighborSolicitation {
+        target_addr: IPv6Address,
+    },
+    NeighborAdvertisement {
+        router: bool,
+        solicited: bool,
+        override_flag: bool,
+        target_addr: IPv6Address,
+    },
+    Redirect {
+        target_addr: IPv6Address,
+        dest_addr: IPv6Address,
+    },
+    /// The 4 bytes following the checksum of an unknown message type
+    Other(u32),
+}
+/// A Neighbor Discovery option (RFC 4861), following the fixed part of a
+/// Router Solicitation, Router Advertisement, Neighbor Solicitation, Neighbor
+/// Advertisement or Redirect message
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NdpOption {
+    /// The sender's link-layer address, a MAC address on Ethernet. Any
+    /// padding up to the option length is included.
+    SourceLinkLayerAddress(Vec<u8>),
+    TargetLinkLayerAddress(Vec<u8>),
+    PrefixInformation {
+        prefix_length: u8,
+        on_link: bool,
+        autonomous: bool,
+        valid_lifetime: u32,
+        preferred_lifetime: u32,
+        prefix: IPv6Address,
+    },
+    /// The start of the packet a Redirect was sent for
+    RedirectedHeader(Vec<u8>),
+    Mtu(u32),
+    /// An option type this crate does not decode, with its value bytes
+    Unknown {
+        option_type: u8,
+        value: Vec<u8>,
+    },
+}
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Icmpv6Header {
+    pub code: Icmpv6Code,
+    pub checksum: u16,
+    pub data: Icmpv6Data,
+}
+
+fn to_icmpv6_code(t: u8, c: u8) -> Icmpv6Code {
+    match t {
+        1 => Icmpv6Code::DestinationUnreachable(match c {
+            0 => DestinationUnreachableCode::NoRoute,
+            1 => DestinationUnreachableCode::AdministrativelyProhibited,
+            2 => DestinationUnreachableCode::BeyondScope,
+            3 => DestinationUnreachableCode::AddressUnreachable,
+            4 => DestinationUnreachableCode::PortUnreachable,
+            5 => DestinationUnreachableCode::SourceAddressFailedPolicy,
+            6 => DestinationUnreachableCode::RejectRoute,
+            _ => DestinationUnreachableCode::Other(c),
+        }),
+        2 => Icmpv6Code::PacketTooBig,
+        3 => Icmpv6Code::TimeExceeded(match c {
+            0 => TimeExceededCode::HopLimitExceeded,
+            1 => TimeExceededCode::FragmentReassemblyTimeExceeded,
+            _ => TimeExceededCode::Other(c),
+        }),
+        4 => Icmpv6Code::ParameterProblem(match c {
+            0 => ParameterProblemCode::ErroneousHeaderField,
+            1 => ParameterProblemCode::UnrecognizedNextHeader,
+            2 => ParameterProblemCode::UnrecognizedOption,
+            _ => ParameterProblemCode::Other(c),
+        }),
+        128 => Icmpv6Code::EchoRequest,
+        129 => Icmpv6Code::EchoReply,
+        133 => Icmpv6Code::RouterSolicitation,
+        134 => Icmpv6Code::RouterAdvertisement,
+        135 => Icmpv6Code::NeighborSolicitation,
+        136 => Icmpv6Code::NeighborAdvertisement,
+        137 => Icmpv6Code::Redirect,
+        _ => Icmpv6Code::Other((t as u16) << 8 | c as u16),
+    }
+}
+
+fn to_ipv6_address(i: &[u8]) -> IPv6Address {
+    IPv6Address(*array_ref![i, 0, 16])
+}
+
+named!(ipv6_address<&[u8], IPv6Address>, map!(take!(16), to_ipv6_address));
+
+named!(router_advertisement<&[u8], Icmpv6Data>, chain!(
+    cur_hop_limit: be_u8 ~
+    flags: be_u8 ~
+    router_lifetime: be_u16 ~
+    reachable_time: be_u32 ~
+    retrans_timer: be_u32,
+    || Icmpv6Data::RouterAdvertisement {
+        cur_hop_limit,
+        managed: flags & 0x80 == 0x80,
+        other_config: flags & 0x40 == 0x40,
+        router_lifetime,
+        reachable_time,
+        retrans_timer,
+    }
+));
+
+named!(neighbor_solicitation<&[u8], Icmpv6Data>, chain!(
+    be_u32 ~
+    target_addr: ipv6_address,
+    || Icmpv6Data::NeighborSolicitation { target_addr }
+));
+
+named!(neighbor_advertisement<&[u8], Icmpv6Data>, chain!(
+    flags: be_u32 ~
+    target_addr: ipv6_address,
+    || Icmpv6Data::NeighborAdvertisement {
+        router: flags & 0x8000_0000 == 0x8000_0000,
+        solicited: flags & 0x4000_0000 == 0x4000_0000,
+        override_flag: flags & 0x2000_0000 == 0x2000_0000,
+        target_addr,
+    }
+));
+
+named!(redirect<&[u8], Icmpv6Data>, chain!(
+    be_u32 ~
+    target_addr: ipv6_address ~
+    dest_addr: ipv6_address,
+    || Icmpv6Data::Redirect { target_addr, dest_addr }
+));
+
+// The option length counts 8-byte units, including the type and length bytes
+named!(ndp_option_length<&[u8], usize>,
+    map_opt!(be_u8, |l: u8| (l as usize * 8).checked_sub(2)));
+
+fn to_ndp_option(option_type: u8, value: &[u8]) -> Option<NdpOption> {
+    match option_type {
+        1 => Some(NdpOption::SourceLinkLayerAddress(value.to_vec())),
+        2 => Some(NdpOption::TargetLinkLayerAddress(value.to_vec())),
+        3 if value.len() == 30 => Some(NdpOption::PrefixInformation {
+            prefix_length: value[0],
+            on_link: value[1] & 0x80 == 0x80,
+            autonomous: value[1] & 0x40 == 0x40,
+            valid_lifetime: u32::from_be_bytes(*array_ref![value, 2, 4]),
+            preferred_lifetime: u32::from_be_bytes(*array_ref![value, 6, 4]),
+            prefix: IPv6Address(*array_ref![value, 14, 16]),
+        }),
+        4 if value.len() >= 6 => Some(NdpOption::RedirectedHeader(value[6..].to_vec())),
+        5 if value.len() == 6 => Some(NdpOption::Mtu(u32::from_be_bytes(*array_ref![value, 2, 4]))),
+        3..=5 => None,
+        _ => Some(NdpOption::Unknown { option_type, value: value.to_vec() }),
+    }
+}
+
+named!(ndp_option<&[u8], NdpOption>, map_opt!(
+    chain!(
+        option_type: be_u8 ~
+        length: ndp_option_length ~
+        value: take!(length),
+        || (option_type, value)
+    ),
+    |(option_type, value): (u8, &[u8])| to_ndp_option(option_type, value)
+));
+
+/// Parses the Neighbor Discovery options left in the remaining input by
+/// `parse_icmpv6_header` for an NDP message. Options run to the end of the
+/// message; parsing stops at the first malformed one.
+pub fn parse_ndp_options(i: &[u8]) -> IResult<&[u8], Vec<NdpOption>> {
+    many0!(i, ndp_option)
+}
+
+fn parse_icmpv6_data(i: &[u8], msg_type: u8) -> IResult<&[u8], Icmpv6Data> {
+    match msg_type {
+        1 | 3 => map!(i, be_u32, |_| Icmpv6Data::Unused),
+        2 => map!(i, be_u32, |mtu| Icmpv6Data::PacketTooBig { mtu }),
+        4 => map!(i, be_u32, |pointer| Icmpv6Data::ParameterProblem { pointer }),
+        128 | 129 => chain!(i,
+            identifier: be_u16 ~
+            sequence: be_u16,
+            || Icmpv6Data::Echo { identifier, sequence }),
+        133 => map!(i, be_u32, |_| Icmpv6Data::RouterSolicitation),
+        134 => router_advertisement(i),
+        135 => neighbor_solicitation(i),
+        136 => neighbor_advertisement(i),
+        137 => redirect(i),
+        _ => map!(i, be_u32, Icmpv6Data::Other),
+    }
+}
+
+named!(icmpv6_header<&[u8], Icmpv6Header>, chain!(
+    msg_type: be_u8 ~
+    code: be_u8 ~
+    checksum: be_u16 ~
+    data: apply!(parse_icmpv6_data, msg_type),
+    || Icmpv6Header {
+        code: to_icmpv6_code(msg_type, code),
+        checksum,
+        data,
+    }
+));
+
+pub fn parse_icmpv6_header(i: &[u8]) -> IResult<&[u8], Icmpv6Header> {
+    icmpv6_header(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::IResult;
+    const EMPTY_SLICE: &[u8] = &[];
+
+    #[test]
+    fn icmpv6_echo_request_works() {
+        let bytes = [0x80, 0x00, /* Type and code */
+                     0x3f, 0x58, /* Checksum */
+                     0x1c, 0x2e, /* Identifier */
+                     0x00, 0x01, /* Sequence number */
+                     0x61, 0x62, 0x63, 0x64 /* Data */];
+        let expectation = Icmpv6Header {
+            code: Icmpv6Code::EchoRequest,
+            checksum: 0x3f58,
+            data: Icmpv6Data::Echo {
+                identifier: 0x1c2e,
+                sequence: 1,
+            },
+        };
+        assert_eq!(icmpv6_header(&bytes), IResult::Done(&b"abcd"[..], expectation));
+    }
+
+    #[test]
+    fn icmpv6_router_advertisement_works() {
+        let bytes = [0x86, 0x00, /* Type and code */
+                     0x7e, 0x3c, /* Checksum */
+                     0x40, /* Cur hop limit */
+                     0xc0, /* M and O flags */
+                     0x07, 0x08, /* Router lifetime */
+                     0x00, 0x00, 0x00, 0x00, /* Reachable time */
+                     0x00, 0x00, 0x00, 0x00, /* Retrans timer */
+                     0x01, 0x01, 0x00, 0x0c, 0x29, 0x4f, 0x1a, 0x3b /* Source link-layer address option */];
+        let expectation = Icmpv6Header {
+            code: Icmpv6Code::RouterAdvertisement,
+            checksum: 0x7e3c,
+            data: Icmpv6Data::RouterAdvertisement {
+                cur_hop_limit: 64,
+                managed: true,
+                other_config: true,
+                router_lifetime: 1800,
+                reachable_time: 0,
+                retrans_timer: 0,
+            },
+        };
+        assert_eq!(icmpv6_header(&bytes),
+                   IResult::Done(&bytes[16..], expectation));
+    }
+
+    #[test]
+    fn icmpv6_packet_too_big_works() {
+        let bytes = [0x02, 0x00, 0x12, 0x34, 0x00, 0x00, 0x05, 0x00];
+        let expectation = Icmpv6Header {
+            code: Icmpv6Code::PacketTooBig,
+            checksum: 0x1234,
+            data: Icmpv6Data::PacketTooBig { mtu: 1280 },
+        };
+        assert_eq!(icmpv6_header(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[test]
+    fn icmpv6_destination_unreachable_works() {
+        let bytes = [0x01, 0x04, 0x12, 0x34, 0x00, 0x00, 0x00, 0x00];
+        let expectation = Icmpv6Header {
+            code: Icmpv6Code::DestinationUnreachable(DestinationUnreachableCode::PortUnreachable),
+            checksum: 0x1234,
+            data: Icmpv6Data::Unused,
+        };
+        assert_eq!(icmpv6_header(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[test]
+    fn icmpv6_neighbor_advertisement_works() {
+        let bytes = [0x88, 0x00, 0x12, 0x34,
+                     0x60, 0x00, 0x00, 0x00, /* Flags */
+                     0xfe, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                     0x02, 0x0c, 0x29, 0xff, 0xfe, 0x4f, 0x1a, 0x3b /* Target address */];
+        let expectation = Icmpv6Header {
+            code: Icmpv6Code::NeighborAdvertisement,
+            checksum: 0x1234,
+            data: Icmpv6Data::NeighborAdvertisement {
+                router: false,
+                solicited: true,
+                override_flag: true,
+                target_addr: IPv6Address(*array_ref![bytes, 8, 16]),
+            },
+        };
+        assert_eq!(icmpv6_header(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[test]
+    fn neighbor_solicitation_with_source_link_layer_address_works() {
+        let bytes = [0x87, 0x00, 0x12, 0x34, 0x00, 0x00, 0x00, 0x00,
+                     0xfe, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                     0x02, 0x0c, 0x29, 0xff, 0xfe, 0x4f, 0x1a, 0x3b, /* Target address */
+                     0x01, 0x01, 0x00, 0x0c, 0x29, 0x9a, 0x5c, 0x10 /* Source link-layer address */];
+        let options = match icmpv6_header(&bytes) {
+            IResult::Done(options, header) => {
+                assert_eq!(header.data,
+                           Icmpv6Data::NeighborSolicitation { target_addr: IPv6Address(*array_ref![bytes, 8, 16]) });
+                options
+            }
+            other => panic!("expected a header, got {:?}", other),
+        };
+        let expectation = vec![NdpOption::SourceLinkLayerAddress(vec![0x00, 0x0c, 0x29, 0x9a, 0x5c, 0x10])];
+        assert_eq!(parse_ndp_options(options), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[test]
+    fn router_advertisement_prefix_and_mtu_options_work() {
+        let bytes = [0x05, 0x01, 0x00, 0x00, 0x00, 0x00, 0x05, 0xdc, /* MTU 1500 */
+                     0x03, 0x04, 0x40, 0xc0, /* Prefix information, /64, L and A */
+                     0x00, 0x27, 0x8d, 0x00, /* Valid lifetime */
+                     0x00, 0x09, 0x3a, 0x80, /* Preferred lifetime */
+                     0x00, 0x00, 0x00, 0x00, /* Reserved */
+                     0x20, 0x01, 0x0d, 0xb8, 0x00, 0x01, 0x00, 0x00,
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00 /* Prefix */];
+        let expectation = vec![
+            NdpOption::Mtu(1500),
+            NdpOption::PrefixInformation {
+                prefix_length: 64,
+                on_link: true,
+                autonomous: true,
+                valid_lifetime: 2592000,
+                preferred_lifetime: 604800,
+                prefix: IPv6Address(*array_ref![bytes, 24, 16]),
+            },
+        ];
+        assert_eq!(parse_ndp_options(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[test]
+    fn ndp_option_with_zero_length_stops_parsing() {
+        let bytes = [0x01, 0x00, 0x00, 0x0c, 0x29, 0x9a, 0x5c, 0x10];
+        assert_eq!(parse_ndp_options(&bytes), IResult::Done(&bytes[..], vec![]));
+    }
+}
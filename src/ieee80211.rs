@@ -0,0 +1,287 @@
+//! Handles parsing of radiotap and IEEE 802.11 MAC headers
+
+use alloc::vec::Vec;
+
+use nom::{IResult, Err, ErrorKind, le_u8, le_u16, le_u32};
+
+use ethernet::{mac_address, MacAddress};
+
+/// Largest number of chained `present` words accepted in a radiotap header
+pub const MAX_RADIOTAP_PRESENT_WORDS: usize = 8;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RadiotapHeader {
+    pub version: u8,
+    /// Length of the whole radiotap header, fields included
+    pub length: u16,
+    /// The `present` bitmaps; a word with bit 31 set is followed by another
+    pub present: Vec<u32>,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FrameType {
+    Management,
+    Control,
+    Data,
+    Extension,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct FrameControl {
+    pub protocol_version: u8,
+    pub frame_type: FrameType,
+    pub subtype: u8,
+    pub to_ds: bool,
+    pub from_ds: bool,
+    pub more_fragments: bool,
+    pub retry: bool,
+    pub power_management: bool,
+    pub more_data: bool,
+    pub protected: bool,
+    pub order: bool,
+}
+/// The MAC header of an 802.11 frame. Which addresses are present depends
+/// on the frame type: control frames carry one or two, management and data
+/// frames three, and data frames between two distribution systems four.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Ieee80211Header {
+    pub frame_control: FrameControl,
+    pub duration: u16,
+    pub addr1: MacAddress,
+    pub addr2: Option<MacAddress>,
+    pub addr3: Option<MacAddress>,
+    pub sequence_control: Option<u16>,
+    pub addr4: Option<MacAddress>,
+    /// Present on QoS data frames
+    pub qos_control: Option<u16>,
+}
+
+impl FrameControl {
+    pub fn is_qos_data(&self) -> bool {
+        self.frame_type == FrameType::Data && self.subtype & 0x08 == 0x08
+    }
+
+    /// Control frames that carry only a receiver address (CTS and ACK)
+    fn has_single_address(&self) -> bool {
+        self.frame_type == FrameType::Control && (self.subtype == 12 || self.subtype == 13)
+    }
+}
+
+impl Ieee80211Header {
+    pub fn sequence_number(&self) -> Option<u16> {
+        self.sequence_control.map(|s| s >> 4)
+    }
+
+    pub fn fragment_number(&self) -> Option<u8> {
+        self.sequence_control.map(|s| (s & 0x0f) as u8)
+    }
+}
+
+fn to_frame_control(first: u8, flags: u8) -> FrameControl {
+    FrameControl {
+        protocol_version: first & 0x03,
+        frame_type: match (first >> 2) & 0x03 {
+            0 => FrameType::Management,
+            1 => FrameType::Control,
+            2 => FrameType::Data,
+            _ => FrameType::Extension,
+        },
+        subtype: first >> 4,
+        to_ds: flags & 0x01 == 0x01,
+        from_ds: flags & 0x02 == 0x02,
+        more_fragments: flags & 0x04 == 0x04,
+        retry: flags & 0x08 == 0x08,
+        power_management: flags & 0x10 == 0x10,
+        more_data: flags & 0x20 == 0x20,
+        protected: flags & 0x40 == 0x40,
+        order: flags & 0x80 == 0x80,
+    }
+}
+
+named!(frame_control<&[u8], FrameControl>, chain!(
+    first: le_u8 ~
+    flags: le_u8,
+    || to_frame_control(first, flags)
+));
+
+/// Parses a radiotap header, skipping its fields so that the remaining
+/// input starts at the 802.11 frame
+pub fn parse_radiotap_header(i: &[u8]) -> IResult<&[u8], RadiotapHeader> {
+    let (mut left, (version, length)) = try_parse!(i, chain!(
+        version: le_u8 ~
+        le_u8 ~
+        length: le_u16,
+        || (version, length)
+    ));
+    let mut present = Vec::new();
+    loop {
+        if present.len() == MAX_RADIOTAP_PRESENT_WORDS {
+            return IResult::Error(Err::Position(ErrorKind::ManyMN, left));
+        }
+        let (rest, word) = try_parse!(left, le_u32);
+        present.push(word);
+        left = rest;
+        if word & 0x8000_0000 == 0 {
+            break;
+        }
+    }
+    let fields_len = match (length as usize).checked_sub(4 + 4 * present.len()) {
+        Some(len) => len,
+        None => return IResult::Error(Err::Position(ErrorKind::LengthValue, i)),
+    };
+    let (left, _) = try_parse!(left, take!(fields_len));
+    IResult::Done(left, RadiotapHeader { version, length, present })
+}
+
+/// Parses the MAC header of an 802.11 frame, leaving the frame body
+pub fn parse_ieee80211_header(i: &[u8]) -> IResult<&[u8], Ieee80211Header> {
+    let (left, (frame_control, duration, addr1)) = try_parse!(i, chain!(
+        frame_control: frame_control ~
+        duration: le_u16 ~
+        addr1: mac_address,
+        || (frame_control, duration, addr1)
+    ));
+    let mut header = Ieee80211Header {
+        frame_control,
+        duration,
+        addr1,
+        addr2: None,
+        addr3: None,
+        sequence_control: None,
+        addr4: None,
+        qos_control: None,
+    };
+    if header.frame_control.has_single_address() {
+        return IResult::Done(left, header);
+    }
+    let (left, addr2) = try_parse!(left, mac_address);
+    header.addr2 = Some(addr2);
+    if header.frame_control.frame_type == FrameType::Control {
+        return IResult::Done(left, header);
+    }
+
+    let (mut left, (addr3, sequence_control)) = try_parse!(left, pair!(mac_address, le_u16));
+    header.addr3 = Some(addr3);
+    header.sequence_control = Some(sequence_control);
+    if header.frame_control.frame_type == FrameType::Data {
+        if header.frame_control.to_ds && header.frame_control.from_ds {
+            let (rest, addr4) = try_parse!(left, mac_address);
+            header.addr4 = Some(addr4);
+            left = rest;
+        }
+        if header.frame_control.is_qos_data() {
+            let (rest, qos_control) = try_parse!(left, le_u16);
+            header.qos_control = Some(qos_control);
+            left = rest;
+        }
+    }
+    IResult::Done(left, header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::IResult;
+
+    #[test]
+    fn radiotap_beacon_frame_works() {
+        let bytes = [0x00, 0x00, 0x0c, 0x00, /* Radiotap version, pad, length 12 */
+                     0x04, 0x80, 0x00, 0x00, /* Present: rate, TX flags */
+                     0x02, 0x00, 0x18, 0x00, /* Rate 1 Mb/s, pad, TX flags */
+                     0x80, 0x00, /* Beacon */
+                     0x00, 0x00, /* Duration */
+                     0xff, 0xff, 0xff, 0xff, 0xff, 0xff, /* Receiver */
+                     0x00, 0x11, 0x22, 0x33, 0x44, 0x55, /* Transmitter */
+                     0x00, 0x11, 0x22, 0x33, 0x44, 0x55, /* BSSID */
+                     0x30, 0x02, /* Sequence 35, fragment 0 */
+                     0x00, 0x01, 0x02, 0x03 /* Body */];
+        let (rest, radiotap) = match parse_radiotap_header(&bytes) {
+            IResult::Done(rest, radiotap) => (rest, radiotap),
+            _ => panic!(),
+        };
+        assert_eq!(radiotap, RadiotapHeader { version: 0, length: 12, present: vec![0x8004] });
+
+        if let IResult::Done(body, header) = parse_ieee80211_header(rest) {
+            assert_eq!(header.frame_control.frame_type, FrameType::Management);
+            assert_eq!(header.frame_control.subtype, 8);
+            assert!(header.addr1.is_broadcast());
+            assert_eq!(header.addr2, Some(MacAddress([0x00, 0x11, 0x22, 0x33, 0x44, 0x55])));
+            assert_eq!(header.addr3, header.addr2);
+            assert_eq!(header.addr4, None);
+            assert_eq!(header.sequence_number(), Some(35));
+            assert_eq!(header.fragment_number(), Some(0));
+            assert_eq!(header.qos_control, None);
+            assert_eq!(body, &[0x00, 0x01, 0x02, 0x03]);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn qos_data_frame_works() {
+        let bytes = [0x88, 0x01, /* QoS data, to DS */
+                     0x2c, 0x00, /* Duration */
+                     0x00, 0x11, 0x22, 0x33, 0x44, 0x55, /* BSSID */
+                     0x9c, 0x5c, 0x8e, 0x90, 0xca, 0xfc, /* Source */
+                     0x00, 0x23, 0x54, 0x07, 0x93, 0x6c, /* Destination */
+                     0x21, 0x05, /* Sequence 82, fragment 1 */
+                     0x06, 0x00, /* QoS control: TID 6 */
+                     0xaa, 0xaa, 0x03];
+        let expectation = Ieee80211Header {
+            frame_control: FrameControl {
+                protocol_version: 0,
+                frame_type: FrameType::Data,
+                subtype: 8,
+                to_ds: true,
+                from_ds: false,
+                more_fragments: false,
+                retry: false,
+                power_management: false,
+                more_data: false,
+                protected: false,
+                order: false,
+            },
+            duration: 44,
+            addr1: MacAddress([0x00, 0x11, 0x22, 0x33, 0x44, 0x55]),
+            addr2: Some(MacAddress([0x9c, 0x5c, 0x8e, 0x90, 0xca, 0xfc])),
+            addr3: Some(MacAddress([0x00, 0x23, 0x54, 0x07, 0x93, 0x6c])),
+            sequence_control: Some(0x0521),
+            addr4: None,
+            qos_control: Some(6),
+        };
+        assert_eq!(parse_ieee80211_header(&bytes), IResult::Done(&bytes[26..], expectation));
+    }
+
+    #[test]
+    fn four_address_and_ack_frames_work() {
+        let mut wds = vec![0x08, 0x03, 0x00, 0x00];
+        for n in 1..5 {
+            wds.extend_from_slice(&[n; 6]);
+            if n == 3 {
+                wds.extend_from_slice(&[0x00, 0x00]);
+            }
+        }
+        if let IResult::Done(rest, header) = parse_ieee80211_header(&wds) {
+            assert!(rest.is_empty());
+            assert_eq!(header.addr4, Some(MacAddress([4; 6])));
+        } else {
+            panic!();
+        }
+
+        let ack = [0xd4, 0x00, 0x00, 0x00, 0x9c, 0x5c, 0x8e, 0x90, 0xca, 0xfc];
+        if let IResult::Done(rest, header) = parse_ieee80211_header(&ack) {
+            assert!(rest.is_empty());
+            assert_eq!(header.frame_control.frame_type, FrameType::Control);
+            assert_eq!(header.addr2, None);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn radiotap_rejects_short_length() {
+        let bytes = [0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00];
+        match parse_radiotap_header(&bytes) {
+            IResult::Error(_) => {}
+            _ => panic!(),
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Handles parsing of IGMP messages
+//!
+//! IGMP runs over IPv4 as `IPProtocol::IGMP`. Version 1 and 2 messages are 8
+//! bytes long, and a longer membership query is a version 3 query, so the
+//! input should be the IP payload trimmed to the length given by IP rather
+//! than including any link layer padding.
+
+use core::iter;
+
+use alloc::vec::Vec;
+
+use nom::{IResult, Err, ErrorKind, be_u8, be_u16};
+
+use ipv4::IPv4Address;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum IgmpMessage {
+    /// A membership query of any version; `group` is 0.0.0.0 in a general
+    /// query
+    Query {
+        /// Maximum response time, 0 in a version 1 query
+        max_resp_code: u8,
+        checksum: u16,
+        group: IPv4Address,
+        /// The fields only present in a version 3 query
+        v3: Option<IgmpV3Query>,
+    },
+    V1Report {
+        checksum: u16,
+        group: IPv4Address,
+    },
+    V2Report {
+        checksum: u16,
+        group: IPv4Address,
+    },
+    V2Leave {
+        checksum: u16,
+        group: IPv4Address,
+    },
+    V3Report {
+        checksum: u16,
+        records: Vec<IgmpGroupRecord>,
+    },
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct IgmpV3Query {
+    /// Suppress Router-Side Processing flag
+    pub suppress: bool,
+    /// Querier's Robustness Variable
+    pub robustness: u8,
+    /// Querier's Query Interval Code
+    pub interval_code: u8,
+    pub sources: Vec<IPv4Address>,
+}
+/// A version 3 report's record of the sources one group is received from
+#[derive(Debug, PartialEq, Eq)]
+pub struct IgmpGroupRecord {
+    /// 1 and 2 report the current INCLUDE or EXCLUDE mode, 3 and 4 a change
+    /// to it, 5 and 6 sources allowed or blocked
+    pub record_type: u8,
+    pub group: IPv4Address,
+    pub sources: Vec<IPv4Address>,
+    pub aux_data: Vec<u8>,
+}
+/// What a message says about membership of a group
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum IgmpAction {
+    Join,
+    Leave,
+    Query,
+}
+
+const MODE_IS_INCLUDE: u8 = 1;
+const CHANGE_TO_INCLUDE_MODE: u8 = 3;
+const ALLOW_NEW_SOURCES: u8 = 5;
+
+impl IgmpMessage {
+    /// The groups this message concerns and what it says about each, for
+    /// building membership tables without matching on every message type.
+    /// A version 3 record including no sources is a leave, and a record only
+    /// blocking sources leaves the group's membership unchanged, so is
+    /// skipped, as is a record of an unknown type.
+    pub fn memberships(&self) -> Vec<(IPv4Address, IgmpAction)> {
+        let (group, action) = match *self {
+            IgmpMessage::Query { group, .. } => (group, IgmpAction::Query),
+            IgmpMessage::V1Report { group, .. } | IgmpMessage::V2Report { group, .. } => {
+                (group, IgmpAction::Join)
+            }
+            IgmpMessage::V2Leave { group, .. } => (group, IgmpAction::Leave),
+            IgmpMessage::V3Report { ref records, .. } => {
+                return records.iter()
+                    .filter_map(|record| match record.record_type {
+                        MODE_IS_INCLUDE | CHANGE_TO_INCLUDE_MODE if record.sources.is_empty() => {
+                            Some((record.group, IgmpAction::Leave))
+                        }
+                        MODE_IS_INCLUDE..=ALLOW_NEW_SOURCES => Some((record.group, IgmpAction::Join)),
+                        _ => None,
+                    })
+                    .collect();
+            }
+        };
+        iter::once((group, action)).collect()
+    }
+}
+
+named!(address<&[u8], IPv4Address>, map!(take!(4), |i: &[u8]| IPv4Address(*array_ref![i, 0, 4])));
+
+named!(v3_query<&[u8], IgmpV3Query>, chain!(
+    flags: be_u8 ~
+    interval_code: be_u8 ~
+    num_sources: be_u16 ~
+    sources: count!(address, num_sources as usize),
+    || IgmpV3Query {
+        suppress: flags & 0x08 == 0x08,
+        robustness: flags & 0x07,
+        interval_code,
+        sources,
+    }
+));
+
+named!(group_record<&[u8], IgmpGroupRecord>, chain!(
+    record_type: be_u8 ~
+    aux_len: be_u8 ~
+    num_sources: be_u16 ~
+    group: address ~
+    sources: count!(address, num_sources as usize) ~
+    aux_data: take!(aux_len as usize * 4),
+    || IgmpGroupRecord { record_type, group, sources, aux_data: aux_data.to_vec() }
+));
+
+fn query(i: &[u8]) -> IResult<&[u8], IgmpMessage> {
+    let (left, (max_resp_code, checksum, group)) = try_parse!(i, chain!(
+        max_resp_code: be_u8 ~
+        checksum: be_u16 ~
+        group: address,
+        || (max_resp_code, checksum, group)
+    ));
+    if left.is_empty() {
+        return IResult::Done(left, IgmpMessage::Query { max_resp_code, checksum, group, v3: None });
+    }
+    map!(left, v3_query,
+         |v3| IgmpMessage::Query { max_resp_code, checksum, group, v3: Some(v3) })
+}
+
+named!(igmp_message<&[u8], IgmpMessage>, switch!(be_u8,
+    0x11 => call!(query) |
+    0x12 => chain!(be_u8 ~ checksum: be_u16 ~ group: address,
+                   || IgmpMessage::V1Report { checksum, group }) |
+    0x16 => chain!(be_u8 ~ checksum: be_u16 ~ group: address,
+                   || IgmpMessage::V2Report { checksum, group }) |
+    0x17 => chain!(be_u8 ~ checksum: be_u16 ~ group: address,
+                   || IgmpMessage::V2Leave { checksum, group }) |
+    0x22 => chain!(
+        be_u8 ~
+        checksum: be_u16 ~
+        be_u16 ~
+        num_records: be_u16 ~
+        records: count!(group_record, num_records as usize),
+        || IgmpMessage::V3Report { checksum, records })
+));
+
+pub fn parse_igmp_message(i: &[u8]) -> IResult<&[u8], IgmpMessage> {
+    match igmp_message(i) {
+        IResult::Incomplete(_) if i.len() >= 8 => {
+            // A count running past the message is malformed, not truncated,
+            // since the input is the whole IP payload
+            IResult::Error(Err::Position(ErrorKind::LengthValue, i))
+        }
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::IResult;
+    const EMPTY_SLICE: &[u8] = &[];
+
+    #[test]
+    fn igmp_v2_join_works() {
+        let bytes = [0x16, 0x00, 0xfa, 0x04, 0xef, 0x01, 0x02, 0x03];
+        let message = IgmpMessage::V2Report { checksum: 0xfa04, group: IPv4Address([239, 1, 2, 3]) };
+        assert_eq!(message.memberships(), vec![(IPv4Address([239, 1, 2, 3]), IgmpAction::Join)]);
+        assert_eq!(parse_igmp_message(&bytes), IResult::Done(EMPTY_SLICE, message));
+    }
+
+    #[test]
+    fn igmp_v2_leave_works() {
+        let bytes = [0x17, 0x00, 0xf9, 0x04, 0xef, 0x01, 0x02, 0x03];
+        match parse_igmp_message(&bytes) {
+            IResult::Done(_, message) => {
+                assert_eq!(message.memberships(),
+                           vec![(IPv4Address([239, 1, 2, 3]), IgmpAction::Leave)]);
+            }
+            other => panic!("expected a message, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn igmp_v3_report_works() {
+        let bytes = [0x22, 0x00, 0xe7, 0xf5, 0x00, 0x00, 0x00, 0x03,
+                     0x04, 0x00, 0x00, 0x00, 0xef, 0x01, 0x02, 0x03, /* Change to exclude {} */
+                     0x03, 0x00, 0x00, 0x00, 0xef, 0x01, 0x02, 0x04, /* Change to include {} */
+                     0x01, 0x01, 0x00, 0x01, 0xe8, 0x01, 0x01, 0x01, /* Include {192.0.2.1} */
+                     0xc0, 0x00, 0x02, 0x01, 0xde, 0xad, 0xbe, 0xef];
+        let message = match parse_igmp_message(&bytes) {
+            IResult::Done(rest, message) => {
+                assert_eq!(rest, EMPTY_SLICE);
+                message
+            }
+            other => panic!("expected a message, got {:?}", other),
+        };
+        if let IgmpMessage::V3Report { ref records, .. } = message {
+            assert_eq!(records[2], IgmpGroupRecord {
+                record_type: 1,
+                group: IPv4Address([232, 1, 1, 1]),
+                sources: vec![IPv4Address([192, 0, 2, 1])],
+                aux_data: vec![0xde, 0xad, 0xbe, 0xef],
+            });
+        } else {
+            panic!("expected a v3 report, got {:?}", message);
+        }
+        assert_eq!(message.memberships(), vec![
+            (IPv4Address([239, 1, 2, 3]), IgmpAction::Join),
+            (IPv4Address([239, 1, 2, 4]), IgmpAction::Leave),
+            (IPv4Address([232, 1, 1, 1]), IgmpAction::Join),
+        ]);
+    }
+
+    #[test]
+    fn igmp_v3_unknown_record_type_is_skipped() {
+        let bytes = [0x22, 0x00, 0xe7, 0xf5, 0x00, 0x00, 0x00, 0x02,
+                     0x07, 0x00, 0x00, 0x00, 0xef, 0x01, 0x02, 0x03, /* Unknown type 7 */
+                     0x05, 0x00, 0x00, 0x01, 0xef, 0x01, 0x02, 0x04, /* Allow {192.0.2.1} */
+                     0xc0, 0x00, 0x02, 0x01];
+        match parse_igmp_message(&bytes) {
+            IResult::Done(_, message) => {
+                assert_eq!(message.memberships(),
+                           vec![(IPv4Address([239, 1, 2, 4]), IgmpAction::Join)]);
+            }
+            other => panic!("expected a message, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn igmp_queries_of_each_version_work() {
+        let v2 = [0x11, 0x64, 0xee, 0x9b, 0x00, 0x00, 0x00, 0x00];
+        let v3 = [0x11, 0x64, 0xec, 0x1e, 0x00, 0x00, 0x00, 0x00, 0x02, 0x7d, 0x00, 0x00];
+        assert_eq!(parse_igmp_message(&v2), IResult::Done(EMPTY_SLICE, IgmpMessage::Query {
+            max_resp_code: 100,
+            checksum: 0xee9b,
+            group: IPv4Address([0, 0, 0, 0]),
+            v3: None,
+        }));
+        match parse_igmp_message(&v3) {
+            IResult::Done(_, message) => {
+                assert_eq!(message.memberships(),
+                           vec![(IPv4Address([0, 0, 0, 0]), IgmpAction::Query)]);
+                if let IgmpMessage::Query { v3: Some(ref query), .. } = message {
+                    assert_eq!(query.robustness, 2);
+                    assert_eq!(query.interval_code, 125);
+                } else {
+                    panic!("expected a v3 query, got {:?}", message);
+                }
+            }
+            other => panic!("expected a message, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,350 @@
+//! Handles parsing of IP protocol numbers shared by IPv4 and IPv6, and of
+//! headers of either version
+
+use core::cmp;
+use core::fmt;
+
+use nom::{IResult, Err, ErrorKind, Needed, be_u8};
+
+use ipv4::{parse_ipv4_header, IPv4Header};
+use ipv6::{parse_ipv6_header, IPv6Header};
+
+/// `ErrorKind::Custom` code raised when an IPv4 or IPv6 parser is given a
+/// header carrying the other version
+pub const IP_VERSION_MISMATCH: u32 = 1;
+
+/// `ErrorKind::Custom` code raised for an IPv4 header whose IHL is below
+/// the five words of the fixed header
+pub const IPV4_IHL_TOO_SHORT: u32 = 2;
+
+/// An IP protocol number this crate has a name for, with the number as the
+/// discriminant so that `as u8` is free
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[repr(u8)]
+pub enum KnownIPProtocol {
+    HOPOPT = 0,
+    ICMP = 1,
+    IGMP = 2,
+    TCP = 6,
+    UDP = 17,
+    IPV6 = 41,
+    IPV6ROUTE = 43,
+    IPV6FRAG = 44,
+    GRE = 47,
+    ESP = 50,
+    AH = 51,
+    ICMP6 = 58,
+    IPV6OPTS = 60,
+    OSPF = 89,
+    L2TP = 115,
+    SCTP = 132,
+    UDPLITE = 136,
+}
+/// The protocol field of an IPv4 header, or a next header field in IPv6.
+/// Numbers without a name are kept as `Other` rather than rejected; build
+/// one from a number with `from_raw`, so that `Other` never holds the
+/// number of a `KnownIPProtocol`.
+#[derive(PartialEq, Eq, Clone, Copy, Hash)]
+pub enum IPProtocol {
+    Known(KnownIPProtocol),
+    Other(u8),
+}
+
+impl KnownIPProtocol {
+    /// The conventional short name, e.g. `"TCP"`
+    pub fn name(&self) -> &'static str {
+        match *self {
+            KnownIPProtocol::HOPOPT => "IPv6 hop-by-hop options",
+            KnownIPProtocol::ICMP => "ICMP",
+            KnownIPProtocol::IGMP => "IGMP",
+            KnownIPProtocol::TCP => "TCP",
+            KnownIPProtocol::UDP => "UDP",
+            KnownIPProtocol::IPV6 => "IPv6",
+            KnownIPProtocol::IPV6ROUTE => "IPv6 routing",
+            KnownIPProtocol::IPV6FRAG => "IPv6 fragment",
+            KnownIPProtocol::GRE => "GRE",
+            KnownIPProtocol::ESP => "ESP",
+            KnownIPProtocol::AH => "AH",
+            KnownIPProtocol::ICMP6 => "ICMPv6",
+            KnownIPProtocol::IPV6OPTS => "IPv6 destination options",
+            KnownIPProtocol::OSPF => "OSPF",
+            KnownIPProtocol::L2TP => "L2TP",
+            KnownIPProtocol::SCTP => "SCTP",
+            KnownIPProtocol::UDPLITE => "UDP-Lite",
+        }
+    }
+}
+
+// Lets the known values be written, and matched on, as before the split
+#[allow(non_upper_case_globals)]
+impl IPProtocol {
+    pub const HOPOPT: IPProtocol = IPProtocol::Known(KnownIPProtocol::HOPOPT);
+    pub const ICMP: IPProtocol = IPProtocol::Known(KnownIPProtocol::ICMP);
+    pub const IGMP: IPProtocol = IPProtocol::Known(KnownIPProtocol::IGMP);
+    pub const TCP: IPProtocol = IPProtocol::Known(KnownIPProtocol::TCP);
+    pub const UDP: IPProtocol = IPProtocol::Known(KnownIPProtocol::UDP);
+    pub const IPV6: IPProtocol = IPProtocol::Known(KnownIPProtocol::IPV6);
+    pub const IPV6ROUTE: IPProtocol = IPProtocol::Known(KnownIPProtocol::IPV6ROUTE);
+    pub const IPV6FRAG: IPProtocol = IPProtocol::Known(KnownIPProtocol::IPV6FRAG);
+    pub const GRE: IPProtocol = IPProtocol::Known(KnownIPProtocol::GRE);
+    pub const ESP: IPProtocol = IPProtocol::Known(KnownIPProtocol::ESP);
+    pub const AH: IPProtocol = IPProtocol::Known(KnownIPProtocol::AH);
+    pub const ICMP6: IPProtocol = IPProtocol::Known(KnownIPProtocol::ICMP6);
+    pub const IPV6OPTS: IPProtocol = IPProtocol::Known(KnownIPProtocol::IPV6OPTS);
+    pub const OSPF: IPProtocol = IPProtocol::Known(KnownIPProtocol::OSPF);
+    pub const L2TP: IPProtocol = IPProtocol::Known(KnownIPProtocol::L2TP);
+    pub const SCTP: IPProtocol = IPProtocol::Known(KnownIPProtocol::SCTP);
+    pub const UDPLITE: IPProtocol = IPProtocol::Known(KnownIPProtocol::UDPLITE);
+
+    /// Classifies a protocol number
+    pub const fn from_raw(raw: u8) -> IPProtocol {
+        let known = match raw {
+            0 => KnownIPProtocol::HOPOPT,
+            1 => KnownIPProtocol::ICMP,
+            2 => KnownIPProtocol::IGMP,
+            6 => KnownIPProtocol::TCP,
+            17 => KnownIPProtocol::UDP,
+            41 => KnownIPProtocol::IPV6,
+            43 => KnownIPProtocol::IPV6ROUTE,
+            44 => KnownIPProtocol::IPV6FRAG,
+            47 => KnownIPProtocol::GRE,
+            50 => KnownIPProtocol::ESP,
+            51 => KnownIPProtocol::AH,
+            58 => KnownIPProtocol::ICMP6,
+            60 => KnownIPProtocol::IPV6OPTS,
+            89 => KnownIPProtocol::OSPF,
+            115 => KnownIPProtocol::L2TP,
+            132 => KnownIPProtocol::SCTP,
+            136 => KnownIPProtocol::UDPLITE,
+            _ => return IPProtocol::Other(raw),
+        };
+        IPProtocol::Known(known)
+    }
+
+    /// The protocol number; for a known one, read straight from the
+    /// discriminant
+    pub const fn to_raw(&self) -> u8 {
+        match *self {
+            IPProtocol::Known(known) => known as u8,
+            IPProtocol::Other(raw) => raw,
+        }
+    }
+
+    /// Whether the value names an IPv6 extension header rather than an
+    /// upper-layer protocol
+    pub fn is_ipv6_extension(&self) -> bool {
+        matches!(*self, IPProtocol::HOPOPT | IPProtocol::IPV6ROUTE | IPProtocol::IPV6FRAG |
+                        IPProtocol::IPV6OPTS | IPProtocol::ESP | IPProtocol::AH)
+    }
+
+    /// The conventional short name, e.g. `"TCP"`, or `"Unknown"`
+    pub fn name(&self) -> &'static str {
+        match *self {
+            IPProtocol::Known(ref known) => known.name(),
+            IPProtocol::Other(_) => "Unknown",
+        }
+    }
+}
+
+impl From<u8> for IPProtocol {
+    fn from(raw: u8) -> IPProtocol {
+        IPProtocol::from_raw(raw)
+    }
+}
+
+impl From<KnownIPProtocol> for IPProtocol {
+    fn from(known: KnownIPProtocol) -> IPProtocol {
+        IPProtocol::Known(known)
+    }
+}
+
+impl fmt::Debug for IPProtocol {
+    /// Writes a known value by name, e.g. `TCP`, and any other as
+    /// `Other(253)`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IPProtocol::Known(ref known) => fmt::Debug::fmt(known, f),
+            IPProtocol::Other(raw) => write!(f, "Other({})", raw),
+        }
+    }
+}
+
+impl PartialOrd for IPProtocol {
+    fn partial_cmp(&self, other: &IPProtocol) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for IPProtocol {
+    /// Orders by number, as the discriminants did before `Other` existed
+    fn cmp(&self, other: &IPProtocol) -> cmp::Ordering {
+        self.to_raw().cmp(&other.to_raw())
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for IPProtocol {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<IPProtocol> {
+        if u.arbitrary()? {
+            Ok(IPProtocol::Known(u.arbitrary()?))
+        } else {
+            Ok(IPProtocol::from_raw(u.arbitrary()?))
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for IPProtocol {
+    /// Writes the protocol number
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.to_raw())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for IPProtocol {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<IPProtocol, D::Error> {
+        <u8 as serde::Deserialize>::deserialize(deserializer).map(IPProtocol::from_raw)
+    }
+}
+
+/// The Explicit Congestion Notification field shared by the IPv4 TOS byte
+/// and the IPv6 traffic class (RFC 3168)
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum EcnCodepoint {
+    /// Not ECN-capable transport
+    NotEct,
+    Ect0,
+    Ect1,
+    /// Congestion experienced
+    Ce,
+}
+
+impl From<u8> for EcnCodepoint {
+    /// Decodes the two low bits of `raw`
+    fn from(raw: u8) -> Self {
+        match raw & 0b11 {
+            0b00 => EcnCodepoint::NotEct,
+            0b10 => EcnCodepoint::Ect0,
+            0b01 => EcnCodepoint::Ect1,
+            _ => EcnCodepoint::Ce,
+        }
+    }
+}
+
+impl From<IPProtocol> for u8 {
+    fn from(p: IPProtocol) -> u8 {
+        p.to_raw()
+    }
+}
+
+named!(pub protocol<&[u8], IPProtocol>, map!(be_u8, IPProtocol::from_raw));
+
+/// The version nibble of the IP header at the start of `i`, for choosing
+/// between `parse_ipv4_header` and `parse_ipv6_header`
+pub fn peek_ip_version(i: &[u8]) -> Option<u8> {
+    i.first().map(|b| b >> 4)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum IpHeader {
+    V4(IPv4Header),
+    V6(IPv6Header),
+}
+
+/// Parses an IPv4 or IPv6 header, chosen by the version nibble, for input
+/// that starts directly at the IP layer such as tun devices or raw IP
+/// captures
+pub fn parse_ip(i: &[u8]) -> IResult<&[u8], IpHeader> {
+    match peek_ip_version(i) {
+        Some(4) => parse_ipv4_header(i).map(IpHeader::V4),
+        Some(6) => parse_ipv6_header(i).map(IpHeader::V6),
+        Some(_) => IResult::Error(Err::Position(ErrorKind::Switch, i)),
+        None => IResult::Incomplete(Needed::Size(1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_ip, peek_ip_version, IpHeader, IPProtocol, KnownIPProtocol};
+    use nom::{IResult, Needed};
+
+    #[test]
+    fn protocol_round_trips_through_u8() {
+        for &raw in &[0, 1, 2, 6, 17, 41, 43, 44, 47, 50, 51, 58, 60, 89, 115, 132, 136] {
+            let protocol = IPProtocol::from_raw(raw);
+            assert!(match protocol { IPProtocol::Known(_) => true, IPProtocol::Other(_) => false });
+            assert_eq!(protocol.to_raw(), raw);
+            assert_eq!(u8::from(protocol), raw);
+        }
+    }
+
+    #[test]
+    fn protocol_casts_to_its_number() {
+        const UDP: u8 = IPProtocol::UDP.to_raw();
+        assert_eq!(UDP, 17);
+        assert_eq!(KnownIPProtocol::SCTP as u8, 132);
+        assert_eq!(IPProtocol::from(132), IPProtocol::SCTP);
+    }
+
+    #[test]
+    fn unknown_protocol_is_kept_as_other() {
+        for &raw in &[3, 0xfd, 255] {
+            let protocol = IPProtocol::from_raw(raw);
+            assert_eq!(protocol, IPProtocol::Other(raw));
+            assert_eq!(protocol.to_raw(), raw);
+        }
+        assert_eq!(format!("{:?}", IPProtocol::Other(253)), "Other(253)");
+        assert_eq!(IPProtocol::Other(253).name(), "Unknown");
+    }
+
+    #[test]
+    fn protocol_names() {
+        assert_eq!(IPProtocol::TCP.name(), "TCP");
+        assert_eq!(IPProtocol::UDP.name(), "UDP");
+        assert_eq!(IPProtocol::ICMP6.name(), "ICMPv6");
+    }
+
+    #[test]
+    fn peek_ip_version_reads_the_first_nibble() {
+        assert_eq!(peek_ip_version(&[0x45, 0x00]), Some(4));
+        assert_eq!(peek_ip_version(&[0x60, 0x00, 0x00, 0x00]), Some(6));
+        assert_eq!(peek_ip_version(&[]), None);
+    }
+
+    #[test]
+    fn parse_ip_dispatches_on_version() {
+        let v4 = [0x45, 0x00, 0x00, 0x1c, 0x1a, 0xe6, 0x00, 0x00,
+                  0x40, 0x01, 0x22, 0xed, 0x0a, 0x2d, 0x00, 0x02,
+                  0x0a, 0x2d, 0x00, 0x01, 0x08, 0x00];
+        match parse_ip(&v4) {
+            IResult::Done(rest, IpHeader::V4(header)) => {
+                assert_eq!(header.protocol, IPProtocol::ICMP);
+                assert_eq!(rest, &[0x08, 0x00]);
+            }
+            other => panic!("expected an IPv4 header, got {:?}", other),
+        }
+
+        let mut v6 = [0u8; 40];
+        v6[0] = 0x60;
+        v6[6] = 17;
+        v6[7] = 64;
+        match parse_ip(&v6) {
+            IResult::Done(rest, IpHeader::V6(header)) => {
+                assert_eq!(header.next_header, IPProtocol::UDP);
+                assert!(rest.is_empty());
+            }
+            other => panic!("expected an IPv6 header, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_ip_rejects_unknown_version() {
+        match parse_ip(&[0x50, 0x00]) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+        assert_eq!(parse_ip(&[]), IResult::Incomplete(Needed::Size(1)));
+    }
+}
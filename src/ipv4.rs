@@ -1,22 +1,83 @@
 //! Handles parsing of IPv4 headers
 
-use nom::{IResult, be_u8};
+use core::fmt;
+use core::net::Ipv4Addr;
 
-#[derive(Debug, PartialEq, Eq)]
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use nom::{IResult, Err, ErrorKind, be_u8};
+#[cfg(feature = "alloc")]
+use nom::{be_u32, eof};
+
+use checksum::ones_complement_sum;
+use ip::{protocol, EcnCodepoint, IP_VERSION_MISMATCH, IPV4_IHL_TOO_SHORT};
+#[cfg(feature = "alloc")]
+use options::for_each_option;
+pub use ip::IPProtocol as IPv4Protocol;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct IPv4Address(pub [u8; 4]);
-#[derive(Debug, PartialEq, Eq)]
-pub enum IPv4Protocol {
-    ICMP,
-    TCP,
-    UDP,
+#[cfg(feature = "alloc")]
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Ipv4Option {
+    EndOfOptions,
+    NoOperation,
+    /// Record Route (RFC 791), laid out like the two source route options.
+    /// One whose route is not a whole number of addresses is parsed as
+    /// `Other`.
+    RecordRoute {
+        pointer: u8,
+        route: Vec<IPv4Address>,
+    },
+    LooseSourceRoute {
+        pointer: u8,
+        route: Vec<IPv4Address>,
+    },
+    StrictSourceRoute {
+        pointer: u8,
+        route: Vec<IPv4Address>,
+    },
+    /// Internet Timestamp (RFC 791). `flag` 0 records timestamps only; 1
+    /// and 3 record an address with each timestamp. `entries` holds only the
+    /// slots before `pointer`, which have been filled in; an undefined flag
+    /// is parsed as `Other`.
+    Timestamp {
+        pointer: u8,
+        /// Number of hosts that could not record a timestamp for lack of space
+        overflow: u8,
+        flag: u8,
+        entries: Vec<Ipv4TimestampEntry>,
+    },
+    /// Router Alert (RFC 2113); a value of 0 asks routers to examine the
+    /// packet. One that does not carry exactly two bytes is parsed as `Other`.
+    RouterAlert(u16),
+    Other {
+        kind: u8,
+        data: Vec<u8>,
+    },
 }
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ipv4TimestampEntry {
+    /// The recording host, present unless the option's flag is 0
+    pub address: Option<IPv4Address>,
+    /// Milliseconds since midnight UT
+    pub timestamp: u32,
+}
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IPv4Header {
     pub version: u8,
     pub ihl: u8,
     pub tos: u8,
     pub length: u16,
     pub id: u16,
+    /// The 3-bit flags field as laid out in RFC 791: bit 0 (`0b100`) is
+    /// reserved, bit 1 (`0b010`) is Don't Fragment and bit 2 (`0b001`) is
+    /// More Fragments.
     pub flags: u8,
     pub fragment_offset: u16,
     pub ttl: u8,
@@ -24,28 +85,422 @@ pub struct IPv4Header {
     pub chksum: u16,
     pub source_addr: IPv4Address,
     pub dest_addr: IPv4Address,
+    /// Only decoded with the `alloc` feature; without it the option bytes
+    /// are skipped unchecked
+    #[cfg(feature = "alloc")]
+    pub options: Vec<Ipv4Option>,
+}
+
+impl IPv4Header {
+    pub fn reserved_bit(&self) -> bool {
+        self.flags & 0b100 == 0b100
+    }
+
+    pub fn dont_fragment(&self) -> bool {
+        self.flags & 0b010 == 0b010
+    }
+
+    pub fn more_fragments(&self) -> bool {
+        self.flags & 0b001 == 0b001
+    }
+
+    /// Whether the header carries options, judged from `ihl` alone so the
+    /// options need not be looked at
+    pub fn has_options(&self) -> bool {
+        self.ihl > 20
+    }
+
+    /// The Differentiated Services Code Point, the upper 6 bits of `tos`
+    pub fn dscp(&self) -> u8 {
+        self.tos >> 2
+    }
+
+    pub fn ecn_codepoint(&self) -> EcnCodepoint {
+        EcnCodepoint::from(self.tos)
+    }
+
+    /// The pseudo-header covered by TCP and UDP checksums, for a transport
+    /// header and payload of `payload_len` bytes
+    pub fn pseudo_header(&self, payload_len: u16) -> [u8; 12] {
+        let mut bytes = [0; 12];
+        bytes[..4].copy_from_slice(&self.source_addr.0);
+        bytes[4..8].copy_from_slice(&self.dest_addr.0);
+        bytes[9] = self.protocol.to_raw();
+        bytes[10..].copy_from_slice(&payload_len.to_be_bytes());
+        bytes
+    }
+
+    pub fn is_from(&self, addr: Ipv4Addr) -> bool {
+        self.source_addr.0 == addr.octets()
+    }
+
+    pub fn is_to(&self, addr: Ipv4Addr) -> bool {
+        self.dest_addr.0 == addr.octets()
+    }
+
+    /// Whether `addr` is the source or the destination
+    pub fn involves(&self, addr: Ipv4Addr) -> bool {
+        self.is_from(addr) || self.is_to(addr)
+    }
+
+    /// Whether the source or the destination lies in `net/prefix`. A prefix
+    /// above 32 is treated as 32.
+    pub fn matches_subnet(&self, net: Ipv4Addr, prefix: u8) -> bool {
+        let mask = match prefix {
+            0 => 0,
+            p => u32::MAX << (32 - p.min(32)),
+        };
+        let net = u32::from(net) & mask;
+        [self.source_addr, self.dest_addr].iter().any(|a| u32::from_be_bytes(a.0) & mask == net)
+    }
+
+    /// Serializes the header in network byte order, padding the options to
+    /// the `ihl` bytes the header declares. The checksum is written as is.
+    #[cfg(feature = "alloc")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.ihl as usize);
+        bytes.push((self.version << 4) | (self.ihl / 4));
+        bytes.push(self.tos);
+        bytes.extend_from_slice(&self.length.to_be_bytes());
+        bytes.extend_from_slice(&self.id.to_be_bytes());
+        bytes.extend_from_slice(&(((self.flags as u16) << 13) | self.fragment_offset).to_be_bytes());
+        bytes.push(self.ttl);
+        bytes.push(self.protocol.to_raw());
+        bytes.extend_from_slice(&self.chksum.to_be_bytes());
+        bytes.extend_from_slice(&self.source_addr.0);
+        bytes.extend_from_slice(&self.dest_addr.0);
+        for option in &self.options {
+            option.write_to(&mut bytes);
+        }
+        if bytes.len() < self.ihl as usize {
+            bytes.resize(self.ihl as usize, 0);
+        }
+        bytes
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Ipv4Option {
+    fn write_to(&self, bytes: &mut Vec<u8>) {
+        let (kind, pointer, data) = match *self {
+            Ipv4Option::EndOfOptions => return bytes.push(0),
+            Ipv4Option::NoOperation => return bytes.push(1),
+            Ipv4Option::RecordRoute { pointer, ref route } => (7, Some(pointer), route_bytes(route)),
+            Ipv4Option::LooseSourceRoute { pointer, ref route } => (131, Some(pointer), route_bytes(route)),
+            Ipv4Option::StrictSourceRoute { pointer, ref route } => (137, Some(pointer), route_bytes(route)),
+            Ipv4Option::Timestamp { pointer, overflow, flag, ref entries } => {
+                let mut data = Vec::with_capacity(1 + entries.len() * 8);
+                data.push(overflow << 4 | flag & 0x0f);
+                for entry in entries {
+                    data.extend(entry.address.iter().flat_map(|a| a.0));
+                    data.extend_from_slice(&entry.timestamp.to_be_bytes());
+                }
+                (68, Some(pointer), data)
+            }
+            Ipv4Option::RouterAlert(value) => (148, None, value.to_be_bytes().to_vec()),
+            Ipv4Option::Other { kind, ref data } => (kind, None, data.clone()),
+        };
+        bytes.push(kind);
+        bytes.push((2 + pointer.is_some() as usize + data.len()) as u8);
+        bytes.extend(pointer);
+        bytes.extend_from_slice(&data);
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn route_bytes(route: &[IPv4Address]) -> Vec<u8> {
+    route.iter().flat_map(|a| a.0).collect()
+}
+
+/// Builds an option-less `IPv4Header` for packet generation. Defaults to a
+/// 20-byte header with TTL 64, protocol TCP and a total length covering the
+/// header alone; `build` leaves the checksum at 0.
+#[derive(Debug)]
+pub struct Ipv4HeaderBuilder {
+    header: IPv4Header,
+}
+
+impl Default for Ipv4HeaderBuilder {
+    fn default() -> Ipv4HeaderBuilder {
+        Ipv4HeaderBuilder {
+            header: IPv4Header {
+                version: 4,
+                ihl: 20,
+                tos: 0,
+                length: 20,
+                id: 0,
+                flags: 0,
+                fragment_offset: 0,
+                ttl: 64,
+                protocol: IPv4Protocol::TCP,
+                chksum: 0,
+                source_addr: IPv4Address([0; 4]),
+                dest_addr: IPv4Address([0; 4]),
+                #[cfg(feature = "alloc")]
+                options: Vec::new(),
+            },
+        }
+    }
+}
+
+impl Ipv4HeaderBuilder {
+    pub fn new() -> Ipv4HeaderBuilder {
+        Ipv4HeaderBuilder::default()
+    }
+
+    pub fn source_addr(mut self, addr: IPv4Address) -> Ipv4HeaderBuilder {
+        self.header.source_addr = addr;
+        self
+    }
+
+    pub fn dest_addr(mut self, addr: IPv4Address) -> Ipv4HeaderBuilder {
+        self.header.dest_addr = addr;
+        self
+    }
+
+    pub fn protocol(mut self, protocol: IPv4Protocol) -> Ipv4HeaderBuilder {
+        self.header.protocol = protocol;
+        self
+    }
+
+    pub fn tos(mut self, tos: u8) -> Ipv4HeaderBuilder {
+        self.header.tos = tos;
+        self
+    }
+
+    pub fn id(mut self, id: u16) -> Ipv4HeaderBuilder {
+        self.header.id = id;
+        self
+    }
+
+    pub fn ttl(mut self, ttl: u8) -> Ipv4HeaderBuilder {
+        self.header.ttl = ttl;
+        self
+    }
+
+    /// Sets the total length of the datagram, header included
+    pub fn length(mut self, length: u16) -> Ipv4HeaderBuilder {
+        self.header.length = length;
+        self
+    }
+
+    pub fn set_dont_fragment(mut self, set: bool) -> Ipv4HeaderBuilder {
+        self.header.flags = (self.header.flags & !0b010) | ((set as u8) << 1);
+        self
+    }
+
+    pub fn set_more_fragments(mut self, set: bool) -> Ipv4HeaderBuilder {
+        self.header.flags = (self.header.flags & !0b001) | set as u8;
+        self
+    }
+
+    /// Sets the fragment offset in 8-byte units
+    pub fn fragment_offset(mut self, offset: u16) -> Ipv4HeaderBuilder {
+        self.header.fragment_offset = offset & 0x1fff;
+        self
+    }
+
+    pub fn build(self) -> IPv4Header {
+        self.header
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for IPv4Header {
+    /// Generates an option-less header whose fields fit their wire widths
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<IPv4Header> {
+        Ok(IPv4Header {
+            version: 4,
+            ihl: 20,
+            tos: u.arbitrary()?,
+            length: u.int_in_range(20..=u16::MAX)?,
+            id: u.arbitrary()?,
+            flags: u.int_in_range(0..=0b011)?,
+            fragment_offset: u.int_in_range(0..=0x1fff)?,
+            ttl: u.arbitrary()?,
+            protocol: u.arbitrary()?,
+            chksum: u.arbitrary()?,
+            source_addr: u.arbitrary()?,
+            dest_addr: u.arbitrary()?,
+            #[cfg(feature = "alloc")]
+            options: Vec::new(),
+        })
+    }
+}
+
+impl From<Ipv4Addr> for IPv4Address {
+    fn from(addr: Ipv4Addr) -> IPv4Address {
+        IPv4Address(addr.octets())
+    }
+}
+
+impl From<IPv4Address> for Ipv4Addr {
+    fn from(addr: IPv4Address) -> Ipv4Addr {
+        Ipv4Addr::from(addr.0)
+    }
+}
+
+impl fmt::Display for IPv4Address {
+    /// Formats in dotted decimal, e.g. `10.10.1.135`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let a = &self.0;
+        write!(f, "{}.{}.{}.{}", a[0], a[1], a[2], a[3])
+    }
 }
 
-fn to_ipv4_protocol(i: u8) -> Option<IPv4Protocol> {
-    match i {
-        1 => Some(IPv4Protocol::ICMP),
-        6 => Some(IPv4Protocol::TCP),
-        17 => Some(IPv4Protocol::UDP),
-        _ => None,
+#[cfg(feature = "serde")]
+impl serde::Serialize for IPv4Address {
+    /// Writes a dotted decimal string for human-readable formats and raw octets otherwise
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for IPv4Address {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<IPv4Address, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = alloc::string::String::deserialize(deserializer)?;
+            s.parse::<Ipv4Addr>()
+                .map(|a| IPv4Address(a.octets()))
+                .map_err(serde::de::Error::custom)
+        } else {
+            <[u8; 4]>::deserialize(deserializer).map(IPv4Address)
+        }
+    }
+}
+
+impl fmt::Display for IPv4Header {
+    /// A one-line summary, e.g. `IPv4 10.10.1.135 -> 10.10.1.180 TCP ttl=64 len=60 [DF]`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "IPv4 {} -> {} {:?} ttl={} len={}",
+               self.source_addr, self.dest_addr, self.protocol, self.ttl, self.length)?;
+        if self.dont_fragment() {
+            write!(f, " [DF]")?;
+        }
+        if self.more_fragments() || self.fragment_offset != 0 {
+            write!(f, " frag={}{}", self.fragment_offset, if self.more_fragments() { "+" } else { "" })?;
+        }
+        Ok(())
     }
 }
 
 fn to_ipv4_address(i: &[u8]) -> IPv4Address {
-    IPv4Address(array_ref![i, 0, 4].clone())
+    IPv4Address(*array_ref![i, 0, 4])
 }
 
 named!(two_nibbles<&[u8], (u8, u8)>, bits!(pair!(take_bits!(u8, 4), take_bits!(u8, 4))));
+fn version_ihl(i: &[u8]) -> IResult<&[u8], (u8, u8)> {
+    match two_nibbles(i) {
+        IResult::Done(_, (version, _)) if version != 4 => {
+            IResult::Error(Err::Position(ErrorKind::Custom(IP_VERSION_MISMATCH), i))
+        }
+        IResult::Done(_, (_, ihl)) if ihl < 5 => {
+            IResult::Error(Err::Position(ErrorKind::Custom(IPV4_IHL_TOO_SHORT), i))
+        }
+        other => other,
+    }
+}
 named!(flag_frag_offset<&[u8], (u8, u16)>, bits!(pair!(take_bits!(u8, 3), take_bits!(u16, 13))));
-named!(protocol<&[u8], IPv4Protocol>, map_opt!(be_u8, to_ipv4_protocol));
 named!(address<&[u8], IPv4Address>, map!(take!(4), to_ipv4_address));
 
-named!(ipparse<&[u8], IPv4Header>,
-       chain!(verihl : two_nibbles ~
+// The option length covers the type and length bytes themselves
+#[cfg(feature = "alloc")]
+named!(option_length<&[u8], u8>, map_opt!(be_u8, |l: u8| l.checked_sub(2)));
+#[cfg(feature = "alloc")]
+named!(option_data<&[u8], &[u8]>, chain!(len: option_length ~ data: take!(len), || data));
+#[cfg(feature = "alloc")]
+named!(route<&[u8], (u8, Vec<IPv4Address>)>, chain!(
+    pointer: be_u8 ~
+    route: many0!(address) ~
+    eof,
+    || (pointer, route)
+));
+
+#[cfg(feature = "alloc")]
+named!(timestamp_entry<&[u8], Ipv4TimestampEntry>, chain!(
+    timestamp: be_u32,
+    || Ipv4TimestampEntry { address: None, timestamp }
+));
+#[cfg(feature = "alloc")]
+named!(addressed_timestamp_entry<&[u8], Ipv4TimestampEntry>, chain!(
+    address: address ~
+    timestamp: be_u32,
+    || Ipv4TimestampEntry { address: Some(address), timestamp }
+));
+#[cfg(feature = "alloc")]
+named!(timestamp<&[u8], Ipv4Option>, chain!(
+    pointer: be_u8 ~
+    overflow_flag: be_u8 ~
+    entries: switch!(value!(overflow_flag & 0x0f),
+        0 => many0!(timestamp_entry) |
+        1 => many0!(addressed_timestamp_entry) |
+        3 => many0!(addressed_timestamp_entry)
+    ) ~
+    eof,
+    || {
+        // The pointer is one past the last filled slot, counting from the
+        // option type at 1 with the first slot at 5
+        let slot_len = if overflow_flag & 0x0f == 0 { 4 } else { 8 };
+        let mut entries = entries;
+        entries.truncate(pointer.saturating_sub(5) as usize / slot_len);
+        Ipv4Option::Timestamp {
+            pointer,
+            overflow: overflow_flag >> 4,
+            flag: overflow_flag & 0x0f,
+            entries,
+        }
+    }
+));
+
+#[cfg(feature = "alloc")]
+fn to_ipv4_option(kind: u8, data: &[u8]) -> Option<Ipv4Option> {
+    match kind {
+        7 | 131 | 137 => {
+            match route(data) {
+                IResult::Done(_, (pointer, route)) => Some(match kind {
+                    7 => Ipv4Option::RecordRoute { pointer, route },
+                    131 => Ipv4Option::LooseSourceRoute { pointer, route },
+                    _ => Ipv4Option::StrictSourceRoute { pointer, route },
+                }),
+                _ => Some(Ipv4Option::Other { kind, data: data.to_vec() }),
+            }
+        }
+        68 => match timestamp(data) {
+            IResult::Done(_, option) => Some(option),
+            _ => Some(Ipv4Option::Other { kind, data: data.to_vec() }),
+        },
+        148 if data.len() == 2 => Some(Ipv4Option::RouterAlert(u16::from_be_bytes([data[0], data[1]]))),
+        _ => Some(Ipv4Option::Other { kind, data: data.to_vec() }),
+    }
+}
+
+#[cfg(feature = "alloc")]
+named!(ipv4_option<&[u8], Ipv4Option>, switch!(be_u8,
+    0 => value!(Ipv4Option::EndOfOptions) |
+    1 => value!(Ipv4Option::NoOperation) |
+    kind => map_opt!(option_data, |data| to_ipv4_option(kind, data))
+));
+
+/// Parses the options area of an IPv4 header. The input must be exactly the
+/// `ihl - 20` option bytes; an option running past the end is an error.
+#[cfg(feature = "alloc")]
+fn ipv4_options(i: &[u8]) -> IResult<&[u8], Vec<Ipv4Option>> {
+    let mut options = Vec::new();
+    for_each_option(i, ipv4_option, |option, rest| {
+        // Everything after the end of the option list is padding
+        let next = if option == Ipv4Option::EndOfOptions { &rest[rest.len()..] } else { rest };
+        options.push(option);
+        Ok(next)
+    }).map(|()| options)
+}
+
+named!(ipv4_fixed<&[u8], (IPv4Header, &[u8])>,
+       chain!(verihl : version_ihl ~
               tos : be_u8 ~
               length : u16!(true) ~
               id : u16!(true) ~
@@ -54,31 +509,61 @@ named!(ipparse<&[u8], IPv4Header>,
               proto : protocol ~
               chksum : u16!(true) ~
               src_addr : address ~
-              dst_addr : address,
-              || { IPv4Header {
+              dst_addr : address ~
+              raw_options : take!(verihl.1 as usize * 4 - 20),
+              || { (IPv4Header {
                   version: verihl.0,
                   ihl: verihl.1 << 2,
-                  tos: tos,
-                  length: length,
-                  id: id,
+                  tos,
+                  length,
+                  id,
                   flags: flagfragoffset.0,
                   fragment_offset: flagfragoffset.1,
-                  ttl: ttl,
+                  ttl,
                   protocol: proto,
-                  chksum: chksum,
+                  chksum,
                   source_addr: src_addr,
                   dest_addr : dst_addr,
-              }}));
+                  #[cfg(feature = "alloc")]
+                  options: Vec::new(),
+              }, raw_options)}));
+
+#[cfg(feature = "alloc")]
+fn ipparse(i: &[u8]) -> IResult<&[u8], IPv4Header> {
+    let (rest, (mut header, raw_options)) = try_parse!(i, ipv4_fixed);
+    let (_, options) = try_parse!(raw_options, ipv4_options);
+    header.options = options;
+    IResult::Done(rest, header)
+}
+
+#[cfg(not(feature = "alloc"))]
+fn ipparse(i: &[u8]) -> IResult<&[u8], IPv4Header> {
+    ipv4_fixed(i).map(|(header, _)| header)
+}
 
 pub fn parse_ipv4_header(i: &[u8]) -> IResult<&[u8], IPv4Header> {
     ipparse(i)
 }
 
+/// Like `parse_ipv4_header`, but also verifies the header checksum. The
+/// `bool` is `checksum_valid`; a mismatch is reported there rather than as a
+/// parse error.
+pub fn parse_ipv4_header_checked(i: &[u8]) -> IResult<&[u8], (IPv4Header, bool)> {
+    let (rest, header) = try_parse!(i, ipparse);
+    let checksum_valid = ones_complement_sum(&i[..i.len() - rest.len()]) == 0xffff;
+    IResult::Done(rest, (header, checksum_valid))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{protocol, IPv4Protocol, ipparse, IPv4Header, IPv4Address};
-    use nom::IResult;
-    const EMPTY_SLICE: &'static [u8] = &[];
+    use super::{protocol, IPv4Protocol, ipparse, parse_ipv4_header_checked, IPv4Header, IPv4Address,
+                Ipv4HeaderBuilder};
+    #[cfg(feature = "alloc")]
+    use super::{Ipv4Option, Ipv4TimestampEntry};
+    use core::net::Ipv4Addr;
+    use ip::{EcnCodepoint, IP_VERSION_MISMATCH, IPV4_IHL_TOO_SHORT};
+    use nom::{IResult, Err, ErrorKind};
+    const EMPTY_SLICE: &[u8] = &[];
     macro_rules! mk_protocol_test {
         ($func_name:ident, $bytes:expr, $correct_proto:expr) => (
             #[test]
@@ -92,6 +577,13 @@ mod tests {
     mk_protocol_test!(protocol_gets_icmp_correct, [1], IPv4Protocol::ICMP);
     mk_protocol_test!(protocol_gets_tcp_correct, [6], IPv4Protocol::TCP);
     mk_protocol_test!(protocol_gets_udp_correct, [17], IPv4Protocol::UDP);
+    mk_protocol_test!(protocol_gets_gre_correct, [47], IPv4Protocol::GRE);
+    mk_protocol_test!(protocol_gets_esp_correct, [50], IPv4Protocol::ESP);
+    mk_protocol_test!(protocol_gets_ah_correct, [51], IPv4Protocol::AH);
+    mk_protocol_test!(protocol_gets_ospf_correct, [89], IPv4Protocol::OSPF);
+    mk_protocol_test!(protocol_gets_l2tp_correct, [115], IPv4Protocol::L2TP);
+    mk_protocol_test!(protocol_gets_sctp_correct, [132], IPv4Protocol::SCTP);
+    mk_protocol_test!(protocol_gets_udplite_correct, [136], IPv4Protocol::UDPLITE);
 
     #[test]
     fn ipparse_gets_packet_correct() {
@@ -119,7 +611,347 @@ mod tests {
             chksum: 0x22ed,
             source_addr: IPv4Address([10, 10, 1, 135]),
             dest_addr: IPv4Address([10, 10, 1, 180]),
+            #[cfg(feature = "alloc")]
+            options: vec![],
         };
         assert_eq!(ipparse(&bytes), IResult::Done(EMPTY_SLICE, expectation));
     }
+
+    #[test]
+    fn ipv4_header_displays_summary() {
+        let bytes = [0x45, 0x00, 0x05, 0xdc, 0x1a, 0xe6, 0x20, 0x00, 0x40, 0x01,
+                     0x22, 0xed, 0x0a, 0x0a, 0x01, 0x87, 0x0a, 0x0a, 0x01, 0xb4];
+        if let IResult::Done(_, header) = ipparse(&bytes) {
+            assert_eq!(header.to_string(), "IPv4 10.10.1.135 -> 10.10.1.180 ICMP ttl=64 len=1500 frag=0+");
+        } else {
+            panic!();
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn ipparse_gets_record_route_option_correct() {
+        let bytes = [0x48, /* IP version and length = 32 */
+                     0x00, /* Differentiated services field */
+                     0x00, 0x2c, /* Total length */
+                     0x1a, 0xe7, /* Identification */
+                     0x40, 0x00, /* flags and fragment offset */
+                     0x40, /* TTL */
+                     0x01, /* protocol */
+                     0x00, 0x00, /* checksum */
+                     0x0a, 0x0a, 0x01, 0x87, /* source IP */
+                     0x0a, 0x0a, 0x01, 0xb4, /* destination IP */
+                     0x01, /* No operation */
+                     0x07, 0x0b, 0x08, /* Record route, length and pointer */
+                     0x0a, 0x0a, 0x01, 0x87, /* First route entry */
+                     0x00, 0x00, 0x00, 0x00, /* Second route entry */
+                     0x08, 0x00, 0xf7, 0xff, 0x00, 0x00, 0x00, 0x00 /* Payload */];
+
+        let expectation = IPv4Header {
+            version: 4,
+            ihl: 32,
+            tos: 0,
+            length: 44,
+            id: 0x1ae7,
+            flags: 0x02,
+            fragment_offset: 0,
+            ttl: 64,
+            protocol: IPv4Protocol::ICMP,
+            chksum: 0,
+            source_addr: IPv4Address([10, 10, 1, 135]),
+            dest_addr: IPv4Address([10, 10, 1, 180]),
+            options: vec![Ipv4Option::NoOperation,
+                          Ipv4Option::RecordRoute {
+                              pointer: 8,
+                              route: vec![IPv4Address([10, 10, 1, 135]),
+                                          IPv4Address([0, 0, 0, 0])],
+                          }],
+        };
+        assert_eq!(ipparse(&bytes), IResult::Done(&bytes[32..], expectation));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn ipparse_gets_timestamp_and_router_alert_options_correct() {
+        let bytes = [0x4b, 0x00, 0x00, 0x2c, 0x1a, 0xe8, 0x00, 0x00,
+                     0x01, 0x11, 0x00, 0x00, /* TTL 1, UDP */
+                     0x0a, 0x0a, 0x01, 0x87, 0xe0, 0x00, 0x00, 0x16,
+                     0x94, 0x04, 0x00, 0x00, /* Router alert */
+                     0x44, 0x14, 0x15, 0x21, /* Timestamp, length, pointer, overflow 2, flag 1 */
+                     0x0a, 0x0a, 0x01, 0x87, 0x00, 0x36, 0xee, 0x80, /* First entry */
+                     0x0a, 0x0a, 0x01, 0x01, 0x00, 0x36, 0xee, 0x81 /* Second entry */];
+        let options = match ipparse(&bytes) {
+            IResult::Done(rest, header) => {
+                assert!(rest.is_empty());
+                header.options
+            }
+            other => panic!("expected a header, got {:?}", other),
+        };
+        assert_eq!(options, vec![
+            Ipv4Option::RouterAlert(0),
+            Ipv4Option::Timestamp {
+                pointer: 0x15,
+                overflow: 2,
+                flag: 1,
+                entries: vec![
+                    Ipv4TimestampEntry { address: Some(IPv4Address([10, 10, 1, 135])), timestamp: 3_600_000 },
+                    Ipv4TimestampEntry { address: Some(IPv4Address([10, 10, 1, 1])), timestamp: 3_600_001 },
+                ],
+            },
+        ]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn timestamp_only_option_round_trips() {
+        let mut header = Ipv4HeaderBuilder::new().build();
+        header.ihl = 32;
+        header.options = vec![Ipv4Option::Timestamp {
+            pointer: 13,
+            overflow: 0,
+            flag: 0,
+            entries: vec![Ipv4TimestampEntry { address: None, timestamp: 1 },
+                          Ipv4TimestampEntry { address: None, timestamp: 2 }],
+        }];
+        let bytes = header.to_bytes();
+        assert_eq!(&bytes[20..], &[0x44, 0x0c, 0x0d, 0x00, 0x00, 0x00, 0x00, 0x01,
+                                   0x00, 0x00, 0x00, 0x02]);
+        assert_eq!(ipparse(&bytes), IResult::Done(EMPTY_SLICE, header));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn timestamp_entries_stop_at_pointer() {
+        let bytes = [0x48, 0x00, 0x00, 0x20, 0x1a, 0xe8, 0x00, 0x00,
+                     0x40, 0x11, 0x00, 0x00, 0x0a, 0x0a, 0x01, 0x87,
+                     0x0a, 0x0a, 0x01, 0xb4,
+                     0x44, 0x0c, 0x09, 0x00, /* Timestamp, length 12, pointer 9, flag 0 */
+                     0x00, 0x36, 0xee, 0x80, /* Filled slot */
+                     0x00, 0x00, 0x00, 0x00 /* Empty slot */];
+        match ipparse(&bytes) {
+            IResult::Done(_, header) => assert_eq!(header.options, vec![Ipv4Option::Timestamp {
+                pointer: 9,
+                overflow: 0,
+                flag: 0,
+                entries: vec![Ipv4TimestampEntry { address: None, timestamp: 3_600_000 }],
+            }]),
+            other => panic!("expected a header, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn malformed_options_are_other() {
+        // A route must be whole addresses; a timestamp flag of 2 is
+        // undefined; a router alert must carry two bytes
+        for option in &[[0x07, 0x08, 0x04, 0x0a, 0x0a, 0x01, 0x87, 0x00],
+                        [0x44, 0x08, 0x05, 0x02, 0x00, 0x00, 0x00, 0x01],
+                        [0x94, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]] {
+            let mut bytes = vec![0x47, 0x00, 0x00, 0x1c, 0x1a, 0xe8, 0x00, 0x00,
+                                 0x40, 0x11, 0x00, 0x00, 0x0a, 0x0a, 0x01, 0x87,
+                                 0x0a, 0x0a, 0x01, 0xb4];
+            bytes.extend_from_slice(option);
+            match ipparse(&bytes) {
+                IResult::Done(_, header) => assert_eq!(header.options[0], Ipv4Option::Other {
+                    kind: option[0],
+                    data: option[2..option[1] as usize].to_vec(),
+                }),
+                other => panic!("expected a header, got {:?}", other),
+            }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn ipparse_rejects_option_overrunning_header() {
+        let bytes = [0x46, 0x00, 0x00, 0x18, 0x1a, 0xe7, 0x40, 0x00,
+                     0x40, 0x01, 0x00, 0x00,
+                     0x0a, 0x0a, 0x01, 0x87,
+                     0x0a, 0x0a, 0x01, 0xb4,
+                     0x07, 0x0b, 0x04, 0x00, /* Record route claiming 11 bytes */
+                     0x00, 0x00, 0x00, 0x00];
+        match ipparse(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn ipparse_rejects_option_with_short_length() {
+        let bytes = [0x46, 0x00, 0x00, 0x18, 0x1a, 0xe7, 0x40, 0x00,
+                     0x40, 0x01, 0x00, 0x00,
+                     0x0a, 0x0a, 0x01, 0x87,
+                     0x0a, 0x0a, 0x01, 0xb4,
+                     0x94, 0x01, 0x00, 0x00 /* Router alert with length 1 */];
+        match ipparse(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn checked_parse_verifies_checksum() {
+        let mut bytes = [0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11,
+                         0xb8, 0x61, 0xc0, 0xa8, 0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7, 0x00];
+        match parse_ipv4_header_checked(&bytes) {
+            IResult::Done(rest, (header, checksum_valid)) => {
+                assert_eq!(header.chksum, 0xb861);
+                assert!(checksum_valid);
+                assert_eq!(rest, &[0x00]);
+            }
+            other => panic!("expected a header, got {:?}", other),
+        }
+
+        bytes[11] ^= 0x01;
+        match parse_ipv4_header_checked(&bytes) {
+            IResult::Done(_, (_, checksum_valid)) => assert!(!checksum_valid),
+            other => panic!("expected a header, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ipparse_rejects_ipv6_header() {
+        let bytes = [0x60, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x11, 0x40,
+                     0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00,
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01];
+        assert_eq!(ipparse(&bytes),
+                   IResult::Error(Err::Position(ErrorKind::Custom(IP_VERSION_MISMATCH), &bytes[..])));
+    }
+
+    #[test]
+    fn ipparse_rejects_ihl_below_five() {
+        let bytes = [0x44, 0x00, 0x00, 0x14, 0x1a, 0x2b, 0x00, 0x00,
+                     0x40, 0x06, 0x00, 0x00, 0x0a, 0x0a, 0x01, 0x87,
+                     0x0a, 0x0a, 0x01, 0xb4];
+        assert_eq!(ipparse(&bytes),
+                   IResult::Error(Err::Position(ErrorKind::Custom(IPV4_IHL_TOO_SHORT), &bytes[..])));
+    }
+
+    #[test]
+    fn ipparse_decodes_flags() {
+        let fragment = [0x45, 0x00, 0x05, 0xdc, 0x1a, 0xe6,
+                        0x20, 0x00, /* More fragments */
+                        0x40, 0x01, 0x22, 0xed,
+                        0x0a, 0x0a, 0x01, 0x87,
+                        0x0a, 0x0a, 0x01, 0xb4];
+        let dont_fragment = [0x45, 0x00, 0x05, 0xdc, 0x1a, 0xe6,
+                             0x40, 0x00, /* Don't fragment */
+                             0x40, 0x01, 0x22, 0xed,
+                             0x0a, 0x0a, 0x01, 0x87,
+                             0x0a, 0x0a, 0x01, 0xb4];
+
+        if let IResult::Done(_, header) = ipparse(&fragment) {
+            assert!(!header.reserved_bit());
+            assert!(!header.dont_fragment());
+            assert!(header.more_fragments());
+        } else {
+            panic!();
+        }
+        if let IResult::Done(_, header) = ipparse(&dont_fragment) {
+            assert!(!header.reserved_bit());
+            assert!(header.dont_fragment());
+            assert!(!header.more_fragments());
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn pseudo_header_works() {
+        let header = Ipv4HeaderBuilder::new()
+            .source_addr(IPv4Address([192, 168, 0, 108]))
+            .dest_addr(IPv4Address([208, 97, 177, 124]))
+            .protocol(IPv4Protocol::TCP)
+            .build();
+        assert_eq!(header.pseudo_header(36), [0xc0, 0xa8, 0x00, 0x6c, 0xd0, 0x61, 0xb1, 0x7c,
+                                              0x00, 0x06, 0x00, 0x24]);
+    }
+
+    #[test]
+    fn has_options_follows_ihl() {
+        let mut header = Ipv4HeaderBuilder::new().build();
+        assert!(!header.has_options());
+        header.ihl = 24;
+        assert!(header.has_options());
+    }
+
+    #[test]
+    fn ipparse_decodes_dscp_and_ecn() {
+        let bytes = [0x45, 0xc2, /* CS6, ECT(0) */
+                     0x00, 0x14, 0x1a, 0xe6, 0x00, 0x00,
+                     0x40, 0x01, 0x22, 0xed,
+                     0x0a, 0x0a, 0x01, 0x87,
+                     0x0a, 0x0a, 0x01, 0xb4];
+        if let IResult::Done(_, header) = ipparse(&bytes) {
+            assert_eq!(header.dscp(), 48);
+            assert_eq!(header.ecn_codepoint(), EcnCodepoint::Ect0);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn address_predicates_work() {
+        let header = Ipv4HeaderBuilder::new()
+            .source_addr(IPv4Address([192, 168, 1, 20]))
+            .dest_addr(IPv4Address([8, 8, 8, 8]))
+            .build();
+        assert!(header.is_from(Ipv4Addr::new(192, 168, 1, 20)));
+        assert!(!header.is_to(Ipv4Addr::new(192, 168, 1, 20)));
+        assert!(header.involves(Ipv4Addr::new(8, 8, 8, 8)));
+        assert!(header.matches_subnet(Ipv4Addr::new(192, 168, 1, 0), 24));
+        assert!(!header.matches_subnet(Ipv4Addr::new(192, 168, 2, 0), 24));
+        assert!(header.matches_subnet(Ipv4Addr::new(0, 0, 0, 0), 0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn ipv4_address_serde_is_human_readable_only_for_json() {
+        let addr = IPv4Address([10, 10, 1, 135]);
+        let json = ::serde_json::to_string(&addr).unwrap();
+        assert_eq!(json, "\"10.10.1.135\"");
+        assert_eq!(::serde_json::from_str::<IPv4Address>(&json).unwrap(), addr);
+        assert!(::serde_json::from_str::<IPv4Address>("\"10.10.1\"").is_err());
+
+        let encoded = ::bincode::serialize(&addr).unwrap();
+        assert_eq!(encoded, addr.0);
+        assert_eq!(::bincode::deserialize::<IPv4Address>(&encoded).unwrap(), addr);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn ipv4_header_builder_serializes_minimal_header() {
+        let header = Ipv4HeaderBuilder::new()
+            .source_addr(IPv4Address([10, 10, 1, 135]))
+            .dest_addr(IPv4Address([10, 10, 1, 180]))
+            .protocol(IPv4Protocol::UDP)
+            .id(0x1ae6)
+            .length(28)
+            .set_dont_fragment(true)
+            .build();
+        assert_eq!(header.chksum, 0);
+        assert!(header.dont_fragment());
+        assert!(!header.more_fragments());
+
+        let bytes = header.to_bytes();
+        assert_eq!(bytes, [0x45, 0x00, 0x00, 0x1c, 0x1a, 0xe6, 0x40, 0x00,
+                           0x40, 0x11, 0x00, 0x00, 0x0a, 0x0a, 0x01, 0x87,
+                           0x0a, 0x0a, 0x01, 0xb4]);
+        assert_eq!(ipparse(&bytes), IResult::Done(EMPTY_SLICE, header));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn ipv4_header_to_bytes_pads_options() {
+        let mut header = Ipv4HeaderBuilder::new().build();
+        header.ihl = 28;
+        header.options = vec![
+            Ipv4Option::RecordRoute { pointer: 4, route: vec![IPv4Address([0; 4])] },
+        ];
+        let bytes = header.to_bytes();
+        assert_eq!(&bytes[20..], &[0x07, 0x07, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        header.options.push(Ipv4Option::EndOfOptions);
+        assert_eq!(ipparse(&bytes), IResult::Done(EMPTY_SLICE, header));
+    }
 }
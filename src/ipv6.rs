@@ -0,0 +1,517 @@
+//! Handles parsing of IPv6 headers
+
+use core::fmt;
+use core::net::Ipv6Addr;
+
+use nom::{IResult, Err, ErrorKind, be_u8, be_u16, be_u32};
+
+use ip::{protocol, EcnCodepoint, IPProtocol, IP_VERSION_MISMATCH};
+
+// IPv6 Header Format (RFC 8200)
+//
+//    0                   1                   2                   3
+//    0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |Version|    DS     |ECN|             Flow Label                |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |         Payload Length        |  Next Header  |   Hop Limit   |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |                                                               |
+//   +                         Source Address                        +
+//   |                                                               |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |                                                               |
+//   +                      Destination Address                      +
+//   |                                                               |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct IPv6Address(pub [u8; 16]);
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct IPv6Header {
+    pub version: u8,
+    pub ds: u8,
+    pub ecn: u8,
+    pub flow_label: u32,
+    /// Length of the payload following this header, in bytes
+    pub length: u16,
+    pub next_header: IPProtocol,
+    pub hop_limit: u8,
+    pub source_addr: IPv6Address,
+    pub dest_addr: IPv6Address,
+}
+
+// Fragment Header Format (RFC 8200)
+//
+//    0                   1                   2                   3
+//    0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |  Next Header  |   Reserved    |      Fragment Offset    |Res|M|
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |                         Identification                        |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Ipv6FragmentHeader {
+    pub next_header: IPProtocol,
+    /// Offset of this fragment in 8-octet units
+    pub fragment_offset: u16,
+    pub more_fragments: bool,
+    pub identification: u32,
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for IPv6Header {
+    /// Generates a header whose fields fit their wire widths
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<IPv6Header> {
+        Ok(IPv6Header {
+            version: 6,
+            ds: u.int_in_range(0..=0x3f)?,
+            ecn: u.int_in_range(0..=0x03)?,
+            flow_label: u.int_in_range(0..=0xfffff)?,
+            length: u.arbitrary()?,
+            next_header: u.arbitrary()?,
+            hop_limit: u.arbitrary()?,
+            source_addr: u.arbitrary()?,
+            dest_addr: u.arbitrary()?,
+        })
+    }
+}
+
+impl IPv6Header {
+    /// Whether an extension header, rather than the upper-layer protocol,
+    /// follows this header
+    pub fn has_extension_headers(&self) -> bool {
+        self.next_header.is_ipv6_extension()
+    }
+
+    /// The Differentiated Services Code Point, the same as `ds`
+    pub fn dscp(&self) -> u8 {
+        self.ds
+    }
+
+    pub fn ecn_codepoint(&self) -> EcnCodepoint {
+        EcnCodepoint::from(self.ecn)
+    }
+
+    /// The pseudo-header covered by upper-layer checksums (RFC 8200), for
+    /// an upper-layer header and payload of `upper_layer_len` bytes. With
+    /// extension headers present, neither that length nor `next_header`
+    /// come from this header, hence both are passed in.
+    pub fn pseudo_header(&self, upper_layer_len: u32, next_header: IPProtocol) -> [u8; 40] {
+        let mut bytes = [0; 40];
+        bytes[..16].copy_from_slice(&self.source_addr.0);
+        bytes[16..32].copy_from_slice(&self.dest_addr.0);
+        bytes[32..36].copy_from_slice(&upper_layer_len.to_be_bytes());
+        bytes[39] = next_header.to_raw();
+        bytes
+    }
+
+    pub fn is_from(&self, addr: Ipv6Addr) -> bool {
+        self.source_addr.0 == addr.octets()
+    }
+
+    pub fn is_to(&self, addr: Ipv6Addr) -> bool {
+        self.dest_addr.0 == addr.octets()
+    }
+
+    /// Whether `addr` is the source or the destination
+    pub fn involves(&self, addr: Ipv6Addr) -> bool {
+        self.is_from(addr) || self.is_to(addr)
+    }
+
+    /// Whether the source or the destination lies in `net/prefix`. A prefix
+    /// above 128 is treated as 128.
+    pub fn matches_subnet(&self, net: Ipv6Addr, prefix: u8) -> bool {
+        let mask = match prefix {
+            0 => 0,
+            p => u128::MAX << (128 - p.min(128)),
+        };
+        let net = u128::from(net) & mask;
+        [self.source_addr, self.dest_addr].iter().any(|a| u128::from_be_bytes(a.0) & mask == net)
+    }
+}
+
+impl From<Ipv6Addr> for IPv6Address {
+    fn from(addr: Ipv6Addr) -> IPv6Address {
+        IPv6Address(addr.octets())
+    }
+}
+
+impl From<IPv6Address> for Ipv6Addr {
+    fn from(addr: IPv6Address) -> Ipv6Addr {
+        Ipv6Addr::from(addr.0)
+    }
+}
+
+impl fmt::Display for IPv6Address {
+    /// Formats as recommended by RFC 5952, e.g. `2001:db8::1`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&Ipv6Addr::from(self.0), f)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for IPv6Address {
+    /// Writes an RFC 5952 string for human-readable formats and raw octets otherwise
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for IPv6Address {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<IPv6Address, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = alloc::string::String::deserialize(deserializer)?;
+            s.parse::<Ipv6Addr>()
+                .map(|a| IPv6Address(a.octets()))
+                .map_err(serde::de::Error::custom)
+        } else {
+            <[u8; 16]>::deserialize(deserializer).map(IPv6Address)
+        }
+    }
+}
+
+impl fmt::Display for IPv6Header {
+    /// A one-line summary, e.g. `IPv6 2001:db8::1 -> 2001:db8::2 UDP hop_limit=64 len=12`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "IPv6 {} -> {} {:?} hop_limit={} len={}",
+               self.source_addr, self.dest_addr, self.next_header, self.hop_limit, self.length)
+    }
+}
+
+named!(ver_ds_ecn_flow<&[u8], (u8, u8, u8, u32)>,
+    bits!(tuple!(
+        take_bits!(u8, 4),
+        take_bits!(u8, 6),
+        take_bits!(u8, 2),
+        take_bits!(u32, 20))));
+
+fn version_fields(i: &[u8]) -> IResult<&[u8], (u8, u8, u8, u32)> {
+    match ver_ds_ecn_flow(i) {
+        IResult::Done(_, (version, _, _, _)) if version != 6 => {
+            IResult::Error(Err::Position(ErrorKind::Custom(IP_VERSION_MISMATCH), i))
+        }
+        other => other,
+    }
+}
+
+named!(address<&[u8], IPv6Address>, map!(take!(16), |i: &[u8]| IPv6Address(*array_ref![i, 0, 16])));
+
+named!(ipv6_header<&[u8], IPv6Header>, chain!(
+    ver_ds_ecn_flow: version_fields ~
+    length: be_u16 ~
+    next_header: protocol ~
+    hop_limit: be_u8 ~
+    source_addr: address ~
+    dest_addr: address,
+    || IPv6Header {
+        version: ver_ds_ecn_flow.0,
+        ds: ver_ds_ecn_flow.1,
+        ecn: ver_ds_ecn_flow.2,
+        flow_label: ver_ds_ecn_flow.3,
+        length,
+        next_header,
+        hop_limit,
+        source_addr,
+        dest_addr,
+    }
+));
+
+pub fn parse_ipv6_header(i: &[u8]) -> IResult<&[u8], IPv6Header> {
+    ipv6_header(i)
+}
+
+named!(offset_res_m<&[u8], (u16, u8, u8)>,
+    bits!(tuple!(
+        take_bits!(u16, 13),
+        take_bits!(u8, 2),
+        take_bits!(u8, 1))));
+
+named!(fragment_header<&[u8], Ipv6FragmentHeader>, chain!(
+    next_header: protocol ~
+    be_u8 ~
+    offset_res_m: offset_res_m ~
+    identification: be_u32,
+    || Ipv6FragmentHeader {
+        next_header,
+        fragment_offset: offset_res_m.0,
+        more_fragments: offset_res_m.2 == 1,
+        identification,
+    }
+));
+
+pub fn parse_ipv6_fragment_header(i: &[u8]) -> IResult<&[u8], Ipv6FragmentHeader> {
+    fragment_header(i)
+}
+
+// Hop-by-Hop, Routing and Destination Options headers share a layout: the
+// next header, then the length in 8-octet units not counting the first 8
+named!(generic_extension<&[u8], IPProtocol>, chain!(
+    next_header: protocol ~
+    len: be_u8 ~
+    take!(6 + len as usize * 8),
+    || next_header
+));
+
+// AH gives its length in 4-octet units less 2, and is at least the 12
+// fixed bytes long; see `ah::parse_ah_header`
+named!(ah_extension<&[u8], IPProtocol>, chain!(
+    next_header: protocol ~
+    len: map_opt!(be_u8, |l: u8| if l >= 1 { Some(l as usize * 4 + 6) } else { None }) ~
+    take!(len),
+    || next_header
+));
+
+/// Skips the extension headers at the start of `payload`, the input after a
+/// header whose next header field is `next_header`. Returns the input at the
+/// upper-layer header with its protocol, and the Fragment header if one was
+/// passed. AH is skipped like the other extension headers, so that its
+/// integrity check value is not mistaken for the upper layer. Nothing after
+/// a Fragment header with a non-zero offset is a header, and nothing after
+/// ESP can be read, so the walk stops there.
+pub fn skip_extension_headers(next_header: IPProtocol, payload: &[u8])
+                              -> IResult<&[u8], (IPProtocol, Option<Ipv6FragmentHeader>)> {
+    let mut next_header = next_header;
+    let mut fragment = None;
+    let mut left = payload;
+    loop {
+        match next_header {
+            IPProtocol::HOPOPT | IPProtocol::IPV6ROUTE | IPProtocol::IPV6OPTS => {
+                let (rest, after) = try_parse!(left, generic_extension);
+                next_header = after;
+                left = rest;
+            }
+            IPProtocol::AH => {
+                let (rest, after) = try_parse!(left, ah_extension);
+                next_header = after;
+                left = rest;
+            }
+            IPProtocol::IPV6FRAG => {
+                let (rest, header) = try_parse!(left, fragment_header);
+                next_header = header.next_header;
+                left = rest;
+                let first = header.fragment_offset == 0;
+                fragment = Some(header);
+                if !first {
+                    break;
+                }
+            }
+            _ => break,
+        }
+    }
+    IResult::Done(left, (next_header, fragment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fragment_header, ipv6_header, skip_extension_headers, Ipv6FragmentHeader, IPv6Address,
+                IPv6Header};
+    use core::net::Ipv6Addr;
+    use ip::{EcnCodepoint, IPProtocol};
+    use nom::IResult;
+    const EMPTY_SLICE: &[u8] = &[];
+
+    macro_rules! mk_fragment_test {
+        ($func_name:ident, $bytes:expr, $offset:expr, $more:expr) => (
+            #[test]
+            fn $func_name() {
+                let bytes = $bytes;
+                let expectation = Ipv6FragmentHeader {
+                    next_header: IPProtocol::UDP,
+                    fragment_offset: $offset,
+                    more_fragments: $more,
+                    identification: 0xf88eb466,
+                };
+                assert_eq!(fragment_header(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+            }
+        )
+    }
+
+    mk_fragment_test!(fragment_header_gets_first_fragment_correct,
+                      [0x11, 0x00, 0x00, 0x01, 0xf8, 0x8e, 0xb4, 0x66], 0, true);
+    mk_fragment_test!(fragment_header_gets_middle_fragment_correct,
+                      [0x11, 0x00, 0x05, 0xa9, 0xf8, 0x8e, 0xb4, 0x66], 181, true);
+    mk_fragment_test!(fragment_header_gets_last_fragment_correct,
+                      [0x11, 0x00, 0x0b, 0x50, 0xf8, 0x8e, 0xb4, 0x66], 362, false);
+
+    #[test]
+    fn fragment_header_ignores_reserved_bits() {
+        let bytes = [0x11, 0xff, 0x05, 0xaf, 0xf8, 0x8e, 0xb4, 0x66];
+        let expectation = Ipv6FragmentHeader {
+            next_header: IPProtocol::UDP,
+            fragment_offset: 181,
+            more_fragments: true,
+            identification: 0xf88eb466,
+        };
+        assert_eq!(fragment_header(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[test]
+    fn ipv6_header_works() {
+        let bytes = [0x6b, 0x81, 0x23, 0x45, /* Version, DS, ECN and flow label */
+                     0x00, 0x0c, /* Payload length */
+                     0x11, /* Next header */
+                     0x40, /* Hop limit */
+                     0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00,
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, /* Source address */
+                     0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00,
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, /* Destination address */
+                     0xc3, 0x50, 0x00, 0x35, 0x00, 0x0c, 0x00, 0x00 /* UDP header */];
+        let expectation = IPv6Header {
+            version: 6,
+            ds: 46,
+            ecn: 0,
+            flow_label: 0x12345,
+            length: 12,
+            next_header: IPProtocol::UDP,
+            hop_limit: 64,
+            source_addr: IPv6Address(*array_ref![bytes, 8, 16]),
+            dest_addr: IPv6Address(*array_ref![bytes, 24, 16]),
+        };
+        assert_eq!(expectation.to_string(), "IPv6 2001:db8::1 -> 2001:db8::2 UDP hop_limit=64 len=12");
+        assert_eq!(ipv6_header(&bytes), IResult::Done(&bytes[40..], expectation));
+    }
+
+    #[test]
+    fn extension_headers_are_detected_from_next_header() {
+        let mut bytes = [0u8; 40];
+        bytes[0] = 0x60;
+        bytes[6] = 0x11;
+        match ipv6_header(&bytes) {
+            IResult::Done(_, header) => assert!(!header.has_extension_headers()),
+            other => panic!("expected a header, got {:?}", other),
+        }
+        for &next_header in &[0x00, 0x2b, 0x2c, 0x3c] {
+            bytes[6] = next_header;
+            match ipv6_header(&bytes) {
+                IResult::Done(_, header) => assert!(header.has_extension_headers()),
+                other => panic!("expected a header, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn pseudo_header_works() {
+        let mut bytes = [0u8; 40];
+        bytes[0] = 0x60;
+        bytes[4..6].copy_from_slice(&[0x00, 0x0c]); /* Payload length 12 */
+        bytes[6] = 0x11;
+        bytes[8] = 0x20;
+        bytes[23] = 0x01;
+        bytes[24] = 0x20;
+        bytes[39] = 0x02;
+        let header = match ipv6_header(&bytes) {
+            IResult::Done(_, header) => header,
+            other => panic!("expected a header, got {:?}", other),
+        };
+        let mut expectation = [0u8; 40];
+        expectation[0] = 0x20;
+        expectation[15] = 0x01;
+        expectation[16] = 0x20;
+        expectation[31] = 0x02;
+        expectation[35] = 0x0c;
+        expectation[39] = 0x11;
+        assert_eq!(header.pseudo_header(12, IPProtocol::UDP)[..], expectation[..]);
+
+        // Neither is read from the header, as with extension headers present
+        expectation[35] = 0x14;
+        expectation[39] = 0x06;
+        assert_eq!(header.pseudo_header(20, IPProtocol::TCP)[..], expectation[..]);
+        expectation[33] = 0x01;
+        assert_eq!(header.pseudo_header(0x10014, IPProtocol::TCP)[..], expectation[..]);
+    }
+
+    #[test]
+    fn ipv6_header_decodes_dscp_and_ecn() {
+        let mut bytes = [0u8; 40];
+        bytes[0] = 0x6c; /* Traffic class CS6, ECT(0) */
+        bytes[1] = 0x20;
+        bytes[6] = 0x11;
+        if let IResult::Done(_, header) = ipv6_header(&bytes) {
+            assert_eq!(header.dscp(), 48);
+            assert_eq!(header.ecn_codepoint(), EcnCodepoint::Ect0);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn address_predicates_work() {
+        let mut bytes = [0u8; 40];
+        bytes[0] = 0x60;
+        bytes[6] = 0x11;
+        bytes[8..24].copy_from_slice(&Ipv6Addr::new(0x2001, 0xdb8, 0, 1, 0, 0, 0, 0x20).octets());
+        bytes[24..40].copy_from_slice(&Ipv6Addr::new(0x2001, 0xdb8, 0xffff, 0, 0, 0, 0, 1).octets());
+        if let IResult::Done(_, header) = ipv6_header(&bytes) {
+            assert!(header.is_from(Ipv6Addr::new(0x2001, 0xdb8, 0, 1, 0, 0, 0, 0x20)));
+            assert!(header.involves(Ipv6Addr::new(0x2001, 0xdb8, 0xffff, 0, 0, 0, 0, 1)));
+            assert!(header.matches_subnet(Ipv6Addr::new(0x2001, 0xdb8, 0, 1, 0, 0, 0, 0), 64));
+            assert!(!header.matches_subnet(Ipv6Addr::new(0x2001, 0xdb8, 0, 2, 0, 0, 0, 0), 64));
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn ipv6_header_rejects_ipv4_header() {
+        let mut bytes = [0u8; 40];
+        bytes[0] = 0x45;
+        match ipv6_header(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn ipv6_address_serde_is_human_readable_only_for_json() {
+        let addr = IPv6Address([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01]);
+        let json = ::serde_json::to_string(&addr).unwrap();
+        assert_eq!(json, "\"2001:db8::1\"");
+        assert_eq!(::serde_json::from_str::<IPv6Address>(&json).unwrap(), addr);
+
+        let encoded = ::bincode::serialize(&addr).unwrap();
+        assert_eq!(encoded, addr.0);
+        assert_eq!(::bincode::deserialize::<IPv6Address>(&encoded).unwrap(), addr);
+    }
+
+    #[test]
+    fn skip_extension_headers_reaches_upper_layer() {
+        let bytes = [0x2c, 0x00, 0x01, 0x04, 0x00, 0x00, 0x00, 0x00, /* Hop-by-Hop, PadN */
+                     0x11, 0x00, 0x00, 0x01, 0x00, 0x00, 0x12, 0x34, /* First fragment of UDP */
+                     0xc3, 0x50, 0x00, 0x35];
+        let fragment = Ipv6FragmentHeader {
+            next_header: IPProtocol::UDP,
+            fragment_offset: 0,
+            more_fragments: true,
+            identification: 0x1234,
+        };
+        assert_eq!(skip_extension_headers(IPProtocol::HOPOPT, &bytes),
+                   IResult::Done(&bytes[16..], (IPProtocol::UDP, Some(fragment))));
+        assert_eq!(skip_extension_headers(IPProtocol::TCP, &bytes),
+                   IResult::Done(&bytes[..], (IPProtocol::TCP, None)));
+
+        let mut later = bytes;
+        later[10..12].copy_from_slice(&[0x05, 0xc8]); /* Offset 185, last fragment */
+        match skip_extension_headers(IPProtocol::HOPOPT, &later) {
+            IResult::Done(rest, (IPProtocol::UDP, Some(ref header))) => {
+                assert_eq!(header.fragment_offset, 185);
+                assert_eq!(rest, &later[16..]);
+            }
+            other => panic!("expected a fragment, got {:?}", other),
+        }
+
+        match skip_extension_headers(IPProtocol::HOPOPT, &bytes[..6]) {
+            IResult::Incomplete(_) => {}
+            other => panic!("expected incomplete, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Handles parsing of LACP data units
+//!
+//! An LACPDU is the payload of an Ethernet frame with
+//! `EtherType::SlowProtocols` whose first byte is `LACP_SUBTYPE`.
+
+use nom::{IResult, be_u8, be_u16};
+
+use ethernet::{mac_address, MacAddress};
+
+/// The Slow Protocols subtype of LACP
+pub const LACP_SUBTYPE: u8 = 1;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct LacpPdu {
+    pub version: u8,
+    pub actor: LacpPortInfo,
+    pub partner: LacpPortInfo,
+    /// Collector Max Delay, in tens of microseconds
+    pub collector_max_delay: u16,
+}
+/// The actor or partner information, identifying one end of a link
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct LacpPortInfo {
+    pub system_priority: u16,
+    pub system: MacAddress,
+    pub key: u16,
+    pub port_priority: u16,
+    pub port: u16,
+    pub state: LacpState,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct LacpState {
+    /// Active rather than passive LACP
+    pub activity: bool,
+    /// Short rather than long timeout
+    pub timeout: bool,
+    pub aggregation: bool,
+    pub synchronization: bool,
+    pub collecting: bool,
+    pub distributing: bool,
+    pub defaulted: bool,
+    pub expired: bool,
+}
+
+impl From<u8> for LacpState {
+    fn from(raw: u8) -> Self {
+        LacpState {
+            activity: raw & 0x01 == 0x01,
+            timeout: raw & 0x02 == 0x02,
+            aggregation: raw & 0x04 == 0x04,
+            synchronization: raw & 0x08 == 0x08,
+            collecting: raw & 0x10 == 0x10,
+            distributing: raw & 0x20 == 0x20,
+            defaulted: raw & 0x40 == 0x40,
+            expired: raw & 0x80 == 0x80,
+        }
+    }
+}
+
+named!(port_info<&[u8], LacpPortInfo>, chain!(
+    system_priority: be_u16 ~
+    system: mac_address ~
+    key: be_u16 ~
+    port_priority: be_u16 ~
+    port: be_u16 ~
+    state: be_u8 ~
+    take!(3),
+    || LacpPortInfo {
+        system_priority,
+        system,
+        key,
+        port_priority,
+        port,
+        state: LacpState::from(state),
+    }
+));
+
+named!(lacp_pdu<&[u8], LacpPdu>, chain!(
+    tag!([LACP_SUBTYPE]) ~
+    version: be_u8 ~
+    tag!([0x01, 0x14]) ~
+    actor: port_info ~
+    tag!([0x02, 0x14]) ~
+    partner: port_info ~
+    tag!([0x03, 0x10]) ~
+    collector_max_delay: be_u16 ~
+    take!(12) ~
+    tag!([0x00, 0x00]),
+    || LacpPdu { version, actor, partner, collector_max_delay }
+));
+
+/// Parses an LACPDU up to its Terminator TLV, leaving the reserved bytes
+/// that follow in the remaining input
+pub fn parse_lacp_pdu(i: &[u8]) -> IResult<&[u8], LacpPdu> {
+    lacp_pdu(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethernet::{parse_ethernet_frame, EtherType};
+    use nom::IResult;
+
+    #[test]
+    fn lacpdu_works() {
+        let mut bytes = vec![0x01, 0x80, 0xc2, 0x00, 0x00, 0x02, /* Destination */
+                             0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9c, /* Source */
+                             0x88, 0x09, /* Slow Protocols */
+                             0x01, 0x01, /* LACP, version 1 */
+                             0x01, 0x14, 0x80, 0x00, 0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                             0x00, 0x0d, 0x00, 0xff, 0x00, 0x02, 0x3d, 0x00, 0x00, 0x00, /* Actor */
+                             0x02, 0x14, 0x80, 0x00, 0x00, 0x26, 0x0b, 0x4c, 0x2e, 0x00,
+                             0x00, 0x21, 0x00, 0xff, 0x00, 0x05, 0x0d, 0x00, 0x00, 0x00, /* Partner */
+                             0x03, 0x10, 0x00, 0x05, /* Collector */
+                             0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                             0x00, 0x00 /* Terminator */];
+        bytes.extend_from_slice(&[0; 50]);
+        let lacpdu = match parse_ethernet_frame(&bytes) {
+            IResult::Done(rest, frame) => {
+                assert_eq!(frame.ethertype, EtherType::SlowProtocols);
+                rest
+            }
+            other => panic!("expected a frame, got {:?}", other),
+        };
+        let pdu = match parse_lacp_pdu(lacpdu) {
+            IResult::Done(rest, pdu) => {
+                assert_eq!(rest, &[0; 50][..]);
+                pdu
+            }
+            other => panic!("expected an LACPDU, got {:?}", other),
+        };
+        assert_eq!(pdu.actor, LacpPortInfo {
+            system_priority: 0x8000,
+            system: MacAddress([0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b]),
+            key: 13,
+            port_priority: 255,
+            port: 2,
+            state: LacpState {
+                activity: true,
+                timeout: false,
+                aggregation: true,
+                synchronization: true,
+                collecting: true,
+                distributing: true,
+                defaulted: false,
+                expired: false,
+            },
+        });
+        assert_eq!(pdu.partner.key, 33);
+        assert!(pdu.partner.state.synchronization && !pdu.partner.state.collecting);
+        assert_eq!(pdu.collector_max_delay, 5);
+    }
+
+    #[test]
+    fn lacp_actor_with_wrong_length_is_an_error() {
+        let bytes = [0x01, 0x01, 0x01, 0x12, 0x80, 0x00];
+        match parse_lacp_pdu(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+}
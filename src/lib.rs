@@ -1,8 +1,116 @@
+//! Parsers for network packet headers
+//!
+//! The crate is `no_std` when built without the default `std` feature. The
+//! `alloc` feature, which `std` enables, gates everything built on `Vec`:
+//! option lists, owned payloads and most protocol modules. Without it the
+//! Ethernet, IPv4, IPv6, TCP, UDP, ESP, STP and LACP headers still parse,
+//! with IPv4 options skipped and TCP options left to
+//! `TcpHeader::options_iter`. Only the `reassembly` module, which keeps
+//! timers and hash maps, and the `pcap` module, which reads through
+//! `std::io`, require `std`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(any(feature = "std", test))]
+extern crate core;
+#[cfg(feature = "alloc")]
+extern crate alloc;
 #[macro_use]
 extern crate nom;
 #[macro_use]
 extern crate arrayref;
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(test)]
+extern crate serde_json;
+#[cfg(test)]
+extern crate bincode;
+
+// nom 1's macros expand to `::std::...` paths, which resolve to this module
+// when the standard library is absent
+#[cfg(not(any(feature = "std", test)))]
+mod std {
+    #[cfg(feature = "alloc")]
+    pub use alloc::{boxed, vec};
+    pub use core::{ops, option};
+}
 
+#[cfg(feature = "alloc")]
+pub mod ah;
+#[cfg(feature = "alloc")]
+pub mod arp;
+#[cfg(feature = "alloc")]
+pub mod bgp;
+#[cfg(feature = "alloc")]
+pub mod cdp;
+pub mod checksum;
+#[cfg(feature = "alloc")]
+pub mod complete;
+#[cfg(feature = "alloc")]
+pub mod cursor;
+#[cfg(feature = "alloc")]
+pub mod dhcp;
+#[cfg(feature = "alloc")]
+pub mod dhcpv6;
+#[cfg(feature = "alloc")]
+pub mod dns;
+#[cfg(feature = "alloc")]
+pub mod eapol;
+pub mod error;
+pub mod esp;
+pub mod flow;
 pub mod ethernet;
+#[cfg(feature = "alloc")]
+pub mod gtp;
+#[cfg(feature = "alloc")]
+pub mod igmp;
+pub mod ip;
+pub mod lacp;
+#[cfg(feature = "alloc")]
+pub mod lldp;
 pub mod ipv4;
+pub mod ipv6;
 pub mod tcp;
+#[cfg(feature = "alloc")]
+pub mod tls;
+#[cfg(feature = "alloc")]
+pub mod http;
+#[cfg(feature = "alloc")]
+pub mod icmp;
+#[cfg(feature = "alloc")]
+pub mod icmpv6;
+#[cfg(feature = "alloc")]
+pub mod ieee80211;
+#[cfg(feature = "std")]
+pub mod pcap;
+#[cfg(feature = "std")]
+pub mod reassembly;
+#[cfg(feature = "alloc")]
+pub mod modbus;
+#[cfg(feature = "alloc")]
+pub mod mpls;
+mod options;
+#[cfg(feature = "alloc")]
+pub mod ospf;
+#[cfg(feature = "alloc")]
+pub mod ptp;
+#[cfg(feature = "alloc")]
+pub mod rtp;
+#[cfg(feature = "alloc")]
+pub mod sctp;
+#[cfg(feature = "alloc")]
+pub mod sll;
+#[cfg(feature = "alloc")]
+pub mod someip;
+#[cfg(feature = "alloc")]
+pub mod ssh;
+pub mod stp;
+#[cfg(feature = "alloc")]
+pub mod stream;
+pub mod udp;
+#[cfg(feature = "alloc")]
+pub mod packet;
+#[cfg(feature = "alloc")]
+pub mod result;
@@ -0,0 +1,131 @@
+//! Handles parsing of LLDP data units
+//!
+//! An LLDPDU is the payload of an Ethernet frame with `EtherType::LLDP`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use nom::{IResult, Err, ErrorKind};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LldpTlv {
+    /// End of LLDPDU; always the last TLV returned
+    End,
+    ChassisId {
+        subtype: u8,
+        id: Vec<u8>,
+    },
+    PortId {
+        subtype: u8,
+        id: Vec<u8>,
+    },
+    /// Seconds the receiver may keep this information
+    Ttl(u16),
+    PortDescription(String),
+    SystemName(String),
+    SystemDescription(String),
+    /// A TLV type this crate does not decode, with its value bytes
+    Unknown {
+        tlv_type: u8,
+        value: Vec<u8>,
+    },
+}
+
+/// Upper bound on TLVs in one LLDPDU, so a frame without an End TLV cannot
+/// grow the list without limit
+pub const MAX_LLDP_TLVS: usize = 256;
+
+named!(tlv_header<&[u8], (u8, u16)>, bits!(pair!(take_bits!(u8, 7), take_bits!(u16, 9))));
+
+fn to_lldp_tlv(tlv_type: u8, value: &[u8]) -> Option<LldpTlv> {
+    match tlv_type {
+        0 if value.is_empty() => Some(LldpTlv::End),
+        1 | 2 if !value.is_empty() => {
+            let (subtype, id) = (value[0], value[1..].to_vec());
+            Some(if tlv_type == 1 {
+                LldpTlv::ChassisId { subtype, id }
+            } else {
+                LldpTlv::PortId { subtype, id }
+            })
+        }
+        3 if value.len() == 2 => Some(LldpTlv::Ttl((value[0] as u16) << 8 | value[1] as u16)),
+        4 => Some(LldpTlv::PortDescription(String::from_utf8_lossy(value).into_owned())),
+        5 => Some(LldpTlv::SystemName(String::from_utf8_lossy(value).into_owned())),
+        6 => Some(LldpTlv::SystemDescription(String::from_utf8_lossy(value).into_owned())),
+        0..=3 => None,
+        _ => Some(LldpTlv::Unknown { tlv_type, value: value.to_vec() }),
+    }
+}
+
+named!(lldp_tlv<&[u8], LldpTlv>, map_opt!(
+    chain!(
+        header: tlv_header ~
+        value: take!(header.1),
+        || (header.0, value)
+    ),
+    |(tlv_type, value): (u8, &[u8])| to_lldp_tlv(tlv_type, value)
+));
+
+/// Parses TLVs up to and including the End of LLDPDU TLV
+pub fn parse_lldp_tlvs(i: &[u8]) -> IResult<&[u8], Vec<LldpTlv>> {
+    let mut tlvs = Vec::new();
+    let mut left = i;
+    loop {
+        if tlvs.len() == MAX_LLDP_TLVS {
+            return IResult::Error(Err::Position(ErrorKind::ManyMN, left));
+        }
+        let (rest, tlv) = try_parse!(left, lldp_tlv);
+        left = rest;
+        if tlv == LldpTlv::End {
+            tlvs.push(tlv);
+            return IResult::Done(left, tlvs);
+        }
+        tlvs.push(tlv);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_lldp_tlvs, LldpTlv};
+    use ethernet::{parse_ethernet_frame, EtherType};
+    use nom::IResult;
+
+    #[test]
+    fn basic_lldp_frame_works() {
+        let bytes = [0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e, /* Destination */
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b, /* Source */
+                     0x88, 0xcc, /* LLDP */
+                     0x02, 0x07, 0x04, 0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b, /* Chassis ID: MAC */
+                     0x04, 0x06, 0x05, b'e', b't', b'h', b'0', b'1', /* Port ID: interface name */
+                     0x06, 0x02, 0x00, 0x78, /* TTL 120 */
+                     0x0a, 0x04, b's', b'w', b'0', b'1', /* System name */
+                     0xfe, 0x06, 0x00, 0x80, 0xc2, 0x01, 0x00, 0x01, /* Organizationally specific */
+                     0x00, 0x00, /* End */
+                     0x00, 0x00 /* Padding */];
+        let lldpdu = match parse_ethernet_frame(&bytes) {
+            IResult::Done(rest, frame) => {
+                assert_eq!(frame.ethertype, EtherType::LLDP);
+                rest
+            }
+            _ => panic!(),
+        };
+        let expectation = vec![
+            LldpTlv::ChassisId { subtype: 4, id: vec![0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b] },
+            LldpTlv::PortId { subtype: 5, id: b"eth01".to_vec() },
+            LldpTlv::Ttl(120),
+            LldpTlv::SystemName("sw01".into()),
+            LldpTlv::Unknown { tlv_type: 127, value: vec![0x00, 0x80, 0xc2, 0x01, 0x00, 0x01] },
+            LldpTlv::End,
+        ];
+        assert_eq!(parse_lldp_tlvs(lldpdu), IResult::Done(&[0x00, 0x00][..], expectation));
+    }
+
+    #[test]
+    fn lldp_ttl_with_bad_length_is_an_error() {
+        let bytes = [0x06, 0x01, 0x78, 0x00, 0x00];
+        match parse_lldp_tlvs(&bytes) {
+            IResult::Error(_) => {}
+            _ => panic!(),
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Handles parsing of Modbus/TCP application data units
+//!
+//! An ADU is an MBAP header followed by a PDU of a function code and its
+//! data. The layout of the data differs between requests and responses, so
+//! there is a parser for each direction; requests are sent to `MODBUS_PORT`.
+
+use alloc::vec::Vec;
+
+use nom::{IResult, Err, ErrorKind, be_u8, be_u16, eof};
+
+/// The TCP port Modbus servers listen on
+pub const MODBUS_PORT: u16 = 502;
+
+pub const READ_HOLDING_REGISTERS: u8 = 0x03;
+pub const WRITE_SINGLE_REGISTER: u8 = 0x06;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ModbusAdu {
+    /// Pairs a response with its request
+    pub transaction_id: u16,
+    /// 0 for Modbus
+    pub protocol_id: u16,
+    /// Length of the rest of the ADU, the unit id included
+    pub length: u16,
+    pub unit_id: u8,
+    pub pdu: ModbusPdu,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub enum ModbusPdu {
+    ReadHoldingRegistersRequest {
+        start_address: u16,
+        quantity: u16,
+    },
+    ReadHoldingRegistersResponse(Vec<u16>),
+    /// A Write Single Register request, or the response echoing it
+    WriteSingleRegister {
+        address: u16,
+        value: u16,
+    },
+    /// A response reporting that the request failed. `function_code` is
+    /// that of the request, without the high bit set on the wire.
+    Exception {
+        function_code: u8,
+        exception_code: u8,
+    },
+    /// A function code this crate does not decode, with its data
+    Other {
+        function_code: u8,
+        data: Vec<u8>,
+    },
+}
+
+named!(address_value<&[u8], (u16, u16)>, chain!(
+    first: be_u16 ~
+    second: be_u16 ~
+    eof,
+    || (first, second)
+));
+
+named!(registers<&[u8], Vec<u16> >, chain!(
+    byte_count: be_u8 ~
+    registers: count!(be_u16, byte_count as usize / 2) ~
+    eof,
+    || registers
+));
+
+fn to_modbus_pdu(function_code: u8, data: &[u8], response: bool) -> Option<ModbusPdu> {
+    match (function_code, response) {
+        (READ_HOLDING_REGISTERS, false) => match address_value(data) {
+            IResult::Done(_, (start_address, quantity)) => {
+                Some(ModbusPdu::ReadHoldingRegistersRequest { start_address, quantity })
+            }
+            _ => None,
+        },
+        (READ_HOLDING_REGISTERS, true) => match registers(data) {
+            IResult::Done(_, registers) => Some(ModbusPdu::ReadHoldingRegistersResponse(registers)),
+            _ => None,
+        },
+        (WRITE_SINGLE_REGISTER, _) => match address_value(data) {
+            IResult::Done(_, (address, value)) => Some(ModbusPdu::WriteSingleRegister { address, value }),
+            _ => None,
+        },
+        (f, true) if f & 0x80 == 0x80 => match *data {
+            [exception_code] => Some(ModbusPdu::Exception { function_code: f & 0x7f, exception_code }),
+            _ => None,
+        },
+        _ => Some(ModbusPdu::Other { function_code, data: data.to_vec() }),
+    }
+}
+
+// The length counts the unit id and the PDU, which has at least a function
+// code
+fn modbus_adu(i: &[u8], response: bool) -> IResult<&[u8], ModbusAdu> {
+    let (left, (transaction_id, protocol_id, length)) = try_parse!(i, tuple!(
+        be_u16,
+        be_u16,
+        map_opt!(be_u16, |l: u16| if l >= 2 { Some(l) } else { None })
+    ));
+    let (left, (unit_id, function_code, data)) = try_parse!(left, tuple!(
+        be_u8,
+        be_u8,
+        take!(length - 2)
+    ));
+    match to_modbus_pdu(function_code, data, response) {
+        Some(pdu) => IResult::Done(left, ModbusAdu { transaction_id, protocol_id, length, unit_id, pdu }),
+        None => IResult::Error(Err::Position(ErrorKind::MapOpt, i)),
+    }
+}
+
+/// Parses an ADU sent by a client
+pub fn parse_modbus_request(i: &[u8]) -> IResult<&[u8], ModbusAdu> {
+    modbus_adu(i, false)
+}
+
+/// Parses an ADU sent by a server
+pub fn parse_modbus_response(i: &[u8]) -> IResult<&[u8], ModbusAdu> {
+    modbus_adu(i, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::IResult;
+    const EMPTY_SLICE: &[u8] = &[];
+
+    #[test]
+    fn modbus_read_holding_registers_works() {
+        let request = [0x00, 0x2a, 0x00, 0x00, 0x00, 0x06, /* Transaction 42, Modbus, length 6 */
+                       0x11, 0x03, /* Unit 17, Read Holding Registers */
+                       0x00, 0x6b, 0x00, 0x03 /* Start address 107, 3 registers */];
+        let expectation = ModbusAdu {
+            transaction_id: 42,
+            protocol_id: 0,
+            length: 6,
+            unit_id: 17,
+            pdu: ModbusPdu::ReadHoldingRegistersRequest { start_address: 107, quantity: 3 },
+        };
+        assert_eq!(parse_modbus_request(&request), IResult::Done(EMPTY_SLICE, expectation));
+
+        let response = [0x00, 0x2a, 0x00, 0x00, 0x00, 0x09,
+                        0x11, 0x03, 0x06, /* Byte count */
+                        0x02, 0x2b, 0x00, 0x00, 0x00, 0x64];
+        let expectation = ModbusAdu {
+            transaction_id: 42,
+            protocol_id: 0,
+            length: 9,
+            unit_id: 17,
+            pdu: ModbusPdu::ReadHoldingRegistersResponse(vec![0x022b, 0x0000, 0x0064]),
+        };
+        assert_eq!(parse_modbus_response(&response), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[test]
+    fn modbus_write_single_register_and_exception_work() {
+        let request = [0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x06, 0x00, 0x01, 0x00, 0x03];
+        match parse_modbus_request(&request) {
+            IResult::Done(_, adu) => {
+                assert_eq!(adu.pdu, ModbusPdu::WriteSingleRegister { address: 1, value: 3 });
+            }
+            other => panic!("expected an ADU, got {:?}", other),
+        }
+
+        let response = [0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x01, 0x86, 0x02];
+        match parse_modbus_response(&response) {
+            IResult::Done(_, adu) => {
+                assert_eq!(adu.pdu, ModbusPdu::Exception { function_code: 6, exception_code: 2 });
+            }
+            other => panic!("expected an ADU, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn modbus_other_function_is_raw_and_bad_length_is_an_error() {
+        let request = [0x00, 0x01, 0x00, 0x00, 0x00, 0x03, 0x01, 0x2b, 0x0e];
+        match parse_modbus_request(&request) {
+            IResult::Done(_, adu) => {
+                assert_eq!(adu.pdu, ModbusPdu::Other { function_code: 0x2b, data: vec![0x0e] });
+            }
+            other => panic!("expected an ADU, got {:?}", other),
+        }
+
+        let request = [0x00, 0x01, 0x00, 0x00, 0x00, 0x05, 0x01, 0x03, 0x00, 0x6b, 0x00];
+        match parse_modbus_request(&request) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Handles parsing of MPLS label stacks
+
+use alloc::vec::Vec;
+
+use nom::IResult;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct MplsLabel {
+    pub label: u32,
+    pub traffic_class: u8,
+    pub bottom_of_stack: bool,
+    pub ttl: u8,
+}
+
+named!(mpls_label<&[u8], MplsLabel>, map!(
+    bits!(tuple!(
+        take_bits!(u32, 20),
+        take_bits!(u8, 3),
+        take_bits!(u8, 1),
+        take_bits!(u8, 8))),
+    |(label, traffic_class, bos, ttl): (u32, u8, u8, u8)| MplsLabel {
+        label,
+        traffic_class,
+        bottom_of_stack: bos == 1,
+        ttl,
+    }
+));
+
+/// Parses label stack entries up to and including the one with the
+/// bottom-of-stack bit set
+pub fn parse_mpls_stack(i: &[u8]) -> IResult<&[u8], Vec<MplsLabel>> {
+    let mut labels = Vec::new();
+    let mut left = i;
+    loop {
+        let (rest, label) = try_parse!(left, mpls_label);
+        let bottom_of_stack = label.bottom_of_stack;
+        labels.push(label);
+        left = rest;
+        if bottom_of_stack {
+            return IResult::Done(left, labels);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_mpls_stack, MplsLabel};
+    use nom::IResult;
+
+    #[test]
+    fn mpls_stack_works() {
+        let bytes = [0x00, 0x01, 0x20, 0xfe, /* Label 18, TC 0, TTL 254 */
+                     0x00, 0x01, 0x0b, 0xfe, /* Label 16, TC 5, bottom of stack, TTL 254 */
+                     0x45, 0x00];
+        let expectation = vec![
+            MplsLabel { label: 18, traffic_class: 0, bottom_of_stack: false, ttl: 254 },
+            MplsLabel { label: 16, traffic_class: 5, bottom_of_stack: true, ttl: 254 },
+        ];
+        assert_eq!(parse_mpls_stack(&bytes), IResult::Done(&bytes[8..], expectation));
+    }
+
+    #[test]
+    fn mpls_stack_without_bottom_is_incomplete() {
+        let bytes = [0x00, 0x01, 0x20, 0xfe];
+        match parse_mpls_stack(&bytes) {
+            IResult::Incomplete(_) => {}
+            other => panic!("expected incomplete, got {:?}", other),
+        }
+    }
+}
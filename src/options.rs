@@ -0,0 +1,31 @@
+//! The loop shared by the parsers of option and TLV lists
+
+use nom::{Err, ErrorKind, IResult};
+
+/// Applies `parser` until the input is used up, handing each item and the
+/// input that follows it to `f`. `f` returns the input to carry on from,
+/// which lets it skip padding, or end the list early by returning an empty
+/// slice. An error kind returned by `f` is reported at the start of the
+/// item, and an item running past the end of the input is an
+/// `ErrorKind::LengthValue` error.
+pub fn for_each_option<'a, T, P, F>(i: &'a [u8], parser: P, mut f: F) -> IResult<&'a [u8], ()>
+    where P: Fn(&'a [u8]) -> IResult<&'a [u8], T>,
+          F: FnMut(T, &'a [u8]) -> Result<&'a [u8], ErrorKind>
+{
+    let mut left = i;
+    while !left.is_empty() {
+        match parser(left) {
+            IResult::Done(rest, item) => {
+                left = match f(item, rest) {
+                    Ok(next) => next,
+                    Err(kind) => return IResult::Error(Err::Position(kind, left)),
+                };
+            }
+            IResult::Error(e) => return IResult::Error(e),
+            IResult::Incomplete(_) => {
+                return IResult::Error(Err::Position(ErrorKind::LengthValue, left))
+            }
+        }
+    }
+    IResult::Done(left, ())
+}
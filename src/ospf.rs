@@ -0,0 +1,194 @@
+//! Handles parsing of OSPFv2 packets
+//!
+//! OSPF runs directly over IP as `IPProtocol::OSPF`.
+
+use alloc::vec::Vec;
+
+use nom::{IResult, Err, ErrorKind, be_u8, be_u16, be_u32};
+
+use ipv4::IPv4Address;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum OspfPacketType {
+    Hello,
+    DatabaseDescription,
+    LinkStateRequest,
+    LinkStateUpdate,
+    LinkStateAck,
+    Other(u8),
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct OspfHeader {
+    pub version: u8,
+    pub packet_type: OspfPacketType,
+    /// Length of the whole OSPF packet, this header included
+    pub length: u16,
+    pub router_id: IPv4Address,
+    pub area_id: IPv4Address,
+    pub checksum: u16,
+    pub auth_type: u16,
+    pub authentication: [u8; 8],
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct OspfHello {
+    pub network_mask: IPv4Address,
+    pub hello_interval: u16,
+    pub options: u8,
+    pub router_priority: u8,
+    pub dead_interval: u32,
+    pub designated_router: IPv4Address,
+    pub backup_designated_router: IPv4Address,
+    pub neighbors: Vec<IPv4Address>,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub enum OspfBody {
+    Hello(OspfHello),
+    /// The body of any packet type other than Hello
+    Other(Vec<u8>),
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct OspfPacket {
+    pub header: OspfHeader,
+    pub body: OspfBody,
+}
+
+const OSPF_HEADER_LEN: usize = 24;
+
+impl From<u8> for OspfPacketType {
+    fn from(raw: u8) -> Self {
+        match raw {
+            1 => OspfPacketType::Hello,
+            2 => OspfPacketType::DatabaseDescription,
+            3 => OspfPacketType::LinkStateRequest,
+            4 => OspfPacketType::LinkStateUpdate,
+            5 => OspfPacketType::LinkStateAck,
+            other => OspfPacketType::Other(other),
+        }
+    }
+}
+
+named!(address<&[u8], IPv4Address>, map!(take!(4), |i: &[u8]| IPv4Address(*array_ref![i, 0, 4])));
+
+named!(ospf_header<&[u8], OspfHeader>, chain!(
+    version: be_u8 ~
+    packet_type: be_u8 ~
+    length: be_u16 ~
+    router_id: address ~
+    area_id: address ~
+    checksum: be_u16 ~
+    auth_type: be_u16 ~
+    authentication: take!(8),
+    || OspfHeader {
+        version,
+        packet_type: packet_type.into(),
+        length,
+        router_id,
+        area_id,
+        checksum,
+        auth_type,
+        authentication: *array_ref![authentication, 0, 8],
+    }
+));
+
+named!(ospf_hello<&[u8], OspfHello>, chain!(
+    network_mask: address ~
+    hello_interval: be_u16 ~
+    options: be_u8 ~
+    router_priority: be_u8 ~
+    dead_interval: be_u32 ~
+    designated_router: address ~
+    backup_designated_router: address ~
+    neighbors: many0!(address),
+    || OspfHello {
+        network_mask,
+        hello_interval,
+        options,
+        router_priority,
+        dead_interval,
+        designated_router,
+        backup_designated_router,
+        neighbors,
+    }
+));
+
+pub fn parse_ospf_header(i: &[u8]) -> IResult<&[u8], OspfHeader> {
+    ospf_header(i)
+}
+
+/// Parses the common header and the body it covers, decoding Hello bodies.
+/// Anything after `header.length` bytes, such as link-layer padding, is left
+/// in the remaining input.
+pub fn parse_ospf_packet(i: &[u8]) -> IResult<&[u8], OspfPacket> {
+    let (left, header) = try_parse!(i, ospf_header);
+    let body_len = match (header.length as usize).checked_sub(OSPF_HEADER_LEN) {
+        Some(len) => len,
+        None => return IResult::Error(Err::Position(ErrorKind::LengthValue, i)),
+    };
+    let (left, body) = try_parse!(left, take!(body_len));
+    let body = match header.packet_type {
+        OspfPacketType::Hello => {
+            match ospf_hello(body) {
+                IResult::Done(&[], hello) => OspfBody::Hello(hello),
+                _ => return IResult::Error(Err::Position(ErrorKind::LengthValue, body)),
+            }
+        }
+        _ => OspfBody::Other(body.to_vec()),
+    };
+    IResult::Done(left, OspfPacket { header, body })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::IResult;
+
+    #[test]
+    fn ospf_hello_works() {
+        let bytes = [0x02, 0x01, 0x00, 0x30, /* Version 2, Hello, 48 bytes */
+                     0xc0, 0xa8, 0xaa, 0x08, /* Router ID */
+                     0x00, 0x00, 0x00, 0x01, /* Area 0.0.0.1 */
+                     0x27, 0x3b, 0x00, 0x00, /* Checksum, no authentication */
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                     0xff, 0xff, 0xff, 0x00, /* Network mask */
+                     0x00, 0x0a, 0x02, 0x01, /* Hello 10s, options, priority 1 */
+                     0x00, 0x00, 0x00, 0x28, /* Dead interval 40s */
+                     0xc0, 0xa8, 0xaa, 0x08, /* DR */
+                     0x00, 0x00, 0x00, 0x00, /* BDR */
+                     0xc0, 0xa8, 0xaa, 0x03 /* Neighbor */];
+        let expectation = OspfPacket {
+            header: OspfHeader {
+                version: 2,
+                packet_type: OspfPacketType::Hello,
+                length: 48,
+                router_id: IPv4Address([192, 168, 170, 8]),
+                area_id: IPv4Address([0, 0, 0, 1]),
+                checksum: 0x273b,
+                auth_type: 0,
+                authentication: [0; 8],
+            },
+            body: OspfBody::Hello(OspfHello {
+                network_mask: IPv4Address([255, 255, 255, 0]),
+                hello_interval: 10,
+                options: 0x02,
+                router_priority: 1,
+                dead_interval: 40,
+                designated_router: IPv4Address([192, 168, 170, 8]),
+                backup_designated_router: IPv4Address([0, 0, 0, 0]),
+                neighbors: vec![IPv4Address([192, 168, 170, 3])],
+            }),
+        };
+        assert_eq!(parse_ospf_packet(&bytes), IResult::Done(&[][..], expectation));
+    }
+
+    #[test]
+    fn ospf_packet_rejects_short_length() {
+        let mut bytes = [0u8; 24];
+        bytes[0] = 2;
+        bytes[1] = 1;
+        bytes[3] = 20;
+        match parse_ospf_packet(&bytes) {
+            IResult::Error(_) => {}
+            _ => panic!(),
+        }
+    }
+}
@@ -0,0 +1,676 @@
+//! Handles parsing of whole packets, following the ethertype and IP protocol
+//! fields from one layer to the next
+
+use core::cmp;
+use core::net::IpAddr;
+
+use alloc::vec::Vec;
+
+use nom::IResult;
+
+use arp::ArpPacket;
+use error::{from_iresult, Layer, PktError};
+use ethernet::{EtherType, EthernetFrame, VlanEthernetFrame, parse_ethernet_frame,
+               parse_vlan_ethernet_frame};
+use flow::FiveTuple;
+use icmp::IcmpHeader;
+use icmpv6::Icmpv6Header;
+use ip::IPProtocol;
+pub use ip::{parse_ip, IpHeader};
+use ipv4::IPv4Header;
+use ipv6::{skip_extension_headers, IPv6Header};
+use result;
+use tcp::TcpHeader;
+use udp::UdpHeader;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TransportHeader {
+    Tcp(TcpHeader),
+    Udp(UdpHeader),
+    Icmp(IcmpHeader),
+    Icmpv6(Icmpv6Header),
+}
+/// An IP address and, for TCP and UDP, a port
+pub type Endpoint = (IpAddr, Option<u16>);
+/// The headers of every layer `parse_packet` managed to parse. A layer is
+/// `None` when it is absent or could not be parsed, in which case all the
+/// layers above it are `None` too.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PacketHeaders<'a> {
+    pub ethernet: Option<EthernetFrame>,
+    /// Set when the frame carries 802.1Q tags
+    pub vlan: Option<VlanEthernetFrame>,
+    pub ip: Option<IpHeader>,
+    pub transport: Option<TransportHeader>,
+    /// The bytes following the last parsed header
+    pub payload: &'a [u8],
+}
+
+impl<'a> PacketHeaders<'a> {
+    pub fn ip_protocol(&self) -> Option<IPProtocol> {
+        match self.ip {
+            Some(IpHeader::V4(ref header)) => Some(header.protocol),
+            Some(IpHeader::V6(ref header)) => Some(header.next_header),
+            None => None,
+        }
+    }
+
+    pub fn is_tcp(&self) -> bool {
+        matches!(self.transport, Some(TransportHeader::Tcp(_)))
+    }
+
+    pub fn is_udp(&self) -> bool {
+        matches!(self.transport, Some(TransportHeader::Udp(_)))
+    }
+
+    /// The payload, if it is carried by a TCP segment
+    pub fn tcp_payload(&self) -> Option<&'a [u8]> {
+        if self.is_tcp() { Some(self.payload) } else { None }
+    }
+
+    /// The payload, if it is carried by a UDP datagram
+    pub fn udp_payload(&self) -> Option<&'a [u8]> {
+        if self.is_udp() { Some(self.payload) } else { None }
+    }
+
+    /// The source and destination IP addresses, each with its TCP or UDP
+    /// port if the transport header is one of those
+    pub fn endpoints(&self) -> Option<(Endpoint, Endpoint)> {
+        let (source, dest) = match self.ip {
+            Some(IpHeader::V4(ref header)) => {
+                (IpAddr::V4(header.source_addr.into()), IpAddr::V4(header.dest_addr.into()))
+            }
+            Some(IpHeader::V6(ref header)) => {
+                (IpAddr::V6(header.source_addr.into()), IpAddr::V6(header.dest_addr.into()))
+            }
+            None => return None,
+        };
+        let ports = match self.transport {
+            Some(TransportHeader::Tcp(ref tcp)) => Some((tcp.source_port, tcp.dest_port)),
+            Some(TransportHeader::Udp(ref udp)) => Some((udp.source_port, udp.dest_port)),
+            _ => None,
+        };
+        Some(((source, ports.map(|p| p.0)), (dest, ports.map(|p| p.1))))
+    }
+
+    /// The addresses, ports and IP protocol of the packet, for keying
+    /// connection tracking tables
+    pub fn five_tuple(&self) -> Option<FiveTuple> {
+        let ((src_ip, src_port), (dst_ip, dst_port)) = self.endpoints()?;
+        let protocol = self.ip_protocol()?;
+        Some(FiveTuple { src_ip, dst_ip, src_port, dst_port, protocol })
+    }
+
+    /// Copies the payload so the headers can outlive the input buffer
+    pub fn into_owned(self) -> OwnedPacketHeaders {
+        OwnedPacketHeaders {
+            ethernet: self.ethernet,
+            vlan: self.vlan,
+            ip: self.ip,
+            transport: self.transport,
+            payload: self.payload.to_vec(),
+        }
+    }
+}
+
+/// `PacketHeaders` with a copy of the payload rather than a borrow of it
+#[derive(Debug, PartialEq, Eq)]
+pub struct OwnedPacketHeaders {
+    pub ethernet: Option<EthernetFrame>,
+    pub vlan: Option<VlanEthernetFrame>,
+    pub ip: Option<IpHeader>,
+    pub transport: Option<TransportHeader>,
+    pub payload: Vec<u8>,
+}
+
+/// The header following an Ethernet header, parsed according to the
+/// ethertype
+#[derive(Debug, PartialEq, Eq)]
+pub enum NextLayer<'a> {
+    /// An IPv4 header and its payload, without any link layer padding
+    Ipv4(IPv4Header, &'a [u8]),
+    /// An IPv6 header and its payload, without any link layer padding
+    Ipv6(IPv6Header, &'a [u8]),
+    Arp(ArpPacket),
+    /// The payload of an ethertype with no parser here
+    Raw(&'a [u8]),
+    /// The payload did not parse as its ethertype says it should
+    Error(PktError),
+}
+
+impl EthernetFrame {
+    /// Parses `payload`, the input following this header, with the parser
+    /// for `ethertype`
+    pub fn parse_next<'a>(&self, payload: &'a [u8]) -> NextLayer<'a> {
+        next_layer(self.ethertype, payload)
+    }
+}
+
+impl VlanEthernetFrame {
+    /// Parses `payload`, the input following the innermost tag, with the
+    /// parser for `ethertype`
+    pub fn parse_next<'a>(&self, payload: &'a [u8]) -> NextLayer<'a> {
+        next_layer(self.ethertype, payload)
+    }
+}
+
+fn next_layer(ethertype: EtherType, i: &[u8]) -> NextLayer<'_> {
+    match ethertype {
+        EtherType::IPv4 => match result::parse_ipv4_header(i) {
+            Ok((rest, header)) => {
+                // Drop any link layer padding after the datagram
+                let len = (header.length as usize).saturating_sub(header.ihl as usize);
+                NextLayer::Ipv4(header, &rest[..cmp::min(len, rest.len())])
+            }
+            Err(e) => NextLayer::Error(e),
+        },
+        EtherType::IPv6 => match result::parse_ipv6_header(i) {
+            Ok((rest, header)) => {
+                let len = header.length as usize;
+                NextLayer::Ipv6(header, &rest[..cmp::min(len, rest.len())])
+            }
+            Err(e) => NextLayer::Error(e),
+        },
+        EtherType::ARP => match result::parse_arp_pkt(i) {
+            Ok((_, arp)) => NextLayer::Arp(arp),
+            Err(e) => NextLayer::Error(e),
+        },
+        _ => NextLayer::Raw(i),
+    }
+}
+
+fn ip_for_ethertype(ethertype: EtherType, i: &[u8]) -> Option<(&[u8], IpHeader)> {
+    match ethertype {
+        EtherType::IPv4 | EtherType::IPv6 => match next_layer(ethertype, i) {
+            NextLayer::Ipv4(header, rest) => Some((rest, IpHeader::V4(header))),
+            NextLayer::Ipv6(header, rest) => Some((rest, IpHeader::V6(header))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The header following an IP header, parsed according to the IP protocol
+#[derive(Debug, PartialEq, Eq)]
+pub enum TransportLayer<'a> {
+    Tcp(TcpHeader, &'a [u8]),
+    Udp(UdpHeader, &'a [u8]),
+    Icmp(IcmpHeader, &'a [u8]),
+    Icmp6(Icmpv6Header, &'a [u8]),
+    /// The payload of a protocol with no parser here
+    Raw(&'a [u8]),
+    /// The payload did not parse as its protocol says it should
+    Error(PktError),
+}
+
+impl IPv4Header {
+    /// Parses `payload`, the input following this header, with the parser
+    /// for `protocol`. ICMPv6 is only valid over IPv6, so it is left `Raw`.
+    pub fn parse_transport<'a>(&self, payload: &'a [u8]) -> TransportLayer<'a> {
+        match self.protocol {
+            IPProtocol::ICMP6 => TransportLayer::Raw(payload),
+            protocol => transport_layer(protocol, payload),
+        }
+    }
+}
+
+impl IPv6Header {
+    /// Parses `payload`, the input following this header, skipping any
+    /// extension headers and then using the parser for the upper-layer
+    /// protocol. Only the first fragment carries the transport header, so a
+    /// later one is left `Raw` after its Fragment header. ICMP for IPv4 has
+    /// no meaning over IPv6, as its messages quote IPv4 headers, so it is
+    /// left `Raw` too.
+    pub fn parse_transport<'a>(&self, payload: &'a [u8]) -> TransportLayer<'a> {
+        let (rest, (protocol, fragment)) =
+            match from_iresult(Layer::Ipv6, skip_extension_headers(self.next_header, payload)) {
+                Ok(walked) => walked,
+                Err(e) => return TransportLayer::Error(e),
+            };
+        match (protocol, fragment) {
+            (_, Some(ref header)) if header.fragment_offset != 0 => TransportLayer::Raw(rest),
+            (IPProtocol::ICMP, _) => TransportLayer::Raw(rest),
+            (protocol, _) => transport_layer(protocol, rest),
+        }
+    }
+}
+
+fn transport_layer(protocol: IPProtocol, i: &[u8]) -> TransportLayer<'_> {
+    let result = match protocol {
+        IPProtocol::TCP => result::parse_tcp_header(i).map(|(rest, h)| TransportLayer::Tcp(h, rest)),
+        IPProtocol::UDP => result::parse_udp_header(i).map(|(rest, h)| TransportLayer::Udp(h, rest)),
+        IPProtocol::ICMP => result::parse_icmp_header(i).map(|(rest, h)| TransportLayer::Icmp(h, rest)),
+        IPProtocol::ICMP6 => {
+            result::parse_icmpv6_header(i).map(|(rest, h)| TransportLayer::Icmp6(h, rest))
+        }
+        _ => return TransportLayer::Raw(i),
+    };
+    result.unwrap_or_else(TransportLayer::Error)
+}
+
+fn transport_header(layer: TransportLayer<'_>) -> Option<(&[u8], TransportHeader)> {
+    match layer {
+        TransportLayer::Tcp(header, rest) => Some((rest, TransportHeader::Tcp(header))),
+        TransportLayer::Udp(header, rest) => Some((rest, TransportHeader::Udp(header))),
+        TransportLayer::Icmp(header, rest) => Some((rest, TransportHeader::Icmp(header))),
+        TransportLayer::Icmp6(header, rest) => Some((rest, TransportHeader::Icmpv6(header))),
+        TransportLayer::Raw(_) | TransportLayer::Error(_) => None,
+    }
+}
+
+/// Parses an Ethernet frame and as many of the headers it encapsulates as
+/// possible. Parsing stops at the first layer that is unknown or malformed;
+/// the remaining input is whatever follows the last parsed header.
+/// `payload` is always the same slice as the remaining input.
+pub fn parse_packet(i: &[u8]) -> IResult<&[u8], PacketHeaders<'_>> {
+    let mut headers = PacketHeaders {
+        ethernet: None,
+        vlan: None,
+        ip: None,
+        transport: None,
+        payload: i,
+    };
+
+    let (mut rest, ethernet) = match parse_ethernet_frame(i) {
+        IResult::Done(rest, ethernet) => (rest, ethernet),
+        _ => return IResult::Done(i, headers),
+    };
+    let mut ethertype = ethernet.ethertype;
+    headers.ethernet = Some(ethernet);
+
+    if ethertype.is_vlan() {
+        match parse_vlan_ethernet_frame(i) {
+            IResult::Done(after_tags, vlan) => {
+                ethertype = vlan.ethertype;
+                headers.vlan = Some(vlan);
+                rest = after_tags;
+            }
+            _ => return IResult::Done(rest, PacketHeaders { payload: rest, ..headers }),
+        }
+    }
+
+    let (after_ip, ip) = match ip_for_ethertype(ethertype, rest) {
+        Some(parsed) => parsed,
+        None => return IResult::Done(rest, PacketHeaders { payload: rest, ..headers }),
+    };
+    let transport = match ip {
+        // Only the first fragment carries the transport header
+        IpHeader::V4(ref header) if header.fragment_offset != 0 => None,
+        IpHeader::V4(ref header) => transport_header(header.parse_transport(after_ip)),
+        IpHeader::V6(ref header) => transport_header(header.parse_transport(after_ip)),
+    };
+    headers.ip = Some(ip);
+    rest = after_ip;
+
+    if let Some((after_transport, transport)) = transport {
+        headers.transport = Some(transport);
+        rest = after_transport;
+    }
+    IResult::Done(rest, PacketHeaders { payload: rest, ..headers })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethernet::MacAddress;
+    use ipv4::parse_ipv4_header;
+    use ipv6::parse_ipv6_header;
+    use nom::IResult;
+
+    #[test]
+    fn packet_stops_at_unknown_ethertype() {
+        let bytes = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x88, 0xb5, /* Local experimental, unlisted */
+                     0x00, 0x01];
+        if let IResult::Done(remaining, headers) = parse_packet(&bytes) {
+            let ethernet = headers.ethernet.as_ref().unwrap();
+            assert_eq!(ethernet.source_mac, MacAddress([0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b]));
+            assert_eq!(ethernet.ethertype, EtherType::Other(0x88b5));
+            assert_eq!(headers.ip, None);
+            assert_eq!(headers.ip_protocol(), None);
+            assert_eq!(remaining, &bytes[14..]);
+            assert_eq!(headers.payload, &bytes[14..]);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn packet_follows_vlan_tags() {
+        let bytes = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x81, 0x00, 0x00, 0x64, /* VLAN 100 */
+                     0x86, 0xdd, /* IPv6 */
+                     0x60, 0x00, 0x00, 0x00, 0x00, 0x08, 0x11, 0x40,
+                     0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00,
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+                     0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00,
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+                     0xc3, 0x50, 0x00, 0x35, 0x00, 0x08, 0x00, 0x00, /* UDP header */
+                     0x00, 0x00 /* Ethernet padding */];
+        if let IResult::Done(remaining, headers) = parse_packet(&bytes) {
+            assert_eq!(headers.vlan.as_ref().unwrap().tags[0].vid, 100);
+            match headers.transport {
+                Some(TransportHeader::Udp(ref udp)) => assert_eq!(udp.dest_port, 53),
+                ref other => panic!("expected UDP, got {:?}", other),
+            }
+            assert!(remaining.is_empty());
+            assert_eq!(headers.udp_payload(), Some(&[][..]));
+            assert_eq!(headers.tcp_payload(), None);
+        } else {
+            panic!();
+        }
+    }
+
+    fn frame(ethertype: EtherType) -> EthernetFrame {
+        use ethernet::MacAddress;
+        EthernetFrame {
+            source_mac: MacAddress([0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b]),
+            dest_mac: MacAddress([0xff; 6]),
+            ethertype,
+        }
+    }
+
+    #[test]
+    fn parse_next_dispatches_ipv4() {
+        let payload = [0x45, 0x00, 0x00, 0x1c, 0x1a, 0xe6, 0x00, 0x00,
+                       0x40, 0x01, 0x22, 0xed, 0x0a, 0x2d, 0x00, 0x02,
+                       0x0a, 0x2d, 0x00, 0x01, 0x08, 0x00, 0x00, 0x00,
+                       0x00, 0x00, 0x00, 0x00, 0x00, 0x00 /* Ethernet padding */];
+        match frame(EtherType::IPv4).parse_next(&payload) {
+            NextLayer::Ipv4(header, rest) => {
+                assert_eq!(header.protocol, IPProtocol::ICMP);
+                assert_eq!(rest, &payload[20..28]);
+            }
+            other => panic!("expected an IPv4 header, got {:?}", other),
+        }
+        match frame(EtherType::IPv4).parse_next(&payload[..10]) {
+            NextLayer::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_next_dispatches_ipv6() {
+        let mut payload = [0u8; 44];
+        payload[0] = 0x60;
+        payload[5] = 4;
+        payload[6] = 58;
+        payload[7] = 64;
+        match frame(EtherType::IPv6).parse_next(&payload) {
+            NextLayer::Ipv6(header, rest) => {
+                assert_eq!(header.next_header, IPProtocol::ICMP6);
+                assert_eq!(rest.len(), 4);
+            }
+            other => panic!("expected an IPv6 header, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_next_dispatches_arp() {
+        let payload = [0x00, 0x01, 0x08, 0x00, 0x06, 0x04, 0x00, 0x02,
+                       0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b, 0x0a, 0x0a, 0x01, 0x87,
+                       0x00, 0x26, 0x0b, 0x4c, 0x2e, 0x00, 0x0a, 0x0a, 0x01, 0xb4];
+        match frame(EtherType::ARP).parse_next(&payload) {
+            NextLayer::Arp(arp) => assert_eq!(arp.operation, ::arp::Operation::Reply),
+            other => panic!("expected an ARP packet, got {:?}", other),
+        }
+        assert_eq!(frame(EtherType::LLDP).parse_next(&payload), NextLayer::Raw(&payload[..]));
+    }
+
+    fn ipv4_header(protocol: IPProtocol) -> IPv4Header {
+        let bytes = [0x45, 0x00, 0x00, 0x1c, 0x1a, 0xe6, 0x00, 0x00,
+                     0x40, 0x01, 0x22, 0xed, 0x0a, 0x2d, 0x00, 0x02,
+                     0x0a, 0x2d, 0x00, 0x01];
+        match parse_ipv4_header(&bytes) {
+            IResult::Done(_, header) => IPv4Header { protocol, ..header },
+            other => panic!("expected an IPv4 header, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_transport_dispatches_tcp() {
+        let payload = [0xc3, 0x50, 0x00, 0x50, 0x00, 0x00, 0x00, 0x01,
+                       0x00, 0x00, 0x00, 0x00, 0x50, 0x02, 0xfa, 0xf0,
+                       0x00, 0x00, 0x00, 0x00, b'h', b'i'];
+        match ipv4_header(IPProtocol::TCP).parse_transport(&payload) {
+            TransportLayer::Tcp(header, rest) => {
+                assert_eq!((header.source_port, header.dest_port), (50000, 80));
+                assert!(header.flag_syn);
+                assert_eq!(rest, b"hi");
+            }
+            other => panic!("expected a TCP header, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_transport_dispatches_udp() {
+        let payload = [0xc3, 0x50, 0x00, 0x35, 0x00, 0x0a, 0x00, 0x00, 0xab, 0xcd];
+        match ipv4_header(IPProtocol::UDP).parse_transport(&payload) {
+            TransportLayer::Udp(header, rest) => {
+                assert_eq!(header.dest_port, 53);
+                assert_eq!(rest, &[0xab, 0xcd]);
+            }
+            other => panic!("expected a UDP header, got {:?}", other),
+        }
+        match ipv4_header(IPProtocol::UDP).parse_transport(&payload[..4]) {
+            TransportLayer::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_transport_dispatches_icmp() {
+        let payload = [0x08, 0x00, 0x4d, 0x5a, 0x00, 0x01, 0x00, 0x07, b'a', b'b'];
+        match ipv4_header(IPProtocol::ICMP).parse_transport(&payload) {
+            TransportLayer::Icmp(header, rest) => {
+                assert_eq!(header.code, ::icmp::IcmpCode::EchoRequest);
+                assert_eq!(rest, b"ab");
+            }
+            other => panic!("expected an ICMP header, got {:?}", other),
+        }
+        assert_eq!(ipv4_header(IPProtocol::GRE).parse_transport(&payload),
+                   TransportLayer::Raw(&payload[..]));
+    }
+
+    #[test]
+    fn parse_transport_dispatches_icmpv6_echo_request() {
+        let bytes = [0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x00, 0x26, 0x0b, 0x4c, 0x2e, 0x00,
+                     0x86, 0xdd, /* IPv6 */
+                     0x60, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x3a, 0x40,
+                     0xfe, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                     0x02, 0x26, 0x0b, 0xff, 0xfe, 0x4c, 0x2e, 0x00,
+                     0xfe, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                     0x02, 0x1b, 0x21, 0xff, 0xfe, 0x0f, 0x91, 0x9b,
+                     0x80, 0x00, 0x12, 0x34, 0x00, 0x01, 0x00, 0x07, /* Echo Request */
+                     b'p', b'i', b'n', b'g'];
+        let (ethernet, payload) = match parse_ethernet_frame(&bytes) {
+            IResult::Done(rest, ethernet) => (ethernet, rest),
+            other => panic!("expected a frame, got {:?}", other),
+        };
+        let (ipv6, payload) = match ethernet.parse_next(payload) {
+            NextLayer::Ipv6(header, rest) => (header, rest),
+            other => panic!("expected an IPv6 header, got {:?}", other),
+        };
+        match ipv6.parse_transport(payload) {
+            TransportLayer::Icmp6(header, rest) => {
+                assert_eq!(header.code, ::icmpv6::Icmpv6Code::EchoRequest);
+                assert_eq!(rest, b"ping");
+            }
+            other => panic!("expected an ICMPv6 header, got {:?}", other),
+        }
+
+        let ipv6 = IPv6Header { next_header: IPProtocol::ICMP, ..ipv6 };
+        assert_eq!(ipv6.parse_transport(payload), TransportLayer::Raw(payload));
+        assert_eq!(ipv4_header(IPProtocol::ICMP6).parse_transport(payload),
+                   TransportLayer::Raw(payload));
+    }
+
+    #[test]
+    fn packet_skips_ipv6_extension_headers() {
+        let mut bytes = vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                             0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                             0x86, 0xdd, /* IPv6 */
+                             0x60, 0x00, 0x00, 0x00, 0x00, 0x24, 0x00, 0x40, /* Hop-by-Hop next */
+                             0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00,
+                             0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+                             0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00,
+                             0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+                             0x2c, 0x00, 0x01, 0x04, 0x00, 0x00, 0x00, 0x00, /* Hop-by-Hop, PadN */
+                             0x06, 0x00, 0x00, 0x01, 0x00, 0x00, 0x12, 0x34, /* First fragment of TCP */
+                             0xc3, 0x50, 0x00, 0x50, 0x00, 0x00, 0x00, 0x01, /* TCP header */
+                             0x00, 0x00, 0x00, 0x00, 0x50, 0x02, 0xfa, 0xf0,
+                             0x00, 0x00, 0x00, 0x00];
+        match parse_packet(&bytes) {
+            IResult::Done(rest, headers) => {
+                match headers.transport {
+                    Some(TransportHeader::Tcp(ref tcp)) => assert_eq!(tcp.dest_port, 80),
+                    ref other => panic!("expected TCP, got {:?}", other),
+                }
+                assert!(rest.is_empty());
+            }
+            other => panic!("expected headers, got {:?}", other),
+        }
+
+        bytes[64..66].copy_from_slice(&[0x05, 0xc8]); /* Offset 185, last fragment */
+        match parse_packet(&bytes) {
+            IResult::Done(rest, headers) => {
+                assert!(headers.ip.is_some());
+                assert_eq!(headers.transport, None);
+                assert_eq!(rest, &bytes[54..]);
+            }
+            other => panic!("expected headers, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_transport_skips_ipv6_auth_header() {
+        let mut ipv6 = [0u8; 40];
+        ipv6[0] = 0x60;
+        ipv6[5] = 44;
+        ipv6[6] = 51; /* AH */
+        ipv6[7] = 64;
+        let ipv6 = match parse_ipv6_header(&ipv6) {
+            IResult::Done(_, header) => header,
+            other => panic!("expected an IPv6 header, got {:?}", other),
+        };
+        let payload = [0x06, 0x04, 0x00, 0x00, /* TCP, 24 bytes */
+                       0x00, 0x00, 0x10, 0x02, /* SPI */
+                       0x00, 0x00, 0x00, 0x07, /* Sequence number */
+                       0x5a, 0x1c, 0x3e, 0x9f, 0x27, 0x40, 0x8b, 0xd1,
+                       0x66, 0x0e, 0xa3, 0x52, /* HMAC-SHA1-96 ICV */
+                       0xc3, 0x50, 0x00, 0x50, 0x00, 0x00, 0x00, 0x01,
+                       0x00, 0x00, 0x00, 0x00, 0x50, 0x02, 0x72, 0x10,
+                       0x00, 0x00, 0x00, 0x00 /* TCP SYN */];
+        match ipv6.parse_transport(&payload) {
+            TransportLayer::Tcp(header, rest) => {
+                assert_eq!((header.source_port, header.dest_port), (50000, 80));
+                assert!(header.flag_syn);
+                assert!(rest.is_empty());
+            }
+            other => panic!("expected a TCP header, got {:?}", other),
+        }
+        match ipv6.parse_transport(&payload[..20]) {
+            TransportLayer::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn vlan_parse_next_dispatches_inner_ethertype() {
+        let untagged = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                        0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                        0x08, 0x00, /* IPv4 */
+                        0x45, 0x00, 0x00, 0x1c, 0x1a, 0xe6, 0x00, 0x00,
+                        0x40, 0x01, 0x22, 0xed, 0x0a, 0x2d, 0x00, 0x02,
+                        0x0a, 0x2d, 0x00, 0x01,
+                        0x08, 0x00, 0x4d, 0x5a, 0x00, 0x01, 0x00, 0x07];
+        let mut tagged = untagged[..12].to_vec();
+        tagged.extend_from_slice(&[0x81, 0x00, 0x20, 0x64]); /* VLAN 100, PCP 1 */
+        tagged.extend_from_slice(&untagged[12..]);
+
+        for (bytes, tags) in [(&untagged[..], 0), (&tagged[..], 1)] {
+            let (frame, payload) = match parse_vlan_ethernet_frame(bytes) {
+                IResult::Done(rest, frame) => (frame, rest),
+                other => panic!("expected a frame, got {:?}", other),
+            };
+            assert_eq!(frame.tags.len(), tags);
+            match frame.parse_next(payload) {
+                NextLayer::Ipv4(header, rest) => {
+                    assert_eq!(header.protocol, IPProtocol::ICMP);
+                    assert_eq!(rest, &untagged[34..]);
+                }
+                other => panic!("expected an IPv4 header, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn endpoints_of_tcp_and_icmp_packets() {
+        use core::net::Ipv4Addr;
+
+        let mut bytes = vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                             0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                             0x08, 0x00, /* IPv4 */
+                             0x45, 0x00, 0x00, 0x28, 0x1a, 0xe6, 0x00, 0x00,
+                             0x40, 0x06, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x01,
+                             0x0a, 0x00, 0x00, 0x02,
+                             0xc3, 0x50, 0x00, 0x50, 0x00, 0x00, 0x00, 0x01, /* TCP header */
+                             0x00, 0x00, 0x00, 0x00, 0x50, 0x02, 0xfa, 0xf0,
+                             0x00, 0x00, 0x00, 0x00];
+        let client = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let server = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        match parse_packet(&bytes) {
+            IResult::Done(_, headers) => {
+                assert_eq!(headers.endpoints(), Some(((client, Some(50000)), (server, Some(80)))));
+            }
+            other => panic!("expected headers, got {:?}", other),
+        }
+
+        bytes[17] = 0x1c;
+        bytes[23] = 0x01; /* ICMP */
+        bytes.truncate(34);
+        bytes.extend_from_slice(&[0x08, 0x00, 0x4d, 0x5a, 0x00, 0x01, 0x00, 0x07]);
+        match parse_packet(&bytes) {
+            IResult::Done(_, headers) => {
+                match headers.transport {
+                    Some(TransportHeader::Icmp(_)) => {}
+                    ref other => panic!("expected ICMP, got {:?}", other),
+                }
+                assert_eq!(headers.endpoints(), Some(((client, None), (server, None))));
+            }
+            other => panic!("expected headers, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn both_directions_have_the_same_normalized_five_tuple() {
+        let request = [0x00, 0x26, 0x0b, 0x4c, 0x2e, 0x00,
+                       0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                       0x08, 0x00, /* IPv4 */
+                       0x45, 0x00, 0x00, 0x1e, 0x1a, 0xe6, 0x00, 0x00,
+                       0x40, 0x11, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x01,
+                       0x0a, 0x00, 0x00, 0x02,
+                       0xc3, 0x50, 0x00, 0x35, 0x00, 0x0a, 0x00, 0x00, /* UDP header */
+                       0xab, 0xcd];
+        let mut reply = request;
+        reply.copy_within(26..30, 30);
+        reply[26..30].copy_from_slice(&request[30..34]);
+        reply.copy_within(34..36, 36);
+        reply[34..36].copy_from_slice(&request[36..38]);
+
+        let five_tuple = |bytes: &[u8]| match parse_packet(bytes) {
+            IResult::Done(_, headers) => headers.five_tuple().unwrap(),
+            other => panic!("expected headers, got {:?}", other),
+        };
+        let (request, reply) = (five_tuple(&request), five_tuple(&reply));
+        assert_eq!(request.protocol, IPProtocol::UDP);
+        assert_eq!((request.src_port, request.dst_port), (Some(50000), Some(53)));
+        assert_eq!(request.reversed(), reply);
+        assert_ne!(request, reply);
+        assert_eq!(request.normalized(), reply.normalized());
+        assert_eq!(request.normalized(), request);
+    }
+}
@@ -0,0 +1,270 @@
+//! Handles reading of packet records from classic pcap files
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+
+// Magic numbers as read in the file's own byte order
+const MAGIC_MICROS: u32 = 0xa1b2c3d4;
+const MAGIC_NANOS: u32 = 0xa1b23c4d;
+
+/// Records larger than this are rejected even when the snapshot length in
+/// the global header claims they are allowed
+const MAX_RECORD_LEN: u32 = 0x0400_0000;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PcapHeader {
+    pub version_major: u16,
+    pub version_minor: u16,
+    /// Offset of the timestamps from UTC in seconds, in practice always 0
+    pub thiszone: i32,
+    pub sigfigs: u32,
+    pub snaplen: u32,
+    /// The LINKTYPE_ value, e.g. 1 for Ethernet
+    pub link_type: u32,
+    pub big_endian: bool,
+    /// Whether record timestamps were written with nanosecond resolution
+    pub nanosecond: bool,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PcapRecordHeader {
+    pub ts_sec: u32,
+    /// Sub-second part of the timestamp in nanoseconds, whatever the
+    /// resolution of the file
+    pub ts_nsec: u32,
+    /// Number of packet bytes stored in the file
+    pub incl_len: u32,
+    /// Length of the packet on the wire
+    pub orig_len: u32,
+}
+#[derive(Debug)]
+pub enum PcapError {
+    Io(io::Error),
+    /// The file does not start with a known pcap magic number
+    BadMagic(u32),
+    /// A record's stored length exceeds the snapshot length
+    RecordTooLarge(u32),
+    /// The file ended partway through a record
+    Truncated,
+}
+
+impl fmt::Display for PcapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PcapError::Io(ref e) => write!(f, "pcap read failed: {}", e),
+            PcapError::BadMagic(m) => write!(f, "not a pcap file (magic 0x{:08x})", m),
+            PcapError::RecordTooLarge(n) => write!(f, "pcap record of {} bytes exceeds the snapshot length", n),
+            PcapError::Truncated => write!(f, "pcap file ends partway through a record"),
+        }
+    }
+}
+
+impl Error for PcapError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            PcapError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PcapError {
+    fn from(e: io::Error) -> PcapError {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => PcapError::Truncated,
+            _ => PcapError::Io(e),
+        }
+    }
+}
+
+/// Reads the records of a pcap file one at a time, reusing a single buffer
+/// for the packet bytes.
+///
+/// Records borrow from the reader, so this is not an `Iterator`; loop with
+/// `while let Some(record) = reader.next_record()` instead.
+pub struct PcapReader<R> {
+    reader: R,
+    header: PcapHeader,
+    buf: Vec<u8>,
+}
+
+impl<R: Read> PcapReader<R> {
+    /// Reads and validates the global header
+    pub fn new(mut reader: R) -> Result<PcapReader<R>, PcapError> {
+        let mut raw = [0u8; 24];
+        reader.read_exact(&mut raw)?;
+        let le_magic = u32::from_le_bytes(*array_ref![raw, 0, 4]);
+        let be_magic = u32::from_be_bytes(*array_ref![raw, 0, 4]);
+        let (big_endian, nanosecond) = match (le_magic, be_magic) {
+            (MAGIC_MICROS, _) => (false, false),
+            (MAGIC_NANOS, _) => (false, true),
+            (_, MAGIC_MICROS) => (true, false),
+            (_, MAGIC_NANOS) => (true, true),
+            _ => return Err(PcapError::BadMagic(be_magic)),
+        };
+        let u16_at = |i: usize| {
+            let b = *array_ref![raw, i, 2];
+            if big_endian { u16::from_be_bytes(b) } else { u16::from_le_bytes(b) }
+        };
+        let u32_at = |i: usize| {
+            let b = *array_ref![raw, i, 4];
+            if big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) }
+        };
+        let header = PcapHeader {
+            version_major: u16_at(4),
+            version_minor: u16_at(6),
+            thiszone: u32_at(8) as i32,
+            sigfigs: u32_at(12),
+            snaplen: u32_at(16),
+            link_type: u32_at(20),
+            big_endian,
+            nanosecond,
+        };
+        Ok(PcapReader {
+            reader,
+            header,
+            buf: Vec::new(),
+        })
+    }
+
+    pub fn header(&self) -> &PcapHeader {
+        &self.header
+    }
+
+    /// Reads the next record, returning `None` at a clean end of file
+    pub fn next_record(&mut self) -> Option<Result<(PcapRecordHeader, &[u8]), PcapError>> {
+        let mut raw = [0u8; 16];
+        match read_record_header(&mut self.reader, &mut raw) {
+            Ok(false) => return None,
+            Ok(true) => {}
+            Err(e) => return Some(Err(e)),
+        }
+        let big_endian = self.header.big_endian;
+        let u32_at = |i: usize| {
+            let b = *array_ref![raw, i, 4];
+            if big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) }
+        };
+        let ts_frac = u32_at(4);
+        let record = PcapRecordHeader {
+            ts_sec: u32_at(0),
+            ts_nsec: if self.header.nanosecond { ts_frac } else { ts_frac.wrapping_mul(1000) },
+            incl_len: u32_at(8),
+            orig_len: u32_at(12),
+        };
+        if record.incl_len > self.header.snaplen.min(MAX_RECORD_LEN) {
+            return Some(Err(PcapError::RecordTooLarge(record.incl_len)));
+        }
+
+        self.buf.resize(record.incl_len as usize, 0);
+        if let Err(e) = self.reader.read_exact(&mut self.buf) {
+            return Some(Err(e.into()));
+        }
+        Some(Ok((record, &self.buf)))
+    }
+}
+
+/// Fills `raw`, returning false if the input was already at its end
+fn read_record_header<R: Read>(reader: &mut R, raw: &mut [u8; 16]) -> Result<bool, PcapError> {
+    let mut filled = 0;
+    while filled < raw.len() {
+        match reader.read(&mut raw[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(PcapError::Truncated),
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PcapError, PcapReader, PcapRecordHeader};
+    use ethernet::{parse_ethernet_frame, EtherType};
+    use nom::IResult;
+
+    const TWO_RECORDS: &[u8] = &[
+        0xd4, 0xc3, 0xb2, 0xa1, 0x02, 0x00, 0x04, 0x00, /* Magic, version 2.4 */
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, /* Zone, sigfigs */
+        0xff, 0xff, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, /* Snaplen 65535, Ethernet */
+        0x10, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, /* Record 1: 16 s, 32 us */
+        0x0e, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x00, 0x00, /* 14 bytes stored of 60 */
+        0x00, 0x23, 0x54, 0x07, 0x93, 0x6c, 0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b, 0x08, 0x00,
+        0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, /* Record 2: 17 s, 0 us */
+        0x02, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, /* 2 bytes */
+        0xca, 0xfe,
+    ];
+
+    #[test]
+    fn pcap_reader_yields_both_records() {
+        let mut reader = PcapReader::new(TWO_RECORDS).unwrap();
+        assert_eq!(reader.header().version_major, 2);
+        assert_eq!(reader.header().version_minor, 4);
+        assert_eq!(reader.header().snaplen, 65535);
+        assert_eq!(reader.header().link_type, 1);
+        assert!(!reader.header().big_endian);
+        assert!(!reader.header().nanosecond);
+
+        {
+            let (record, data) = reader.next_record().unwrap().unwrap();
+            assert_eq!(record, PcapRecordHeader { ts_sec: 16, ts_nsec: 32000, incl_len: 14, orig_len: 60 });
+            if let IResult::Done(_, frame) = parse_ethernet_frame(data) {
+                assert_eq!(frame.ethertype, EtherType::IPv4);
+            } else {
+                panic!();
+            }
+        }
+        {
+            let (record, data) = reader.next_record().unwrap().unwrap();
+            assert_eq!(record.ts_sec, 17);
+            assert_eq!(data, &[0xca, 0xfe]);
+        }
+        assert!(reader.next_record().is_none());
+    }
+
+    #[test]
+    fn pcap_reader_handles_big_endian_nanoseconds() {
+        let bytes = [0xa1, 0xb2, 0x3c, 0x4d, 0x00, 0x02, 0x00, 0x04,
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                     0x00, 0x00, 0xff, 0xff, 0x00, 0x00, 0x00, 0x71,
+                     0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x05,
+                     0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01,
+                     0xaa];
+        let mut reader = PcapReader::new(&bytes[..]).unwrap();
+        assert!(reader.header().big_endian);
+        assert!(reader.header().nanosecond);
+        assert_eq!(reader.header().link_type, 113);
+        let (record, data) = reader.next_record().unwrap().unwrap();
+        assert_eq!(record, PcapRecordHeader { ts_sec: 1, ts_nsec: 5, incl_len: 1, orig_len: 1 });
+        assert_eq!(data, &[0xaa]);
+    }
+
+    #[test]
+    fn pcap_reader_rejects_bad_magic_and_truncation() {
+        match PcapReader::new(&[0u8; 24][..]) {
+            Err(PcapError::BadMagic(0)) => {}
+            _ => panic!(),
+        }
+        let mut reader = PcapReader::new(&TWO_RECORDS[..TWO_RECORDS.len() - 1]).unwrap();
+        assert!(reader.next_record().unwrap().is_ok());
+        match reader.next_record() {
+            Some(Err(PcapError::Truncated)) => {}
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn pcap_reader_caps_records_despite_a_huge_snaplen() {
+        let mut bytes = TWO_RECORDS[..24].to_vec();
+        bytes[16..20].copy_from_slice(&[0xff; 4]); /* Snaplen 2^32 - 1 */
+        bytes.extend_from_slice(&[0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                                  0x00, 0x00, 0x00, 0xf0, 0x00, 0x00, 0x00, 0xf0, /* 3.75 GiB */
+                                  0xca, 0xfe]);
+        let mut reader = PcapReader::new(&bytes[..]).unwrap();
+        match reader.next_record() {
+            Some(Err(PcapError::RecordTooLarge(0xf000_0000))) => {}
+            other => panic!("expected RecordTooLarge, got {:?}", other.map(|r| r.map(|(h, _)| h))),
+        }
+    }
+}
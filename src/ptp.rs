@@ -0,0 +1,234 @@
+//! Handles parsing of PTP (IEEE 1588-2008) messages
+//!
+//! PTP is carried directly in Ethernet frames with `EtherType::PTP`, or
+//! over UDP with event messages on `PTP_EVENT_PORT` and the rest on
+//! `PTP_GENERAL_PORT`. Only version 2 is parsed.
+
+use alloc::vec::Vec;
+
+use nom::{IResult, Err, ErrorKind, be_u8, be_i8, be_u16, be_u32, be_i64};
+
+/// The UDP port of event messages, which are timestamped on receipt
+pub const PTP_EVENT_PORT: u16 = 319;
+/// The UDP port of general messages
+pub const PTP_GENERAL_PORT: u16 = 320;
+/// Length of the common header
+pub const PTP_HEADER_LEN: u16 = 34;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PtpMessageType {
+    Sync,
+    DelayReq,
+    PdelayReq,
+    PdelayResp,
+    FollowUp,
+    DelayResp,
+    PdelayRespFollowUp,
+    Announce,
+    Signaling,
+    Management,
+    Other(u8),
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PtpPortIdentity {
+    pub clock_identity: [u8; 8],
+    pub port_number: u16,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PtpTimestamp {
+    /// Seconds since the PTP epoch; only 48 bits are sent
+    pub seconds: u64,
+    pub nanoseconds: u32,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PtpHeader {
+    pub transport_specific: u8,
+    pub message_type: PtpMessageType,
+    pub version: u8,
+    /// Length of the whole message, header included
+    pub message_length: u16,
+    pub domain: u8,
+    /// E.g. `0x0200` for a two-step clock, whose Sync timestamp follows in
+    /// a Follow_Up
+    pub flags: u16,
+    /// In nanoseconds multiplied by 2^16
+    pub correction_field: i64,
+    pub source_port_identity: PtpPortIdentity,
+    pub sequence_id: u16,
+    pub control_field: u8,
+    /// Log base 2 of the message interval in seconds
+    pub log_message_interval: i8,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub enum PtpBody {
+    Sync(PtpTimestamp),
+    DelayReq(PtpTimestamp),
+    FollowUp(PtpTimestamp),
+    DelayResp {
+        receive_timestamp: PtpTimestamp,
+        requesting_port_identity: PtpPortIdentity,
+    },
+    /// The body of a message type this crate does not decode
+    Other(Vec<u8>),
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct PtpMessage {
+    pub header: PtpHeader,
+    pub body: PtpBody,
+}
+
+impl From<u8> for PtpMessageType {
+    fn from(raw: u8) -> Self {
+        match raw {
+            0x0 => PtpMessageType::Sync,
+            0x1 => PtpMessageType::DelayReq,
+            0x2 => PtpMessageType::PdelayReq,
+            0x3 => PtpMessageType::PdelayResp,
+            0x8 => PtpMessageType::FollowUp,
+            0x9 => PtpMessageType::DelayResp,
+            0xa => PtpMessageType::PdelayRespFollowUp,
+            0xb => PtpMessageType::Announce,
+            0xc => PtpMessageType::Signaling,
+            0xd => PtpMessageType::Management,
+            other => PtpMessageType::Other(other),
+        }
+    }
+}
+
+named!(port_identity<&[u8], PtpPortIdentity>, chain!(
+    clock_identity: take!(8) ~
+    port_number: be_u16,
+    || PtpPortIdentity { clock_identity: *array_ref![clock_identity, 0, 8], port_number }
+));
+
+named!(timestamp<&[u8], PtpTimestamp>, chain!(
+    seconds_high: be_u16 ~
+    seconds_low: be_u32 ~
+    nanoseconds: be_u32,
+    || PtpTimestamp { seconds: (seconds_high as u64) << 32 | seconds_low as u64, nanoseconds }
+));
+
+named!(ptp_header<&[u8], PtpHeader>, chain!(
+    types: bits!(pair!(take_bits!(u8, 4), take_bits!(u8, 4))) ~
+    version: map_opt!(be_u8, |v: u8| if v & 0x0f == 2 { Some(v & 0x0f) } else { None }) ~
+    message_length: map_opt!(be_u16, |l: u16| if l >= PTP_HEADER_LEN { Some(l) } else { None }) ~
+    domain: be_u8 ~
+    be_u8 ~
+    flags: be_u16 ~
+    correction_field: be_i64 ~
+    take!(4) ~
+    source_port_identity: port_identity ~
+    sequence_id: be_u16 ~
+    control_field: be_u8 ~
+    log_message_interval: be_i8,
+    || PtpHeader {
+        transport_specific: types.0,
+        message_type: PtpMessageType::from(types.1),
+        version,
+        message_length,
+        domain,
+        flags,
+        correction_field,
+        source_port_identity,
+        sequence_id,
+        control_field,
+        log_message_interval,
+    }
+));
+
+// Any TLVs following a decoded body, such as those 802.1AS appends to
+// Follow_Up, are skipped
+fn to_ptp_body(message_type: PtpMessageType, body: &[u8]) -> Option<PtpBody> {
+    let decoded = match message_type {
+        PtpMessageType::Sync => timestamp(body).map(PtpBody::Sync),
+        PtpMessageType::DelayReq => timestamp(body).map(PtpBody::DelayReq),
+        PtpMessageType::FollowUp => timestamp(body).map(PtpBody::FollowUp),
+        PtpMessageType::DelayResp => chain!(body,
+            receive_timestamp: timestamp ~
+            requesting_port_identity: port_identity,
+            || PtpBody::DelayResp { receive_timestamp, requesting_port_identity }
+        ),
+        _ => return Some(PtpBody::Other(body.to_vec())),
+    };
+    match decoded {
+        IResult::Done(_, body) => Some(body),
+        _ => None,
+    }
+}
+
+/// Parses the common header, leaving the body in the remaining input
+pub fn parse_ptp_header(i: &[u8]) -> IResult<&[u8], PtpHeader> {
+    ptp_header(i)
+}
+
+/// Parses a whole message, decoding the body of the timestamp-carrying
+/// message types
+pub fn parse_ptp_message(i: &[u8]) -> IResult<&[u8], PtpMessage> {
+    let (left, header) = try_parse!(i, ptp_header);
+    let (left, body) = try_parse!(left, take!(header.message_length - PTP_HEADER_LEN));
+    match to_ptp_body(header.message_type, body) {
+        Some(body) => IResult::Done(left, PtpMessage { header, body }),
+        None => IResult::Error(Err::Position(ErrorKind::MapOpt, i)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethernet::{parse_ethernet_frame, EtherType};
+    use nom::IResult;
+
+    #[test]
+    fn ptp_sync_works() {
+        let bytes = [0x01, 0x1b, 0x19, 0x00, 0x00, 0x00, /* Destination */
+                     0x00, 0x1b, 0x19, 0x00, 0x00, 0x01, /* Source */
+                     0x88, 0xf7, /* PTP */
+                     0x00, 0x02, 0x00, 0x2c, /* Sync, version 2, length 44 */
+                     0x00, 0x00, 0x02, 0x00, /* Domain 0, two-step */
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x80, 0x00, /* Correction 1.5ns */
+                     0x00, 0x00, 0x00, 0x00,
+                     0x00, 0x1b, 0x19, 0xff, 0xfe, 0x00, 0x00, 0x01, 0x00, 0x01, /* Source port */
+                     0x00, 0x2a, 0x00, 0xfd, /* Sequence 42, control 0, interval -3 */
+                     0x00, 0x00, 0x65, 0x4a, 0x1b, 0x80, 0x00, 0x0f, 0x42, 0x40 /* Origin timestamp */];
+        let ptp = match parse_ethernet_frame(&bytes) {
+            IResult::Done(rest, frame) => {
+                assert_eq!(frame.ethertype, EtherType::PTP);
+                rest
+            }
+            other => panic!("expected a frame, got {:?}", other),
+        };
+        let header = PtpHeader {
+            transport_specific: 0,
+            message_type: PtpMessageType::Sync,
+            version: 2,
+            message_length: 44,
+            domain: 0,
+            flags: 0x0200,
+            correction_field: 0x18000,
+            source_port_identity: PtpPortIdentity {
+                clock_identity: [0x00, 0x1b, 0x19, 0xff, 0xfe, 0x00, 0x00, 0x01],
+                port_number: 1,
+            },
+            sequence_id: 42,
+            control_field: 0,
+            log_message_interval: -3,
+        };
+        assert_eq!(parse_ptp_header(ptp), IResult::Done(&ptp[34..], header));
+        let expectation = PtpMessage {
+            header,
+            body: PtpBody::Sync(PtpTimestamp { seconds: 0x654a1b80, nanoseconds: 1_000_000 }),
+        };
+        assert_eq!(parse_ptp_message(ptp), IResult::Done(&[][..], expectation));
+    }
+
+    #[test]
+    fn ptp_version_1_is_an_error() {
+        let mut bytes = [0u8; 44];
+        bytes[1] = 0x01;
+        bytes[3] = 0x2c;
+        match parse_ptp_message(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+}
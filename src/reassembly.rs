@@ -0,0 +1,273 @@
+//! Handles reassembly of fragmented IPv4 datagrams
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use ip::IPProtocol;
+use ipv4::{IPv4Address, IPv4Header};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct FragmentKey {
+    pub source_addr: IPv4Address,
+    pub dest_addr: IPv4Address,
+    pub id: u16,
+    pub protocol: IPProtocol,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReassemblyError {
+    /// A fragment overlaps different data already received for the same
+    /// datagram, or extends past the end given by its last fragment
+    Overlap,
+    /// The reassembled datagram would exceed the configured maximum size
+    TooLarge,
+}
+
+struct FragmentBuffer {
+    /// Received fragments keyed by their byte offset
+    fragments: BTreeMap<usize, Vec<u8>>,
+    /// Known once the fragment without More Fragments has arrived
+    total_len: Option<usize>,
+    first_seen: Instant,
+}
+
+impl FragmentBuffer {
+    /// Whether a fragment with exactly these bytes was already received at
+    /// `start`, as happens when a sender retransmits
+    fn is_duplicate(&self, start: usize, payload: &[u8]) -> bool {
+        self.fragments.get(&start).map(|data| &data[..]) == Some(payload)
+    }
+
+    fn overlaps(&self, start: usize, end: usize) -> bool {
+        let before = self.fragments.range(..end).next_back();
+        match before {
+            Some((&offset, data)) => offset + data.len() > start,
+            None => false,
+        }
+    }
+
+    fn reassemble(&self) -> Option<Vec<u8>> {
+        let total_len = self.total_len?;
+        let mut payload = Vec::with_capacity(total_len);
+        for (&offset, data) in &self.fragments {
+            if offset != payload.len() {
+                return None;
+            }
+            payload.extend_from_slice(data);
+        }
+        if payload.len() == total_len {
+            Some(payload)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct FragmentReassembler {
+    max_size: usize,
+    timeout: Duration,
+    buffers: HashMap<FragmentKey, FragmentBuffer>,
+}
+
+impl FragmentReassembler {
+    pub fn new(max_size: usize, timeout: Duration) -> FragmentReassembler {
+        FragmentReassembler {
+            max_size,
+            timeout,
+            buffers: HashMap::new(),
+        }
+    }
+
+    /// Adds the payload of one fragment, returning the complete datagram
+    /// payload once every fragment has arrived. An unfragmented datagram is
+    /// returned straight away. On error the partially reassembled datagram
+    /// is discarded.
+    pub fn add_fragment(&mut self,
+                        header: &IPv4Header,
+                        payload: &[u8],
+                        now: Instant)
+                        -> Result<Option<Vec<u8>>, ReassemblyError> {
+        // Drop any link-layer padding after the end of the datagram
+        let payload_len = (header.length as usize).saturating_sub(header.ihl as usize);
+        let payload = &payload[..payload.len().min(payload_len)];
+        let start = header.fragment_offset as usize * 8;
+        let end = start + payload.len();
+        let key = FragmentKey {
+            source_addr: header.source_addr,
+            dest_addr: header.dest_addr,
+            id: header.id,
+            protocol: header.protocol,
+        };
+
+        if end > self.max_size {
+            self.buffers.remove(&key);
+            return Err(ReassemblyError::TooLarge);
+        }
+        if start == 0 && !header.more_fragments() {
+            return Ok(Some(payload.to_vec()));
+        }
+
+        let complete = {
+            let buffer = self.buffers.entry(key).or_insert_with(|| {
+                FragmentBuffer {
+                    fragments: BTreeMap::new(),
+                    total_len: None,
+                    first_seen: now,
+                }
+            });
+            let last_mismatch = match buffer.total_len {
+                Some(total_len) => end > total_len || (!header.more_fragments() && end != total_len),
+                // Fragments already received must not start past the end
+                // given by the last fragment
+                None => !header.more_fragments() && buffer.fragments.range(end..).next().is_some(),
+            };
+            if !last_mismatch && buffer.is_duplicate(start, payload) {
+                Some(None)
+            } else if last_mismatch || buffer.overlaps(start, end) {
+                None
+            } else {
+                if !header.more_fragments() {
+                    buffer.total_len = Some(end);
+                }
+                buffer.fragments.insert(start, payload.to_vec());
+                Some(buffer.reassemble())
+            }
+        };
+
+        match complete {
+            None => {
+                self.buffers.remove(&key);
+                Err(ReassemblyError::Overlap)
+            }
+            Some(Some(datagram)) => {
+                self.buffers.remove(&key);
+                Ok(Some(datagram))
+            }
+            Some(None) => Ok(None),
+        }
+    }
+
+    /// Discards incomplete datagrams whose first fragment arrived more than
+    /// the configured timeout before `now`, returning how many were dropped.
+    pub fn expire(&mut self, now: Instant) -> usize {
+        let timeout = self.timeout;
+        let before = self.buffers.len();
+        self.buffers.retain(|_, buffer| now.duration_since(buffer.first_seen) < timeout);
+        before - self.buffers.len()
+    }
+
+    /// Number of datagrams still waiting for fragments
+    pub fn pending(&self) -> usize {
+        self.buffers.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ipv4::IPv4Protocol;
+
+    fn fragment_header(offset: u16, more_fragments: bool, payload_len: u16) -> IPv4Header {
+        IPv4Header {
+            version: 4,
+            ihl: 20,
+            tos: 0,
+            length: 20 + payload_len,
+            id: 0x1ae6,
+            flags: if more_fragments { 0x01 } else { 0x00 },
+            fragment_offset: offset,
+            ttl: 64,
+            protocol: IPv4Protocol::UDP,
+            chksum: 0,
+            source_addr: IPv4Address([10, 10, 1, 135]),
+            dest_addr: IPv4Address([10, 10, 1, 180]),
+            options: vec![],
+        }
+    }
+
+    #[test]
+    fn reassembles_out_of_order_fragments() {
+        let payload: Vec<u8> = (0..40).collect();
+        let now = Instant::now();
+        let mut reassembler = FragmentReassembler::new(65535, Duration::from_secs(30));
+
+        let last = fragment_header(4, false, 8);
+        let first = fragment_header(0, true, 16);
+        let middle = fragment_header(2, true, 16);
+        assert_eq!(reassembler.add_fragment(&last, &payload[32..], now), Ok(None));
+        assert_eq!(reassembler.add_fragment(&first, &payload[..16], now), Ok(None));
+        assert_eq!(reassembler.pending(), 1);
+        assert_eq!(reassembler.add_fragment(&middle, &payload[16..32], now), Ok(Some(payload)));
+        assert_eq!(reassembler.pending(), 0);
+    }
+
+    #[test]
+    fn unfragmented_datagram_passes_through() {
+        let mut reassembler = FragmentReassembler::new(65535, Duration::from_secs(30));
+        let header = fragment_header(0, false, 4);
+        assert_eq!(reassembler.add_fragment(&header, &[1, 2, 3, 4, 0, 0], Instant::now()),
+                   Ok(Some(vec![1, 2, 3, 4])));
+    }
+
+    #[test]
+    fn rejects_overlapping_fragments() {
+        let now = Instant::now();
+        let mut reassembler = FragmentReassembler::new(65535, Duration::from_secs(30));
+        let first = fragment_header(0, true, 16);
+        let overlapping = fragment_header(1, true, 16);
+        assert_eq!(reassembler.add_fragment(&first, &[0; 16], now), Ok(None));
+        assert_eq!(reassembler.add_fragment(&overlapping, &[0; 16], now),
+                   Err(ReassemblyError::Overlap));
+        assert_eq!(reassembler.pending(), 0);
+    }
+
+    #[test]
+    fn accepts_duplicate_fragments() {
+        let payload: Vec<u8> = (0..24).collect();
+        let now = Instant::now();
+        let mut reassembler = FragmentReassembler::new(65535, Duration::from_secs(30));
+        let first = fragment_header(0, true, 16);
+        let last = fragment_header(2, false, 8);
+        assert_eq!(reassembler.add_fragment(&first, &payload[..16], now), Ok(None));
+        assert_eq!(reassembler.add_fragment(&first, &payload[..16], now), Ok(None));
+        assert_eq!(reassembler.pending(), 1);
+        assert_eq!(reassembler.add_fragment(&last, &payload[16..], now), Ok(Some(payload)));
+    }
+
+    #[test]
+    fn rejects_fragments_past_the_last_fragment() {
+        let now = Instant::now();
+        let mut reassembler = FragmentReassembler::new(65535, Duration::from_secs(30));
+        let beyond = fragment_header(4, true, 8);
+        let last = fragment_header(2, false, 8);
+        assert_eq!(reassembler.add_fragment(&beyond, &[0; 8], now), Ok(None));
+        assert_eq!(reassembler.add_fragment(&last, &[0; 8], now), Err(ReassemblyError::Overlap));
+        assert_eq!(reassembler.pending(), 0);
+
+        assert_eq!(reassembler.add_fragment(&last, &[0; 8], now), Ok(None));
+        assert_eq!(reassembler.add_fragment(&beyond, &[0; 8], now), Err(ReassemblyError::Overlap));
+        assert_eq!(reassembler.pending(), 0);
+    }
+
+    #[test]
+    fn rejects_oversized_datagrams() {
+        let mut reassembler = FragmentReassembler::new(32, Duration::from_secs(30));
+        let now = Instant::now();
+        let first = fragment_header(0, true, 16);
+        assert_eq!(reassembler.add_fragment(&first, &[0; 16], now), Ok(None));
+        assert_eq!(reassembler.pending(), 1);
+        let header = fragment_header(4, true, 16);
+        assert_eq!(reassembler.add_fragment(&header, &[0; 16], now), Err(ReassemblyError::TooLarge));
+        assert_eq!(reassembler.pending(), 0);
+    }
+
+    #[test]
+    fn expires_incomplete_datagrams() {
+        let now = Instant::now();
+        let mut reassembler = FragmentReassembler::new(65535, Duration::from_secs(30));
+        let first = fragment_header(0, true, 16);
+        assert_eq!(reassembler.add_fragment(&first, &[0; 16], now), Ok(None));
+        assert_eq!(reassembler.expire(now + Duration::from_secs(10)), 0);
+        assert_eq!(reassembler.expire(now + Duration::from_secs(30)), 1);
+        assert_eq!(reassembler.pending(), 0);
+    }
+}
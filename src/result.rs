@@ -0,0 +1,194 @@
+//! `Result`-returning variants of the top-level parsers
+//!
+//! These share their names with the nom parsers elsewhere in the crate but
+//! return `Result<(&[u8], T), PktError>`, so callers don't need to depend on
+//! nom themselves.
+
+use alloc::vec::Vec;
+
+use ah::{self, AuthHeader};
+use arp::{self, ArpPacket};
+use bgp::{self, BgpHeader, BgpMessage};
+use cdp::{self, CdpPacket};
+use dhcp::{self, DhcpPacket};
+use dhcpv6::{self, Dhcpv6Message};
+use dns::{self, DnsHeader, DnsMessage};
+use eapol::{self, EapolFrame};
+use error::{from_iresult, Layer, PktError};
+use esp::{self, EspHeader};
+use ethernet::{self, EthernetFrame, Ethernet8023Frame, LlcHeader, VlanEthernetFrame};
+use gtp::{self, GtpU};
+use http::{self, HttpRequest};
+use icmp::{self, IcmpHeader};
+use icmpv6::{self, Icmpv6Header, NdpOption};
+use ieee80211::{self, Ieee80211Header, RadiotapHeader};
+use igmp::{self, IgmpMessage};
+use ipv4::{self, IPv4Header};
+use ipv6::{self, IPv6Header, Ipv6FragmentHeader};
+use lacp::{self, LacpPdu};
+use lldp::{self, LldpTlv};
+use modbus::{self, ModbusAdu};
+use mpls::{self, MplsLabel};
+use ospf::{self, OspfHeader, OspfPacket};
+use ptp::{self, PtpHeader, PtpMessage};
+use rtp::{self, RtpHeader};
+use sctp::{self, SctpChunk, SctpHeader};
+use sll::{self, SllHeader};
+use someip::{self, SomeIpHeader, SomeIpMessage};
+use stp::{self, Bpdu};
+use tcp::{self, TcpHeader};
+use tls::{self, TlsHandshakeHeader, TlsRecordHeader};
+use udp::{self, UdpHeader, UdpLiteHeader};
+
+macro_rules! result_parser {
+    ($name:ident, $parser:path, $layer:expr, $out:ty) => (
+        pub fn $name(i: &[u8]) -> Result<(&[u8], $out), PktError> {
+            from_iresult($layer, $parser(i))
+        }
+    )
+}
+
+result_parser!(parse_ah_header, ah::parse_ah_header, Layer::Ah, AuthHeader);
+result_parser!(parse_arp_pkt, arp::parse_arp_pkt, Layer::Arp, ArpPacket);
+result_parser!(parse_bgp_header, bgp::parse_bgp_header, Layer::Bgp, BgpHeader);
+result_parser!(parse_bgp_message, bgp::parse_bgp_message, Layer::Bgp, BgpMessage);
+result_parser!(parse_cdp_packet, cdp::parse_cdp_packet, Layer::Cdp, CdpPacket);
+result_parser!(parse_dhcp_packet, dhcp::parse_dhcp_packet, Layer::Dhcp, DhcpPacket);
+result_parser!(parse_dhcpv6_message, dhcpv6::parse_dhcpv6_message, Layer::Dhcpv6, Dhcpv6Message);
+result_parser!(parse_dns_header, dns::parse_dns_header, Layer::Dns, DnsHeader);
+result_parser!(parse_dns_message, dns::parse_dns_message, Layer::Dns, DnsMessage);
+result_parser!(parse_eapol_frame, eapol::parse_eapol_frame, Layer::Eapol, EapolFrame);
+result_parser!(parse_esp_header, esp::parse_esp_header, Layer::Esp, EspHeader);
+result_parser!(parse_ethernet_frame, ethernet::parse_ethernet_frame, Layer::Ethernet, EthernetFrame);
+result_parser!(parse_vlan_ethernet_frame, ethernet::parse_vlan_ethernet_frame, Layer::Vlan, VlanEthernetFrame);
+result_parser!(parse_ethernet_frame_8023, ethernet::parse_ethernet_frame_8023, Layer::Ethernet, Ethernet8023Frame);
+result_parser!(parse_llc_snap, ethernet::parse_llc_snap, Layer::Ethernet, LlcHeader);
+result_parser!(parse_gtpu_header, gtp::parse_gtpu_header, Layer::Gtp, GtpU);
+result_parser!(parse_http_request, http::parse_http_request, Layer::Http, HttpRequest);
+result_parser!(parse_icmp_header, icmp::parse_icmp_header, Layer::Icmp, IcmpHeader);
+result_parser!(parse_icmpv6_header, icmpv6::parse_icmpv6_header, Layer::Icmpv6, Icmpv6Header);
+result_parser!(parse_ndp_options, icmpv6::parse_ndp_options, Layer::Icmpv6, Vec<NdpOption>);
+result_parser!(parse_radiotap_header, ieee80211::parse_radiotap_header, Layer::Radiotap, RadiotapHeader);
+result_parser!(parse_ieee80211_header, ieee80211::parse_ieee80211_header, Layer::Ieee80211, Ieee80211Header);
+result_parser!(parse_igmp_message, igmp::parse_igmp_message, Layer::Igmp, IgmpMessage);
+result_parser!(parse_ipv4_header, ipv4::parse_ipv4_header, Layer::Ipv4, IPv4Header);
+result_parser!(parse_ipv6_header, ipv6::parse_ipv6_header, Layer::Ipv6, IPv6Header);
+result_parser!(parse_ipv6_fragment_header, ipv6::parse_ipv6_fragment_header, Layer::Ipv6, Ipv6FragmentHeader);
+result_parser!(parse_lacp_pdu, lacp::parse_lacp_pdu, Layer::Lacp, LacpPdu);
+result_parser!(parse_lldp_tlvs, lldp::parse_lldp_tlvs, Layer::Lldp, Vec<LldpTlv>);
+result_parser!(parse_modbus_request, modbus::parse_modbus_request, Layer::Modbus, ModbusAdu);
+result_parser!(parse_modbus_response, modbus::parse_modbus_response, Layer::Modbus, ModbusAdu);
+result_parser!(parse_mpls_stack, mpls::parse_mpls_stack, Layer::Mpls, Vec<MplsLabel>);
+result_parser!(parse_ospf_header, ospf::parse_ospf_header, Layer::Ospf, OspfHeader);
+result_parser!(parse_ospf_packet, ospf::parse_ospf_packet, Layer::Ospf, OspfPacket);
+result_parser!(parse_ptp_header, ptp::parse_ptp_header, Layer::Ptp, PtpHeader);
+result_parser!(parse_ptp_message, ptp::parse_ptp_message, Layer::Ptp, PtpMessage);
+result_parser!(parse_rtp_header, rtp::parse_rtp_header, Layer::Rtp, RtpHeader);
+result_parser!(parse_sctp_header, sctp::parse_sctp_header, Layer::Sctp, SctpHeader);
+result_parser!(parse_sctp_chunks, sctp::parse_sctp_chunks, Layer::Sctp, Vec<SctpChunk>);
+result_parser!(parse_sll_header, sll::parse_sll_header, Layer::Sll, SllHeader);
+result_parser!(parse_someip_header, someip::parse_someip_header, Layer::SomeIp, SomeIpHeader);
+result_parser!(parse_someip_message, someip::parse_someip_message, Layer::SomeIp, SomeIpMessage);
+result_parser!(parse_bpdu, stp::parse_bpdu, Layer::Stp, Bpdu);
+result_parser!(parse_tcp_header, tcp::parse_tcp_header, Layer::Tcp, TcpHeader);
+result_parser!(parse_tcp_header_strict, tcp::parse_tcp_header_strict, Layer::Tcp, TcpHeader);
+result_parser!(parse_tls_record_header, tls::parse_tls_record_header, Layer::Tls, TlsRecordHeader);
+result_parser!(parse_tls_handshake_header, tls::parse_tls_handshake_header, Layer::Tls, TlsHandshakeHeader);
+result_parser!(parse_udp_header, udp::parse_udp_header, Layer::Udp, UdpHeader);
+result_parser!(parse_udplite_header, udp::parse_udplite_header, Layer::Udp, UdpLiteHeader);
+
+/// Parses an Ethernet frame, returning it along with the payload that
+/// follows the header
+pub fn parse_ethernet_frame_with_payload(i: &[u8]) -> Result<(EthernetFrame, &[u8]), PktError> {
+    parse_ethernet_frame(i).map(|(payload, frame)| (frame, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use error::{Layer, PktError};
+    use ip::IPProtocol;
+
+    #[test]
+    fn incomplete_ipv4_header_is_incomplete() {
+        let bytes = [0x45, 0x00, 0x00, 0x38, 0x76, 0xf4, 0x40, 0x00, 0x40, 0x06];
+        match parse_ipv4_header(&bytes) {
+            Err(PktError::Incomplete(_)) => {}
+            other => panic!("expected Incomplete, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unknown_ip_protocol_is_kept() {
+        let bytes = [0x45, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x00, 0x40, 0xfd,
+                     0x00, 0x00, 0x0a, 0x0a, 0x01, 0x87, 0x0a, 0x0a, 0x01, 0xb4];
+        let (_, header) = parse_ipv4_header(&bytes).unwrap();
+        assert_eq!(header.protocol, IPProtocol::Other(0xfd));
+    }
+
+    #[test]
+    fn ipv6_packet_is_not_an_ipv4_header() {
+        let mut bytes = [0u8; 40];
+        bytes[0] = 0x60;
+        let expectation = PktError::InvalidHeader {
+            layer: Layer::Ipv4,
+            reason: "IP version does not match the parser",
+        };
+        assert_eq!(parse_ipv4_header(&bytes), Err(expectation));
+    }
+
+    #[test]
+    fn ethernet_frame_with_payload_returns_the_payload() {
+        let bytes = [0x00, 0x23, 0x54, 0x07, 0x93, 0x6c,
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x08, 0x00,
+                     0x45, 0x00];
+        let (frame, payload) = parse_ethernet_frame_with_payload(&bytes).unwrap();
+        assert_eq!(frame.ethertype, ethernet::EtherType::IPv4);
+        assert_eq!(payload, &[0x45, 0x00]);
+    }
+
+    #[test]
+    fn unknown_ethertype_is_kept() {
+        let bytes = [0x00, 0x23, 0x54, 0x07, 0x93, 0x6c,
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x88, 0xb5];
+        let (_, frame) = parse_ethernet_frame(&bytes).unwrap();
+        assert_eq!(frame.ethertype, ethernet::EtherType::Other(0x88b5));
+    }
+
+    #[test]
+    fn ethernet_frame_8023_parses() {
+        let bytes = [0x01, 0x80, 0xc2, 0x00, 0x00, 0x00,
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b,
+                     0x00, 0x03, /* Length */
+                     0x42, 0x42, 0x03 /* LLC */];
+        match parse_ethernet_frame_8023(&bytes).unwrap() {
+            (rest, ethernet::Ethernet8023Frame::Ieee8023(frame)) => {
+                assert!(rest.is_empty());
+                assert_eq!(frame.llc.dsap, 0x42);
+            }
+            other => panic!("expected an 802.3 frame, got {:?}", other),
+        }
+        assert!(parse_llc_snap(&bytes[14..16]).is_err());
+    }
+
+    #[test]
+    fn sll_llc_protocol_parses() {
+        let bytes = [0x00, 0x04, 0x00, 0x01, 0x00, 0x06,
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b, 0x00, 0x00,
+                     0x00, 0x04, /* LLC */
+                     0xaa, 0xaa, 0x03];
+        let (rest, header) = parse_sll_header(&bytes).unwrap();
+        assert_eq!(header.protocol, ethernet::EtherType::Other(0x0004));
+        assert_eq!(rest, &[0xaa, 0xaa, 0x03]);
+    }
+
+    #[test]
+    fn malformed_dhcp_is_invalid_header() {
+        let mut bytes = [0u8; 240];
+        bytes[236..].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(parse_dhcp_packet(&bytes),
+                   Err(PktError::InvalidHeader { layer: Layer::Dhcp, reason: "unexpected constant" }));
+    }
+}
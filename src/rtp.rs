@@ -0,0 +1,109 @@
+//! Handles parsing of RTP headers
+
+use alloc::vec::Vec;
+
+use nom::{IResult, be_u16, be_u32};
+
+// RTP Header Format (RFC 3550)
+//
+//    0                   1                   2                   3
+//    0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |V=2|P|X|  CC   |M|     PT      |       sequence number         |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |                           timestamp                           |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |           synchronization source (SSRC) identifier            |
+//   +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
+//   |            contributing source (CSRC) identifiers             |
+//   |                             ....                              |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RtpHeader {
+    pub version: u8,
+    pub padding: bool,
+    pub extension: bool,
+    /// Number of CSRC identifiers following the fixed header
+    pub cc: u8,
+    pub marker: bool,
+    pub payload_type: u8,
+    pub sequence_number: u16,
+    pub timestamp: u32,
+    pub ssrc: u32,
+    pub csrc: Vec<u32>,
+}
+
+named!(first_two_bytes<&[u8], (u8, u8, u8, u8, u8, u8)>,
+    bits!(tuple!(
+        take_bits!(u8, 2),
+        take_bits!(u8, 1),
+        take_bits!(u8, 1),
+        take_bits!(u8, 4),
+        take_bits!(u8, 1),
+        take_bits!(u8, 7))));
+
+named!(rtp_header<&[u8], RtpHeader>, chain!(
+    fields: first_two_bytes ~
+    sequence_number: be_u16 ~
+    timestamp: be_u32 ~
+    ssrc: be_u32 ~
+    csrc: count!(be_u32, fields.3 as usize),
+    || RtpHeader {
+        version: fields.0,
+        padding: fields.1 == 1,
+        extension: fields.2 == 1,
+        cc: fields.3,
+        marker: fields.4 == 1,
+        payload_type: fields.5,
+        sequence_number,
+        timestamp,
+        ssrc,
+        csrc,
+    }
+));
+
+/// Parses the fixed header and CSRC list. A header extension, signalled by
+/// `extension`, is left at the start of the remaining input.
+pub fn parse_rtp_header(i: &[u8]) -> IResult<&[u8], RtpHeader> {
+    rtp_header(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_rtp_header, RtpHeader};
+    use nom::IResult;
+
+    #[test]
+    fn rtp_header_with_one_csrc_works() {
+        let bytes = [0x81, 0x80, /* V=2, CC=1, marker, PT 0 (PCMU) */
+                     0x1f, 0x4a, /* Sequence number */
+                     0x00, 0x00, 0xa0, 0x00, /* Timestamp */
+                     0xde, 0xad, 0xbe, 0xef, /* SSRC */
+                     0x12, 0x34, 0x56, 0x78, /* CSRC */
+                     0xff, 0xfe, 0x7f, 0x7e /* G.711 samples */];
+        let expectation = RtpHeader {
+            version: 2,
+            padding: false,
+            extension: false,
+            cc: 1,
+            marker: true,
+            payload_type: 0,
+            sequence_number: 8010,
+            timestamp: 40960,
+            ssrc: 0xdeadbeef,
+            csrc: vec![0x12345678],
+        };
+        assert_eq!(parse_rtp_header(&bytes), IResult::Done(&bytes[16..], expectation));
+    }
+
+    #[test]
+    fn rtp_header_with_missing_csrc_is_incomplete() {
+        let bytes = [0x82, 0x08, 0x00, 0x01, 0x00, 0x00, 0x00, 0xa0,
+                     0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02];
+        match parse_rtp_header(&bytes) {
+            IResult::Incomplete(_) => {}
+            _ => panic!(),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Handles parsing of SCTP headers and chunks
+
+use alloc::vec::Vec;
+
+use nom::{IResult, be_u8, be_u16, be_u32};
+
+use options::for_each_option;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SctpHeader {
+    pub source_port: u16,
+    pub dest_port: u16,
+    pub verification_tag: u32,
+    pub checksum: u32,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct SctpChunk {
+    pub chunk_type: u8,
+    pub flags: u8,
+    /// Length of the chunk including its 4-byte header, excluding padding
+    pub length: u16,
+    pub data: Vec<u8>,
+}
+
+named!(sctp_header<&[u8], SctpHeader>, chain!(
+    source_port: be_u16 ~
+    dest_port: be_u16 ~
+    verification_tag: be_u32 ~
+    checksum: be_u32,
+    || SctpHeader { source_port, dest_port, verification_tag, checksum }
+));
+
+named!(sctp_chunk<&[u8], SctpChunk>, chain!(
+    chunk_type: be_u8 ~
+    flags: be_u8 ~
+    length: map_opt!(be_u16, |l: u16| if l >= 4 { Some(l) } else { None }) ~
+    data: take!(length - 4),
+    || SctpChunk { chunk_type, flags, length, data: data.to_vec() }
+));
+
+pub fn parse_sctp_header(i: &[u8]) -> IResult<&[u8], SctpHeader> {
+    sctp_header(i)
+}
+
+/// Parses every chunk following the common header, skipping the padding
+/// that aligns each chunk to 4 bytes
+pub fn parse_sctp_chunks(i: &[u8]) -> IResult<&[u8], Vec<SctpChunk>> {
+    let mut chunks = Vec::new();
+    for_each_option(i, sctp_chunk, |chunk, rest| {
+        let padding = (4 - chunk.length as usize % 4) % 4;
+        chunks.push(chunk);
+        Ok(&rest[padding.min(rest.len())..])
+    }).map(|()| chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::IResult;
+    const EMPTY_SLICE: &[u8] = &[];
+
+    #[test]
+    fn sctp_init_works() {
+        let bytes = [0x80, 0x44, /* Source port */
+                     0x0b, 0x59, /* Dest port */
+                     0x00, 0x00, 0x00, 0x00, /* Verification tag */
+                     0x4c, 0x8a, 0x2d, 0x1e, /* Checksum */
+                     0x01, 0x00, 0x00, 0x14, /* INIT chunk, length 20 */
+                     0x5b, 0xf1, 0x9a, 0x02, /* Initiate tag */
+                     0x00, 0x01, 0xa0, 0x00, /* Receiver window credit */
+                     0x00, 0x0a, 0xff, 0xff, /* Outbound and inbound streams */
+                     0x12, 0x8e, 0x3f, 0x6d /* Initial TSN */];
+        let header = SctpHeader {
+            source_port: 32836,
+            dest_port: 2905,
+            verification_tag: 0,
+            checksum: 0x4c8a2d1e,
+        };
+        let chunks = vec![SctpChunk {
+            chunk_type: 1,
+            flags: 0,
+            length: 20,
+            data: bytes[16..].to_vec(),
+        }];
+        assert_eq!(parse_sctp_header(&bytes), IResult::Done(&bytes[12..], header));
+        assert_eq!(parse_sctp_chunks(&bytes[12..]), IResult::Done(EMPTY_SLICE, chunks));
+    }
+
+    #[test]
+    fn sctp_data_chunks_skip_padding() {
+        let bytes = [0x00, 0x03, 0x00, 0x13, /* DATA chunk, flags B|E, length 19 */
+                     0x12, 0x8e, 0x3f, 0x6d, /* TSN */
+                     0x00, 0x00, 0x00, 0x00, /* Stream identifier and sequence number */
+                     0x00, 0x00, 0x00, 0x03, /* Payload protocol identifier */
+                     0x01, 0x02, 0x03, 0x00, /* Data and one byte of padding */
+                     0x03, 0x00, 0x00, 0x04 /* SACK chunk without parameters */];
+        let chunks = vec![
+            SctpChunk {
+                chunk_type: 0,
+                flags: 3,
+                length: 19,
+                data: bytes[4..19].to_vec(),
+            },
+            SctpChunk {
+                chunk_type: 3,
+                flags: 0,
+                length: 4,
+                data: vec![],
+            },
+        ];
+        assert_eq!(parse_sctp_chunks(&bytes), IResult::Done(EMPTY_SLICE, chunks));
+    }
+
+    #[test]
+    fn sctp_chunk_rejects_short_length() {
+        match parse_sctp_chunks(&[0x00, 0x00, 0x00, 0x02]) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Handles parsing of Linux cooked capture (SLL) headers
+
+use alloc::vec::Vec;
+
+use nom::{IResult, be_u16};
+
+use ethernet::{ethertype, EtherType};
+
+// SLL Header Format (LINKTYPE_LINUX_SLL, 113)
+//
+//    0                   1                   2                   3
+//    0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |          Packet Type          |   Link-layer Address Type     |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   | Link-layer Address Length     |                               |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               +
+//   |                Link-layer Address (8 bytes)                   |
+//   +                               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |                               |           Protocol            |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SllPacketType {
+    /// Sent to us
+    Host,
+    Broadcast,
+    Multicast,
+    /// Sent by somebody else to somebody else
+    OtherHost,
+    /// Sent by us
+    Outgoing,
+    Other(u16),
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct SllHeader {
+    pub packet_type: SllPacketType,
+    /// The ARPHRD_ type of the interface, e.g. 1 for Ethernet
+    pub address_type: u16,
+    pub address_len: u16,
+    /// The first `address_len` bytes of the 8-byte address field
+    pub address: Vec<u8>,
+    /// Usually an ethertype, but Linux also uses values below 0x0600 here,
+    /// e.g. 0x0004 for LLC frames and 0x0001 for Novell 802.3 frames; those
+    /// are kept as `EtherType::Other`
+    pub protocol: EtherType,
+}
+
+impl From<u16> for SllPacketType {
+    fn from(raw: u16) -> Self {
+        match raw {
+            0 => SllPacketType::Host,
+            1 => SllPacketType::Broadcast,
+            2 => SllPacketType::Multicast,
+            3 => SllPacketType::OtherHost,
+            4 => SllPacketType::Outgoing,
+            other => SllPacketType::Other(other),
+        }
+    }
+}
+
+named!(sll_header<&[u8], SllHeader>, chain!(
+    packet_type: be_u16 ~
+    address_type: be_u16 ~
+    address_len: be_u16 ~
+    address: take!(8) ~
+    protocol: ethertype,
+    || SllHeader {
+        packet_type: packet_type.into(),
+        address_type,
+        address_len,
+        address: address[..(address_len as usize).min(8)].to_vec(),
+        protocol,
+    }
+));
+
+pub fn parse_sll_header(i: &[u8]) -> IResult<&[u8], SllHeader> {
+    sll_header(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_sll_header, SllHeader, SllPacketType};
+    use ethernet::EtherType;
+    use ipv4::parse_ipv4_header;
+    use nom::IResult;
+
+    #[test]
+    fn sll_header_in_front_of_ipv4_works() {
+        let bytes = [0x00, 0x00, /* Packet type: to us */
+                     0x00, 0x01, /* ARPHRD_ETHER */
+                     0x00, 0x06, /* Address length */
+                     0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b, 0x00, 0x00, /* Address and padding */
+                     0x08, 0x00, /* Protocol */
+                     0x45, 0x00, 0x00, 0x1c, 0x1a, 0xe6, 0x40, 0x00,
+                     0x40, 0x11, 0x22, 0xed, 0x0a, 0x0a, 0x01, 0x87,
+                     0x0a, 0x0a, 0x01, 0xb4];
+        let expectation = SllHeader {
+            packet_type: SllPacketType::Host,
+            address_type: 1,
+            address_len: 6,
+            address: vec![0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b],
+            protocol: EtherType::IPv4,
+        };
+        assert_eq!(parse_sll_header(&bytes), IResult::Done(&bytes[16..], expectation));
+        if let IResult::Done(_, header) = parse_ipv4_header(&bytes[16..]) {
+            assert_eq!(header.dest_addr.to_string(), "10.10.1.180");
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn sll_packet_type_other_is_preserved() {
+        let bytes = [0x00, 0x07, 0x03, 0x04, 0x00, 0x00,
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                     0x86, 0xdd];
+        if let IResult::Done(_, header) = parse_sll_header(&bytes) {
+            assert_eq!(header.packet_type, SllPacketType::Other(7));
+            assert_eq!(header.address_type, 0x0304);
+            assert!(header.address.is_empty());
+            assert_eq!(header.protocol, EtherType::IPv6);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn sll_protocols_below_0x0600_are_kept() {
+        let mut bytes = [0x00, 0x01, 0x00, 0x01, 0x00, 0x06,
+                         0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b, 0x00, 0x00,
+                         0x00, 0x04, /* LLC */
+                         0x42, 0x42, 0x03 /* STP */];
+        match parse_sll_header(&bytes) {
+            IResult::Done(rest, header) => {
+                assert_eq!(header.packet_type, SllPacketType::Broadcast);
+                assert_eq!(header.protocol, EtherType::Other(0x0004));
+                assert_eq!(header.protocol.to_raw(), 0x0004);
+                assert_eq!(rest, &[0x42, 0x42, 0x03]);
+            }
+            other => panic!("expected a header, got {:?}", other),
+        }
+
+        bytes[15] = 0x01;
+        match parse_sll_header(&bytes) {
+            IResult::Done(_, header) => assert_eq!(header.protocol, EtherType::Other(0x0001)),
+            other => panic!("expected a header, got {:?}", other),
+        }
+    }
+}
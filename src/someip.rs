@@ -0,0 +1,153 @@
+//! Handles parsing of SOME/IP messages
+//!
+//! SOME/IP is carried over UDP or TCP, with no fixed port. A datagram or
+//! segment may hold several messages back to back, so the remaining input
+//! after a message is the next one.
+
+use alloc::vec::Vec;
+
+use nom::{IResult, be_u8, be_u16, be_u32};
+
+/// Length of the header, up to and including the return code
+pub const SOMEIP_HEADER_LEN: usize = 16;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SomeIpMessageType {
+    Request,
+    /// A request that expects no response
+    RequestNoReturn,
+    Notification,
+    Response,
+    Error,
+    /// Any other type, including the segmented (SOME/IP-TP) variants
+    Other(u8),
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SomeIpHeader {
+    pub service_id: u16,
+    pub method_id: u16,
+    /// Length of the rest of the message, starting at `client_id`
+    pub length: u32,
+    pub client_id: u16,
+    pub session_id: u16,
+    pub protocol_version: u8,
+    pub interface_version: u8,
+    pub message_type: SomeIpMessageType,
+    /// 0 (E_OK) for requests and successful responses
+    pub return_code: u8,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct SomeIpMessage {
+    pub header: SomeIpHeader,
+    pub payload: Vec<u8>,
+}
+
+impl From<u8> for SomeIpMessageType {
+    fn from(raw: u8) -> Self {
+        match raw {
+            0x00 => SomeIpMessageType::Request,
+            0x01 => SomeIpMessageType::RequestNoReturn,
+            0x02 => SomeIpMessageType::Notification,
+            0x80 => SomeIpMessageType::Response,
+            0x81 => SomeIpMessageType::Error,
+            other => SomeIpMessageType::Other(other),
+        }
+    }
+}
+
+impl SomeIpHeader {
+    /// The message ID, the service and method IDs together
+    pub fn message_id(&self) -> u32 {
+        (self.service_id as u32) << 16 | self.method_id as u32
+    }
+
+    /// The request ID, the client and session IDs together
+    pub fn request_id(&self) -> u32 {
+        (self.client_id as u32) << 16 | self.session_id as u32
+    }
+}
+
+// The length covers the 8 header bytes after it, so cannot be less than 8
+named!(someip_header<&[u8], SomeIpHeader>, chain!(
+    service_id: be_u16 ~
+    method_id: be_u16 ~
+    length: map_opt!(be_u32, |l: u32| if l >= 8 { Some(l) } else { None }) ~
+    client_id: be_u16 ~
+    session_id: be_u16 ~
+    protocol_version: be_u8 ~
+    interface_version: be_u8 ~
+    message_type: be_u8 ~
+    return_code: be_u8,
+    || SomeIpHeader {
+        service_id,
+        method_id,
+        length,
+        client_id,
+        session_id,
+        protocol_version,
+        interface_version,
+        message_type: SomeIpMessageType::from(message_type),
+        return_code,
+    }
+));
+
+named!(someip_message<&[u8], SomeIpMessage>, chain!(
+    header: someip_header ~
+    payload: take!(header.length as usize - 8),
+    || SomeIpMessage { header, payload: payload.to_vec() }
+));
+
+/// Parses a message header, leaving the payload in the remaining input
+pub fn parse_someip_header(i: &[u8]) -> IResult<&[u8], SomeIpHeader> {
+    someip_header(i)
+}
+
+/// Parses a message header and the payload its length covers
+pub fn parse_someip_message(i: &[u8]) -> IResult<&[u8], SomeIpMessage> {
+    someip_message(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::{IResult, Needed};
+
+    #[test]
+    fn someip_request_works() {
+        let bytes = [0x12, 0x34, 0x00, 0x01, /* Service 0x1234, method 1 */
+                     0x00, 0x00, 0x00, 0x0c, /* Length */
+                     0x00, 0x10, 0x00, 0x02, /* Client 0x10, session 2 */
+                     0x01, 0x01, 0x00, 0x00, /* Versions, request, E_OK */
+                     0xde, 0xad, 0xbe, 0xef, /* Payload */
+                     0x12, 0x34 /* Next message */];
+        let header = SomeIpHeader {
+            service_id: 0x1234,
+            method_id: 1,
+            length: 12,
+            client_id: 0x10,
+            session_id: 2,
+            protocol_version: 1,
+            interface_version: 1,
+            message_type: SomeIpMessageType::Request,
+            return_code: 0,
+        };
+        assert_eq!((header.message_id(), header.request_id()), (0x12340001, 0x00100002));
+        assert_eq!(parse_someip_header(&bytes), IResult::Done(&bytes[SOMEIP_HEADER_LEN..], header));
+        let expectation = SomeIpMessage { header, payload: vec![0xde, 0xad, 0xbe, 0xef] };
+        assert_eq!(parse_someip_message(&bytes), IResult::Done(&bytes[20..], expectation));
+    }
+
+    #[test]
+    fn someip_truncated_payload_is_incomplete() {
+        let bytes = [0x12, 0x34, 0x80, 0x01, 0x00, 0x00, 0x00, 0x0c,
+                     0x00, 0x00, 0x00, 0x01, 0x01, 0x01, 0x02, 0x00, 0xde];
+        match parse_someip_message(&bytes) {
+            IResult::Incomplete(Needed::Size(_)) => {}
+            other => panic!("expected incomplete input, got {:?}", other),
+        }
+        match parse_someip_header(&bytes) {
+            IResult::Done(_, header) => assert_eq!(header.message_type, SomeIpMessageType::Notification),
+            other => panic!("expected a header, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! Detects the identification string that opens an SSH connection
+//!
+//! Each side of an SSH connection starts by sending a line such as
+//! `SSH-2.0-OpenSSH_9.6 Ubuntu\r\n` (RFC 4253 section 4.2), which names the
+//! protocol version and the implementation, so it can fingerprint a flow
+//! from its first TCP payload.
+
+use alloc::string::String;
+
+/// The TCP port SSH servers listen on
+pub const SSH_PORT: u16 = 22;
+/// Longest identification line allowed, the line ending included
+pub const MAX_SSH_BANNER_LEN: usize = 255;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SshBanner {
+    /// `"2.0"`, or `"1.99"` for a server also accepting version 1
+    pub protocol_version: String,
+    pub software_version: String,
+    /// The text after the first space, if any
+    pub comments: Option<String>,
+}
+
+/// Parses the identification line at the start of `payload`, returning
+/// `None` if it is not one. The line may end in a bare LF, which older
+/// implementations send.
+pub fn parse_ssh_banner(payload: &[u8]) -> Option<SshBanner> {
+    if !payload.starts_with(b"SSH-") {
+        return None;
+    }
+    let window = &payload[..payload.len().min(MAX_SSH_BANNER_LEN)];
+    let end = window.iter().position(|&b| b == b'\n')?;
+    let line = &window[4..end];
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let line = core::str::from_utf8(line).ok()?;
+
+    let (ids, comments) = match line.find(' ') {
+        Some(space) => (&line[..space], Some(String::from(&line[space + 1..]))),
+        None => (line, None),
+    };
+    let dash = ids.find('-')?;
+    let (protocol_version, software_version) = (&ids[..dash], &ids[dash + 1..]);
+    if protocol_version.is_empty() || software_version.is_empty() {
+        return None;
+    }
+    Some(SshBanner {
+        protocol_version: String::from(protocol_version),
+        software_version: String::from(software_version),
+        comments,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_ssh_banner, SshBanner};
+
+    #[test]
+    fn ssh_banner_works() {
+        let payload = b"SSH-2.0-OpenSSH_9.6p1 Ubuntu-3ubuntu13\r\n\x00\x00\x05\xdc\x07\x14";
+        assert_eq!(parse_ssh_banner(payload), Some(SshBanner {
+            protocol_version: "2.0".into(),
+            software_version: "OpenSSH_9.6p1".into(),
+            comments: Some("Ubuntu-3ubuntu13".into()),
+        }));
+        assert_eq!(parse_ssh_banner(b"SSH-1.99-Cisco-1.25\n"), Some(SshBanner {
+            protocol_version: "1.99".into(),
+            software_version: "Cisco-1.25".into(),
+            comments: None,
+        }));
+    }
+
+    #[test]
+    fn non_ssh_payloads_are_not_banners() {
+        assert_eq!(parse_ssh_banner(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n"), None);
+        assert_eq!(parse_ssh_banner(b"SSH-2.0-OpenSSH_9.6"), None);
+        assert_eq!(parse_ssh_banner(b"SSH-2.0\r\n"), None);
+        assert_eq!(parse_ssh_banner(b""), None);
+    }
+}
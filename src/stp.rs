@@ -0,0 +1,201 @@
+//! Handles parsing of Spanning Tree (STP and RSTP) BPDUs
+//!
+//! BPDUs are carried in 802.3 frames sent to `STP_MULTICAST`, behind an LLC
+//! header with both SAPs set to `STP_LLC_SAP`; see `ethernet::parse_llc_snap`.
+
+use nom::{IResult, be_u8, be_u16, be_u32};
+
+use ethernet::{mac_address, MacAddress};
+
+/// The Bridge Group Address BPDUs are sent to
+pub const STP_MULTICAST: MacAddress = MacAddress([0x01, 0x80, 0xc2, 0x00, 0x00, 0x00]);
+/// The LLC DSAP and SSAP of spanning tree frames
+pub const STP_LLC_SAP: u8 = 0x42;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BridgeId {
+    /// The 4-bit priority and 12-bit system ID extension
+    pub priority: u16,
+    pub mac: MacAddress,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BpduType {
+    Configuration,
+    TopologyChangeNotification,
+    /// A Rapid Spanning Tree (or MSTP) BPDU
+    Rst,
+    Other(u8),
+}
+/// The body of a Configuration or RST BPDU. Timers are in units of 1/256
+/// of a second.
+#[derive(Debug, PartialEq, Eq)]
+pub struct BpduConfig {
+    pub flags: u8,
+    pub root_id: BridgeId,
+    pub root_path_cost: u32,
+    pub bridge_id: BridgeId,
+    pub port_id: u16,
+    pub message_age: u16,
+    pub max_age: u16,
+    pub hello_time: u16,
+    pub forward_delay: u16,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct Bpdu {
+    pub protocol_id: u16,
+    /// 0 for STP, 2 for RSTP and 3 for MSTP
+    pub version: u8,
+    pub bpdu_type: BpduType,
+    /// Absent for Topology Change Notification BPDUs, which end after the type
+    pub config: Option<BpduConfig>,
+}
+
+impl From<u8> for BpduType {
+    fn from(raw: u8) -> Self {
+        match raw {
+            0x00 => BpduType::Configuration,
+            0x80 => BpduType::TopologyChangeNotification,
+            0x02 => BpduType::Rst,
+            other => BpduType::Other(other),
+        }
+    }
+}
+
+impl BpduConfig {
+    pub fn topology_change(&self) -> bool {
+        self.flags & 0x01 == 0x01
+    }
+
+    pub fn topology_change_ack(&self) -> bool {
+        self.flags & 0x80 == 0x80
+    }
+
+    /// The RSTP port role: 1 alternate/backup, 2 root, 3 designated
+    pub fn port_role(&self) -> u8 {
+        (self.flags >> 2) & 0x03
+    }
+
+    pub fn learning(&self) -> bool {
+        self.flags & 0x10 == 0x10
+    }
+
+    pub fn forwarding(&self) -> bool {
+        self.flags & 0x20 == 0x20
+    }
+}
+
+named!(bridge_id<&[u8], BridgeId>, chain!(
+    priority: be_u16 ~
+    mac: mac_address,
+    || BridgeId { priority, mac }
+));
+
+named!(bpdu_config<&[u8], BpduConfig>, chain!(
+    flags: be_u8 ~
+    root_id: bridge_id ~
+    root_path_cost: be_u32 ~
+    bridge_id: bridge_id ~
+    port_id: be_u16 ~
+    message_age: be_u16 ~
+    max_age: be_u16 ~
+    hello_time: be_u16 ~
+    forward_delay: be_u16,
+    || BpduConfig {
+        flags,
+        root_id,
+        root_path_cost,
+        bridge_id,
+        port_id,
+        message_age,
+        max_age,
+        hello_time,
+        forward_delay,
+    }
+));
+
+named!(bpdu<&[u8], Bpdu>, chain!(
+    protocol_id: be_u16 ~
+    version: be_u8 ~
+    bpdu_type: map!(be_u8, BpduType::from) ~
+    config: cond!(bpdu_type != BpduType::TopologyChangeNotification, bpdu_config),
+    || Bpdu { protocol_id, version, bpdu_type, config }
+));
+
+/// Parses a BPDU following its LLC header. The Version 1 Length byte of an
+/// RST BPDU and any MSTP data are left in the remaining input.
+pub fn parse_bpdu(i: &[u8]) -> IResult<&[u8], Bpdu> {
+    bpdu(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethernet::{parse_ethernet_frame_8023, Ethernet8023Frame};
+    use nom::IResult;
+
+    #[test]
+    fn configuration_bpdu_works() {
+        let bytes = [0x01, 0x80, 0xc2, 0x00, 0x00, 0x00, /* Destination */
+                     0x00, 0x1c, 0x0e, 0x87, 0x85, 0x04, /* Source */
+                     0x00, 0x26, /* Length */
+                     0x42, 0x42, 0x03, /* LLC */
+                     0x00, 0x00, 0x00, 0x00, /* STP configuration BPDU */
+                     0x01, /* Topology change */
+                     0x80, 0x64, 0x00, 0x1c, 0x0e, 0x87, 0x78, 0x00, /* Root */
+                     0x00, 0x00, 0x00, 0x04, /* Root path cost */
+                     0x80, 0x64, 0x00, 0x1c, 0x0e, 0x87, 0x85, 0x00, /* Bridge */
+                     0x80, 0x04, /* Port */
+                     0x01, 0x00, 0x14, 0x00, 0x02, 0x00, 0x0f, 0x00, /* 1s, 20s, 2s, 15s */
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00 /* Padding */];
+        let frame = match parse_ethernet_frame_8023(&bytes) {
+            IResult::Done(rest, Ethernet8023Frame::Ieee8023(frame)) => {
+                assert_eq!(frame.dest_mac, STP_MULTICAST);
+                assert_eq!(frame.llc.dsap, STP_LLC_SAP);
+                rest
+            }
+            _ => panic!(),
+        };
+        let expectation = Bpdu {
+            protocol_id: 0,
+            version: 0,
+            bpdu_type: BpduType::Configuration,
+            config: Some(BpduConfig {
+                flags: 0x01,
+                root_id: BridgeId {
+                    priority: 0x8064,
+                    mac: MacAddress([0x00, 0x1c, 0x0e, 0x87, 0x78, 0x00]),
+                },
+                root_path_cost: 4,
+                bridge_id: BridgeId {
+                    priority: 0x8064,
+                    mac: MacAddress([0x00, 0x1c, 0x0e, 0x87, 0x85, 0x00]),
+                },
+                port_id: 0x8004,
+                message_age: 256,
+                max_age: 20 * 256,
+                hello_time: 2 * 256,
+                forward_delay: 15 * 256,
+            }),
+        };
+        if let IResult::Done(rest, bpdu) = parse_bpdu(frame) {
+            assert_eq!(rest.len(), 8);
+            assert!(bpdu.config.as_ref().unwrap().topology_change());
+            assert!(!bpdu.config.as_ref().unwrap().topology_change_ack());
+            assert_eq!(bpdu, expectation);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn topology_change_notification_has_no_config() {
+        let bytes = [0x00, 0x00, 0x00, 0x80];
+        let expectation = Bpdu {
+            protocol_id: 0,
+            version: 0,
+            bpdu_type: BpduType::TopologyChangeNotification,
+            config: None,
+        };
+        assert_eq!(parse_bpdu(&bytes), IResult::Done(&[][..], expectation));
+    }
+}
@@ -0,0 +1,192 @@
+//! Handles reassembly of TCP byte streams
+//!
+//! A `TcpStreamReassembler` follows one direction of one connection; feed it
+//! the segments of a single flow, for example as keyed by `flow::FlowKey`.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use tcp::TcpHeader;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StreamError {
+    /// Buffering an out-of-order segment would exceed the configured limit
+    BufferFull,
+}
+
+/// Orders segments by sequence number and returns the application bytes as
+/// they become contiguous. Sequence numbers are tracked relative to the SYN,
+/// or to the first segment seen when the handshake was missed, as a 64-bit
+/// stream offset, so wraparound at the 32-bit boundary is handled.
+/// Retransmitted bytes are dropped; where segments overlap, the bytes
+/// received first win.
+#[derive(Debug)]
+pub struct TcpStreamReassembler {
+    max_buffered: usize,
+    /// The sequence number of the next byte to deliver, once known
+    next_seq: Option<u32>,
+    /// Stream offset of the next byte to deliver
+    delivered: u64,
+    /// Out-of-order segments keyed by stream offset
+    pending: BTreeMap<u64, Vec<u8>>,
+    buffered: usize,
+    /// Stream offset of the FIN, once seen
+    fin: Option<u64>,
+}
+
+impl TcpStreamReassembler {
+    /// Creates a reassembler holding at most `max_buffered` bytes of
+    /// out-of-order data
+    pub fn new(max_buffered: usize) -> TcpStreamReassembler {
+        TcpStreamReassembler {
+            max_buffered,
+            next_seq: None,
+            delivered: 0,
+            pending: BTreeMap::new(),
+            buffered: 0,
+            fin: None,
+        }
+    }
+
+    /// Adds one segment and its payload, returning the bytes that became
+    /// contiguous with the data already returned, which may be none
+    pub fn add_segment(&mut self, header: &TcpHeader, payload: &[u8]) -> Result<Vec<u8>, StreamError> {
+        // The SYN occupies one sequence number before the first data byte
+        let data_seq = header.sequence_no.wrapping_add(header.flag_syn as u32);
+        let next_seq = match self.next_seq {
+            Some(next_seq) => next_seq,
+            None if header.flag_syn || !payload.is_empty() => {
+                self.next_seq = Some(data_seq);
+                data_seq
+            }
+            None => return Ok(Vec::new()),
+        };
+
+        // Offsets more than 2^31 behind are taken as old data, as in TCP
+        // sequence number comparisons
+        let relative = data_seq.wrapping_sub(next_seq) as i32 as i64;
+        let offset = self.delivered as i64 + relative;
+        let end = offset + payload.len() as i64;
+        if header.flag_fin && end >= 0 {
+            self.fin = Some(end as u64);
+        }
+        if end <= self.delivered as i64 {
+            return Ok(Vec::new());
+        }
+
+        // Trim any already delivered prefix of a partial retransmission
+        let skip = (self.delivered as i64 - offset).max(0) as usize;
+        let offset = offset as u64 + skip as u64;
+        let data = &payload[skip..];
+        if offset > self.delivered {
+            return self.buffer(offset, data).map(|_| Vec::new());
+        }
+
+        let mut ready = data.to_vec();
+        self.delivered += data.len() as u64;
+        self.drain_pending(&mut ready);
+        self.next_seq = Some(next_seq.wrapping_add(ready.len() as u32));
+        Ok(ready)
+    }
+
+    fn buffer(&mut self, offset: u64, data: &[u8]) -> Result<(), StreamError> {
+        let existing = self.pending.get(&offset).map_or(0, Vec::len);
+        if data.len() <= existing {
+            return Ok(());
+        }
+        if self.buffered - existing + data.len() > self.max_buffered {
+            return Err(StreamError::BufferFull);
+        }
+        self.buffered = self.buffered - existing + data.len();
+        self.pending.insert(offset, data.to_vec());
+        Ok(())
+    }
+
+    fn drain_pending(&mut self, ready: &mut Vec<u8>) {
+        while let Some((&offset, _)) = self.pending.iter().next() {
+            if offset > self.delivered {
+                break;
+            }
+            let data = self.pending.remove(&offset).unwrap_or_default();
+            self.buffered -= data.len();
+            let end = offset + data.len() as u64;
+            if end > self.delivered {
+                ready.extend_from_slice(&data[(self.delivered - offset) as usize..]);
+                self.delivered = end;
+            }
+        }
+    }
+
+    /// Number of stream bytes returned so far
+    pub fn delivered(&self) -> u64 {
+        self.delivered
+    }
+
+    /// Number of out-of-order bytes waiting for a gap to be filled
+    pub fn buffered(&self) -> usize {
+        self.buffered
+    }
+
+    /// Whether a FIN was seen and every byte before it has been returned
+    pub fn is_finished(&self) -> bool {
+        self.fin.is_some_and(|fin| fin <= self.delivered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StreamError, TcpStreamReassembler};
+    use tcp::TcpHeader;
+
+    fn segment(sequence_no: u32, syn: bool, fin: bool) -> TcpHeader {
+        TcpHeader {
+            sequence_no,
+            flag_syn: syn,
+            flag_fin: fin,
+            data_offset: 20,
+            ..TcpHeader::default()
+        }
+    }
+
+    #[test]
+    fn out_of_order_segments_are_ordered() {
+        let mut stream = TcpStreamReassembler::new(1024);
+        assert_eq!(stream.add_segment(&segment(1000, true, false), &[]), Ok(vec![]));
+        assert_eq!(stream.add_segment(&segment(1011, false, true), b"world"), Ok(vec![]));
+        assert_eq!(stream.add_segment(&segment(1007, false, false), b"big "), Ok(vec![]));
+        assert_eq!(stream.buffered(), 9);
+        assert_eq!(stream.add_segment(&segment(1001, false, false), b"hello"),
+                   Ok(b"hello".to_vec()));
+        assert!(!stream.is_finished());
+        assert_eq!(stream.add_segment(&segment(1006, false, false), b" "),
+                   Ok(b" big world".to_vec()));
+        assert_eq!(stream.buffered(), 0);
+        assert_eq!(stream.delivered(), 15);
+        assert!(stream.is_finished());
+    }
+
+    #[test]
+    fn retransmissions_are_dropped() {
+        let mut stream = TcpStreamReassembler::new(1024);
+        assert_eq!(stream.add_segment(&segment(1, false, false), b"abcd"), Ok(b"abcd".to_vec()));
+        assert_eq!(stream.add_segment(&segment(1, false, false), b"abcd"), Ok(vec![]));
+        assert_eq!(stream.add_segment(&segment(3, false, false), b"cdef"), Ok(b"ef".to_vec()));
+    }
+
+    #[test]
+    fn sequence_numbers_wrap_around() {
+        let mut stream = TcpStreamReassembler::new(1024);
+        stream.add_segment(&segment(0xffff_fffd, true, false), &[]).unwrap();
+        assert_eq!(stream.add_segment(&segment(0x0000_0001, false, false), b"cd"), Ok(vec![]));
+        assert_eq!(stream.add_segment(&segment(0xffff_fffe, false, false), b"ab!"),
+                   Ok(b"ab!cd".to_vec()));
+    }
+
+    #[test]
+    fn buffer_limit_is_enforced() {
+        let mut stream = TcpStreamReassembler::new(4);
+        stream.add_segment(&segment(0, true, false), &[]).unwrap();
+        assert_eq!(stream.add_segment(&segment(10, false, false), b"abcde"),
+                   Err(StreamError::BufferFull));
+    }
+}
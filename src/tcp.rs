@@ -1,6 +1,15 @@
 //! Handles parsing of TCP headers
 
-use nom::IResult;
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use nom::{IResult, Err, ErrorKind, Needed, be_u8, be_u16, be_u32};
+
+use checksum::ChecksumBuilder;
+use error::PktError;
+use options::for_each_option;
 
 // TCP Header Format
 //
@@ -14,9 +23,9 @@ use nom::IResult;
 //   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 //   |                    Acknowledgment Number                      |
 //   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
-//   |  Data |           |U|A|P|R|S|F|                               |
-//   | Offset| Reserved  |R|C|S|S|Y|I|            Window             |
-//   |       |           |G|K|H|T|N|N|                               |
+//   |  Data |     |N|C|E|U|A|P|R|S|F|                               |
+//   | Offset| Res.|S|W|C|R|C|S|S|Y|I|            Window             |
+//   |       |     | |R|E|G|K|H|T|N|N|                               |
 //   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 //   |           Checksum            |         Urgent Pointer        |
 //   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
@@ -26,6 +35,9 @@ use nom::IResult;
 //   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 
 // TCP Flags:
+//    NS:   ECN-nonce concealment protection (RFC 3540)
+//    CWR:  Congestion Window Reduced (RFC 3168)
+//    ECE:  ECN-Echo (RFC 3168)
 //    URG:  Urgent Pointer field significant
 //    ACK:  Acknowledgment field significant
 //    PSH:  Push Function
@@ -34,14 +46,99 @@ use nom::IResult;
 //    FIN:  No more data from sender
 
 
-#[derive(Debug, PartialEq, Eq, Default)]
-pub struct TcpHeader<'a> {
+#[cfg(feature = "alloc")]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum TcpOption {
+    EndOfOptions,
+    NoOperation,
+    MaximumSegmentSize(u16),
+    WindowScale(u8),
+    SackPermitted,
+    /// Selective acknowledgment blocks as (left edge, right edge) pairs
+    Sack {
+        blocks: Vec<(u32, u32)>,
+    },
+    Timestamp {
+        tsval: u32,
+        tsecr: u32,
+    },
+    /// Multipath TCP (RFC 8684)
+    Mptcp(MptcpSubtype),
+    /// TCP Fast Open cookie (RFC 7413), empty in a cookie request
+    FastOpenCookie(Vec<u8>),
+    /// An option kind this crate does not decode, with its data bytes
+    Unknown {
+        kind: u8,
+        data: Vec<u8>,
+    },
+}
+
+/// A TCP option borrowing its variable-length data from the header rather
+/// than copying it, as yielded by `TcpOptionIter`
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum TcpOptionRef<'a> {
+    EndOfOptions,
+    NoOperation,
+    MaximumSegmentSize(u16),
+    WindowScale(u8),
+    SackPermitted,
+    /// Selective acknowledgment blocks, 8 bytes each; see `sack_blocks`
+    Sack(&'a [u8]),
+    Timestamp {
+        tsval: u32,
+        tsecr: u32,
+    },
+    /// Multipath TCP option data, starting with the subtype byte
+    Mptcp(&'a [u8]),
+    /// TCP Fast Open cookie, empty in a cookie request
+    FastOpenCookie(&'a [u8]),
+    /// An option kind this crate does not decode, with its data bytes
+    Unknown {
+        kind: u8,
+        data: &'a [u8],
+    },
+}
+
+/// The body of an MPTCP option, selected by the subtype in its first nibble
+#[cfg(feature = "alloc")]
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum MptcpSubtype {
+    /// MP_CAPABLE, which carries no key on a version 1 SYN, the sender's
+    /// key on a SYN/ACK or a version 0 SYN, and both keys on the third ACK
+    MpCapable {
+        version: u8,
+        flags: u8,
+        sender_key: Option<u64>,
+        receiver_key: Option<u64>,
+    },
+    /// MP_JOIN as sent on a SYN
+    MpJoinSyn {
+        backup: bool,
+        address_id: u8,
+        /// The receiver's token, identifying the connection being joined
+        token: u32,
+        /// The sender's random nonce
+        nonce: u32,
+    },
+    /// Any other subtype or layout, with the option data including the
+    /// subtype byte
+    Other {
+        subtype: u8,
+        data: Vec<u8>,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Default)]
+pub struct TcpHeader {
     pub source_port: u16,
     pub dest_port: u16,
     pub sequence_no: u32,
     pub ack_no: u32,
     pub data_offset: u8,
     pub reserved: u8,
+    pub flag_ns: bool,
+    pub flag_cwr: bool,
+    pub flag_ece: bool,
     pub flag_urg: bool,
     pub flag_ack: bool,
     pub flag_psh: bool,
@@ -51,16 +148,19 @@ pub struct TcpHeader<'a> {
     pub window: u16,
     pub checksum: u16,
     pub urgent_pointer: u16,
-    pub options: Option<&'a[u8]>,
+    /// Only decoded with the `alloc` feature; `options_iter` walks the
+    /// options either way
+    #[cfg(feature = "alloc")]
+    pub options: Option<Vec<TcpOption>>,
 }
-named!(dataof_res_flags<&[u8], (u8, u8, u8)>,
+named!(dataof_res_flags<&[u8], (u8, u8, u16)>,
     bits!(tuple!(
         take_bits!(u8, 4),
-        take_bits!(u8, 6),
-        take_bits!(u8, 6))));
+        take_bits!(u8, 3),
+        take_bits!(u16, 9))));
 
 named!(tcp_parse<&[u8], TcpHeader>,
-       dbg_dmp!(chain!(src: u16!(true) ~
+       chain!(src: u16!(true) ~
               dst: u16!(true) ~
               seq: u32!(true) ~
               ack: u32!(true) ~
@@ -76,34 +176,507 @@ named!(tcp_parse<&[u8], TcpHeader>,
                   ack_no : ack,
                   data_offset : dataof_res_flags.0 * 4,
                   reserved : dataof_res_flags.1,
-                  flag_urg : dataof_res_flags.2 & 0b100000 == 0b100000,
-                  flag_ack : dataof_res_flags.2 & 0b010000 == 0b010000,
-                  flag_psh : dataof_res_flags.2 & 0b001000 == 0b001000,
-                  flag_rst : dataof_res_flags.2 & 0b000100 == 0b000100,
-                  flag_syn : dataof_res_flags.2 & 0b000010 == 0b000010,
-                  flag_fin : dataof_res_flags.2 & 0b000001 == 0b000001,
-                  window : window,
-                  checksum : checksum,
+                  flag_ns : dataof_res_flags.2 & 0b100000000 == 0b100000000,
+                  flag_cwr : dataof_res_flags.2 & 0b010000000 == 0b010000000,
+                  flag_ece : dataof_res_flags.2 & 0b001000000 == 0b001000000,
+                  flag_urg : dataof_res_flags.2 & 0b000100000 == 0b000100000,
+                  flag_ack : dataof_res_flags.2 & 0b000010000 == 0b000010000,
+                  flag_psh : dataof_res_flags.2 & 0b000001000 == 0b000001000,
+                  flag_rst : dataof_res_flags.2 & 0b000000100 == 0b000000100,
+                  flag_syn : dataof_res_flags.2 & 0b000000010 == 0b000000010,
+                  flag_fin : dataof_res_flags.2 & 0b000000001 == 0b000000001,
+                  window,
+                  checksum,
                   urgent_pointer : urgent_ptr,
+                  #[cfg(feature = "alloc")]
                   options : None
-              }})));
+              }}));
 
-pub fn parse_tcp_header(i: &[u8]) -> IResult<&[u8], TcpHeader> {
+// The option length covers the kind and length bytes themselves
+named!(option_length<&[u8], u8>, map_opt!(be_u8, |l: u8| l.checked_sub(2)));
+
+// `u8::is_multiple_of` needs Rust 1.87, newer than this crate supports
+#[allow(clippy::manual_is_multiple_of)]
+fn sack_length(len: u8) -> Option<u8> {
+    if len > 0 && len % 8 == 0 { Some(len) } else { None }
+}
+
+named!(sack_blocks<&[u8], &[u8]>, chain!(
+    len: map_opt!(option_length, sack_length) ~
+    blocks: take!(len),
+    || blocks
+));
+
+#[cfg(feature = "alloc")]
+fn read_u64(i: &[u8]) -> u64 {
+    u64::from_be_bytes(*array_ref![i, 0, 8])
+}
+
+fn read_u32(i: &[u8]) -> u32 {
+    u32::from_be_bytes(*array_ref![i, 0, 4])
+}
+
+// The option data must hold at least the subtype byte
+#[cfg(feature = "alloc")]
+fn to_mptcp_subtype(data: &[u8]) -> MptcpSubtype {
+    let subtype = data[0] >> 4;
+    match (subtype, data.len()) {
+        (0, 2) | (0, 10) | (0, 18) => MptcpSubtype::MpCapable {
+            version: data[0] & 0x0f,
+            flags: data[1],
+            sender_key: data.get(2..10).map(read_u64),
+            receiver_key: data.get(10..18).map(read_u64),
+        },
+        (1, 10) => MptcpSubtype::MpJoinSyn {
+            backup: data[0] & 0x01 == 0x01,
+            address_id: data[1],
+            token: read_u32(&data[2..]),
+            nonce: read_u32(&data[6..]),
+        },
+        _ => MptcpSubtype::Other { subtype, data: data.to_vec() },
+    }
+}
+
+// Dispatches on the kind by hand, since the error path of `switch!` boxes
+// and so would need `alloc`
+fn tcp_parse_option_ref(i: &[u8]) -> IResult<&[u8], TcpOptionRef<'_>> {
+    let (left, kind) = try_parse!(i, be_u8);
+    match kind {
+        0 => IResult::Done(left, TcpOptionRef::EndOfOptions),
+        1 => IResult::Done(left, TcpOptionRef::NoOperation),
+        2 => chain!(left, tag!([4]) ~ mss: be_u16, || TcpOptionRef::MaximumSegmentSize(mss)),
+        3 => chain!(left, tag!([3]) ~ scaling: be_u8, || TcpOptionRef::WindowScale(scaling)),
+        4 => value!(left, TcpOptionRef::SackPermitted, tag!([2])),
+        5 => map!(left, sack_blocks, TcpOptionRef::Sack),
+        8 => chain!(left,
+            tag!([10]) ~
+            tsval: be_u32 ~
+            tsecr: be_u32,
+            || TcpOptionRef::Timestamp { tsval, tsecr }),
+        30 => chain!(left,
+            len: map_opt!(option_length, |l: u8| if l > 0 { Some(l) } else { None }) ~
+            data: take!(len),
+            || TcpOptionRef::Mptcp(data)),
+        34 => chain!(left,
+            len: map_opt!(option_length, |l: u8| if l == 0 || (4..=16).contains(&l) { Some(l) } else { None }) ~
+            cookie: take!(len),
+            || TcpOptionRef::FastOpenCookie(cookie)),
+        kind => chain!(left,
+            len: option_length ~
+            data: take!(len),
+            || TcpOptionRef::Unknown { kind, data }),
+    }
+}
+
+#[cfg(feature = "alloc")]
+named!(tcp_parse_option<&[u8], TcpOption>, map!(tcp_parse_option_ref, TcpOption::from));
+
+impl<'a> TcpOptionRef<'a> {
+    /// The (left edge, right edge) pairs of a SACK option, decoded without
+    /// allocating; empty for any other option. Bytes short of a whole block
+    /// are ignored.
+    pub fn sack_blocks(&self) -> impl Iterator<Item = (u32, u32)> + 'a {
+        let blocks: &'a [u8] = match *self {
+            TcpOptionRef::Sack(blocks) => blocks,
+            _ => &[],
+        };
+        blocks.chunks_exact(8).map(|block| (read_u32(block), read_u32(&block[4..])))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> From<TcpOptionRef<'a>> for TcpOption {
+    /// Copies the option's data, decoding SACK blocks and MPTCP subtypes
+    fn from(option: TcpOptionRef<'a>) -> TcpOption {
+        match option {
+            TcpOptionRef::EndOfOptions => TcpOption::EndOfOptions,
+            TcpOptionRef::NoOperation => TcpOption::NoOperation,
+            TcpOptionRef::MaximumSegmentSize(mss) => TcpOption::MaximumSegmentSize(mss),
+            TcpOptionRef::WindowScale(scaling) => TcpOption::WindowScale(scaling),
+            TcpOptionRef::SackPermitted => TcpOption::SackPermitted,
+            TcpOptionRef::Sack(_) => TcpOption::Sack { blocks: option.sack_blocks().collect() },
+            TcpOptionRef::Timestamp { tsval, tsecr } => TcpOption::Timestamp { tsval, tsecr },
+            TcpOptionRef::Mptcp(data) => TcpOption::Mptcp(to_mptcp_subtype(data)),
+            TcpOptionRef::FastOpenCookie(cookie) => TcpOption::FastOpenCookie(cookie.to_vec()),
+            TcpOptionRef::Unknown { kind, data } => TcpOption::Unknown { kind, data: data.to_vec() },
+        }
+    }
+}
+
+/// Parses the options area of a TCP header. The input must be exactly the
+/// option bytes covered by the data offset; the list ends either at an End
+/// of Options option or at the end of the input, since many stacks omit it.
+#[cfg(feature = "alloc")]
+fn tcp_parse_options(i: &[u8]) -> IResult<&[u8], Vec<TcpOption>> {
+    let mut options = Vec::new();
+    for_each_option(i, tcp_parse_option, |option, rest| {
+        // Everything after the end of the option list is padding
+        let next = if option == TcpOption::EndOfOptions { &rest[rest.len()..] } else { rest };
+        options.push(option);
+        Ok(next)
+    }).map(|()| options)
+}
+
+/// Like `tcp_parse_options`, but writes borrowed options into `buf` and
+/// returns how many were written. Running out of room is an
+/// `ErrorKind::Count` error.
+fn tcp_parse_options_into<'a>(i: &'a [u8], buf: &mut [TcpOptionRef<'a>]) -> IResult<&'a [u8], usize> {
+    let mut count = 0;
+    for_each_option(i, tcp_parse_option_ref, |option, rest| {
+        if count == buf.len() {
+            return Err(ErrorKind::Count);
+        }
+        // Everything after the end of the option list is padding
+        let next = if option == TcpOptionRef::EndOfOptions { &rest[rest.len()..] } else { rest };
+        buf[count] = option;
+        count += 1;
+        Ok(next)
+    }).map(|()| count)
+}
+
+#[cfg(feature = "alloc")]
+impl TcpOption {
+    /// Number of bytes this option occupies on the wire
+    fn wire_len(&self) -> usize {
+        match *self {
+            TcpOption::EndOfOptions | TcpOption::NoOperation => 1,
+            TcpOption::MaximumSegmentSize(_) => 4,
+            TcpOption::WindowScale(_) => 3,
+            TcpOption::SackPermitted => 2,
+            TcpOption::Sack { ref blocks } => 2 + 8 * blocks.len(),
+            TcpOption::Timestamp { .. } => 10,
+            TcpOption::Mptcp(ref subtype) => 2 + subtype.wire_len(),
+            TcpOption::FastOpenCookie(ref cookie) => 2 + cookie.len(),
+            TcpOption::Unknown { ref data, .. } => 2 + data.len(),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl MptcpSubtype {
+    /// Number of option data bytes, the subtype byte included
+    fn wire_len(&self) -> usize {
+        match *self {
+            MptcpSubtype::MpCapable { sender_key, receiver_key, .. } => {
+                2 + 8 * (sender_key.is_some() as usize + receiver_key.is_some() as usize)
+            }
+            MptcpSubtype::MpJoinSyn { .. } => 10,
+            MptcpSubtype::Other { ref data, .. } => data.len(),
+        }
+    }
+}
+
+/// Builds a `TcpHeader` for packet generation. `build` derives the data
+/// offset from the options and leaves the checksum at 0.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Default)]
+pub struct TcpHeaderBuilder {
+    header: TcpHeader,
+    options: Vec<TcpOption>,
+}
+
+#[cfg(feature = "alloc")]
+impl TcpHeaderBuilder {
+    pub fn new() -> TcpHeaderBuilder {
+        TcpHeaderBuilder::default()
+    }
+
+    pub fn source_port(mut self, port: u16) -> TcpHeaderBuilder {
+        self.header.source_port = port;
+        self
+    }
+
+    pub fn dest_port(mut self, port: u16) -> TcpHeaderBuilder {
+        self.header.dest_port = port;
+        self
+    }
+
+    pub fn seq(mut self, sequence_no: u32) -> TcpHeaderBuilder {
+        self.header.sequence_no = sequence_no;
+        self
+    }
+
+    /// Sets the acknowledgment number and the ACK flag
+    pub fn ack(mut self, ack_no: u32) -> TcpHeaderBuilder {
+        self.header.ack_no = ack_no;
+        self.header.flag_ack = true;
+        self
+    }
+
+    pub fn ns(mut self, set: bool) -> TcpHeaderBuilder {
+        self.header.flag_ns = set;
+        self
+    }
+
+    pub fn cwr(mut self, set: bool) -> TcpHeaderBuilder {
+        self.header.flag_cwr = set;
+        self
+    }
+
+    pub fn ece(mut self, set: bool) -> TcpHeaderBuilder {
+        self.header.flag_ece = set;
+        self
+    }
+
+    pub fn urg(mut self, urgent_pointer: u16) -> TcpHeaderBuilder {
+        self.header.flag_urg = true;
+        self.header.urgent_pointer = urgent_pointer;
+        self
+    }
+
+    pub fn psh(mut self, set: bool) -> TcpHeaderBuilder {
+        self.header.flag_psh = set;
+        self
+    }
+
+    pub fn rst(mut self, set: bool) -> TcpHeaderBuilder {
+        self.header.flag_rst = set;
+        self
+    }
+
+    pub fn syn(mut self, set: bool) -> TcpHeaderBuilder {
+        self.header.flag_syn = set;
+        self
+    }
+
+    pub fn fin(mut self, set: bool) -> TcpHeaderBuilder {
+        self.header.flag_fin = set;
+        self
+    }
+
+    pub fn window(mut self, window: u16) -> TcpHeaderBuilder {
+        self.header.window = window;
+        self
+    }
+
+    pub fn mss(self, mss: u16) -> TcpHeaderBuilder {
+        self.option(TcpOption::MaximumSegmentSize(mss))
+    }
+
+    pub fn option(mut self, option: TcpOption) -> TcpHeaderBuilder {
+        self.options.push(option);
+        self
+    }
+
+    pub fn build(self) -> TcpHeader {
+        let mut header = self.header;
+        let options_length: usize = self.options.iter().map(TcpOption::wire_len).sum();
+        // Options are padded to a whole number of 32-bit words
+        header.data_offset = (20 + options_length.div_ceil(4) * 4) as u8;
+        if !self.options.is_empty() {
+            header.options = Some(self.options);
+        }
+        header
+    }
+}
+
+/// Iterator over the options of a TCP header, parsed on demand from the
+/// raw option bytes. Options borrow their data from those bytes, so
+/// iterating never allocates; `TcpOption::from` makes an owned copy.
+/// Iteration stops after End of Options or at the first malformed option.
+pub struct TcpOptionIter<'a> {
+    remaining: &'a [u8],
+}
+
+impl<'a> Iterator for TcpOptionIter<'a> {
+    type Item = TcpOptionRef<'a>;
+
+    fn next(&mut self) -> Option<TcpOptionRef<'a>> {
+        if self.remaining.is_empty() {
+            return None;
+        }
+        match tcp_parse_option_ref(self.remaining) {
+            IResult::Done(_, TcpOptionRef::EndOfOptions) => {
+                self.remaining = &[];
+                Some(TcpOptionRef::EndOfOptions)
+            }
+            IResult::Done(rest, option) => {
+                self.remaining = rest;
+                Some(option)
+            }
+            _ => {
+                self.remaining = &[];
+                None
+            }
+        }
+    }
+}
+
+impl TcpHeader {
+    /// The header length in bytes, options included. `data_offset` already
+    /// holds the on-wire offset scaled to bytes.
+    pub fn header_len_bytes(&self) -> usize {
+        self.data_offset as usize
+    }
+
+    /// Whether the data offset covers at least the 20-byte fixed header and
+    /// is a whole number of 32-bit words no larger than 60 bytes
+    // `u8::is_multiple_of` needs Rust 1.87, newer than this crate supports
+    #[allow(clippy::manual_is_multiple_of)]
+    pub fn is_valid(&self) -> bool {
+        self.data_offset >= 20 && self.data_offset <= 60 && self.data_offset % 4 == 0
+    }
+
+    /// The 13th header byte as on the wire: the data offset in 32-bit words,
+    /// the reserved bits and the NS flag
+    pub fn offset_reserved_byte(&self) -> u8 {
+        (self.data_offset / 4) << 4 | (self.reserved & 0b111) << 1 | self.flag_ns as u8
+    }
+
+    /// Checks the checksum over `pseudo_header`, from `IPv4Header` or
+    /// `IPv6Header::pseudo_header`, and `segment`, which starts with this
+    /// header and runs to the end of the IP payload
+    pub fn verify_checksum(&self, pseudo_header: &[u8], segment: &[u8]) -> bool {
+        ChecksumBuilder::new().write(pseudo_header).write(segment).sum() == 0xffff
+    }
+
+    /// Lazily parses the options from `raw_options`, as returned by
+    /// `parse_tcp_header_raw`. Bytes beyond the data offset are ignored.
+    pub fn options_iter<'a>(&self, raw_options: &'a [u8]) -> TcpOptionIter<'a> {
+        let options_length = (self.data_offset as usize).saturating_sub(20);
+        TcpOptionIter { remaining: &raw_options[..options_length.min(raw_options.len())] }
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for TcpHeader {
+    /// Generates an option-less header with the reserved bits clear
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<TcpHeader> {
+        Ok(TcpHeader {
+            source_port: u.arbitrary()?,
+            dest_port: u.arbitrary()?,
+            sequence_no: u.arbitrary()?,
+            ack_no: u.arbitrary()?,
+            data_offset: 20,
+            reserved: 0,
+            flag_ns: u.arbitrary()?,
+            flag_cwr: u.arbitrary()?,
+            flag_ece: u.arbitrary()?,
+            flag_urg: u.arbitrary()?,
+            flag_ack: u.arbitrary()?,
+            flag_psh: u.arbitrary()?,
+            flag_rst: u.arbitrary()?,
+            flag_syn: u.arbitrary()?,
+            flag_fin: u.arbitrary()?,
+            window: u.arbitrary()?,
+            checksum: u.arbitrary()?,
+            urgent_pointer: u.arbitrary()?,
+            #[cfg(feature = "alloc")]
+            options: None,
+        })
+    }
+}
+
+impl fmt::Display for TcpHeader {
+    /// A one-line summary, e.g. `TCP 49695 -> 80 [PSH,ACK] seq=265846604 ack=3945727432 win=256`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flags = [(self.flag_syn, "SYN"), (self.flag_fin, "FIN"), (self.flag_rst, "RST"),
+                     (self.flag_psh, "PSH"), (self.flag_ack, "ACK"), (self.flag_urg, "URG"),
+                     (self.flag_ece, "ECE"), (self.flag_cwr, "CWR"), (self.flag_ns, "NS")];
+        write!(f, "TCP {} -> {} [", self.source_port, self.dest_port)?;
+        let mut first = true;
+        for &(_, name) in flags.iter().filter(|&&(set, _)| set) {
+            write!(f, "{}{}", if first { "" } else { "," }, name)?;
+            first = false;
+        }
+        write!(f, "] seq={}", self.sequence_no)?;
+        if self.flag_ack {
+            write!(f, " ack={}", self.ack_no)?;
+        }
+        write!(f, " win={}", self.window)
+    }
+}
+
+/// Parses a TCP header without decoding its options, returning the raw
+/// option bytes alongside it. `options` is always `None`; use
+/// `TcpHeader::options_iter` to walk the options without building a `Vec`.
+pub fn parse_tcp_header_raw(i: &[u8]) -> IResult<&[u8], (TcpHeader, &[u8])> {
     match tcp_parse(i) {
-        IResult::Done(left, mut tcp_header) => {
-            let options_length = (tcp_header.data_offset - 20) as usize;
-            // TODO: For now TcpHeader options are not parsed and contains the raw bytes.
-            if options_length > 0 {
-                tcp_header.options = Some(&left[..options_length]);
-                IResult::Done(&left[options_length..], tcp_header)
-            } else {
-                IResult::Done(left, tcp_header)
+        IResult::Done(left, tcp_header) => {
+            // The data offset bounds the options area to at most 40 bytes
+            let options_length = match tcp_header.data_offset.checked_sub(20) {
+                Some(len) => len as usize,
+                None => return IResult::Error(Err::Position(ErrorKind::LengthValue, i)),
+            };
+            if left.len() < options_length {
+                return IResult::Incomplete(Needed::Size(tcp_header.data_offset as usize));
+            }
+            IResult::Done(&left[options_length..], (tcp_header, &left[..options_length]))
+        }
+        IResult::Error(e) => IResult::Error(e),
+        IResult::Incomplete(n) => IResult::Incomplete(n),
+    }
+}
+
+/// Parses a TCP header and its options. A malformed option, such as one
+/// whose length runs past the data offset, does not fail the header: the
+/// header is still returned with `options` set to `None`. Only
+/// `parse_tcp_header_strict` reports such options as an error.
+#[cfg(feature = "alloc")]
+pub fn parse_tcp_header(i: &[u8]) -> IResult<&[u8], TcpHeader> {
+    match parse_tcp_header_raw(i) {
+        IResult::Done(left, (mut tcp_header, raw_options)) => {
+            if !raw_options.is_empty() {
+                // Malformed options are dropped rather than failing the header
+                if let IResult::Done(_, options) = tcp_parse_options(raw_options) {
+                    tcp_header.options = Some(options);
+                }
             }
+            IResult::Done(left, tcp_header)
         }
+        IResult::Error(e) => IResult::Error(e),
+        IResult::Incomplete(n) => IResult::Incomplete(n),
+    }
+}
+
+#[cfg(not(feature = "alloc"))]
+pub fn parse_tcp_header(i: &[u8]) -> IResult<&[u8], TcpHeader> {
+    parse_tcp_header_raw(i).map(|(tcp_header, _)| tcp_header)
+}
+
+/// Like `parse_tcp_header`, but a malformed option fails the whole header
+/// rather than being dropped
+#[cfg(feature = "alloc")]
+pub fn parse_tcp_header_strict(i: &[u8]) -> IResult<&[u8], TcpHeader> {
+    let (left, (mut tcp_header, raw_options)) = try_parse!(i, parse_tcp_header_raw);
+    if !raw_options.is_empty() {
+        let (_, options) = try_parse!(raw_options, tcp_parse_options);
+        tcp_header.options = Some(options);
+    }
+    IResult::Done(left, tcp_header)
+}
 
-        e => e,
+/// Parses a TCP header, writing its options into `options_buf` rather than
+/// a `Vec` and returning how many were written; `options`, when built with
+/// `alloc`, is left `None`. The options borrow their data from `i`, so
+/// nothing is allocated and this is available without `alloc`. Options that
+/// do not fit in `options_buf` are an error, while malformed options are
+/// dropped as in `parse_tcp_header`, giving a count of 0.
+pub fn parse_tcp_header_into<'a>(i: &'a [u8], options_buf: &mut [TcpOptionRef<'a>])
+                                 -> IResult<&'a [u8], (TcpHeader, usize)> {
+    match parse_tcp_header_raw(i) {
+        IResult::Done(left, (tcp_header, raw_options)) => {
+            match tcp_parse_options_into(raw_options, options_buf) {
+                IResult::Done(_, count) => IResult::Done(left, (tcp_header, count)),
+                IResult::Error(Err::Position(ErrorKind::Count, _)) => {
+                    IResult::Error(Err::Position(ErrorKind::Count, i))
+                }
+                _ => IResult::Done(left, (tcp_header, 0)),
+            }
+        }
+        IResult::Error(e) => IResult::Error(e),
+        IResult::Incomplete(n) => IResult::Incomplete(n),
     }
+}
 
+/// The application data of `full_segment`, a TCP segment starting at its
+/// header, found by skipping `header.header_len_bytes()`
+pub fn tcp_payload<'a>(header: &TcpHeader, full_segment: &'a [u8]) -> Result<&'a [u8], PktError> {
+    let header_len = header.header_len_bytes();
+    if full_segment.len() < header_len {
+        return Err(PktError::Incomplete(header_len));
+    }
+    Ok(&full_segment[header_len..])
 }
 
 #[cfg(test)]
@@ -112,7 +685,7 @@ mod tests {
     use super::*;
     use nom::IResult;
 
-    const EMPTY_SLICE: &'static [u8] = &[];
+    const EMPTY_SLICE: &[u8] = &[];
 
     #[test]
     fn test_tcp_parse() {
@@ -131,6 +704,9 @@ mod tests {
             ack_no: 0xeb2f05c8,
             data_offset: 20,
             reserved: 0,
+            flag_ns: false,
+            flag_cwr: false,
+            flag_ece: false,
             flag_urg: false,
             flag_ack: true,
             flag_psh: true,
@@ -140,9 +716,491 @@ mod tests {
             window: 256,
             checksum: 0x7c29,
             urgent_pointer: 0,
+            #[cfg(feature = "alloc")]
+            options: None,
+        };
+
+        assert_eq!(expectation.to_string(), "TCP 49695 -> 80 [PSH,ACK] seq=265846604 ack=3945727432 win=256");
+        assert_eq!(parse_tcp_header(&bytes), IResult::Done(EMPTY_SLICE, expectation));
+    }
+
+    #[test]
+    fn test_tcp_parse_ecn_setup_syn() {
+        let bytes = [0xd4, 0x31, /* Source port */
+                     0x01, 0xbb, /* Dest port */
+                     0x5d, 0x8a, 0x23, 0x10, /* Seq no */
+                     0x00, 0x00, 0x00, 0x00, /* Ack no */
+                     0x50, 0xc2, /* Data offset, reserved and flags */
+                     0xfa, 0xf0, /* Window */
+                     0x3f, 0x1b, /* Checksum */
+                     0x00, 0x00 /* Urgent pointer */];
+
+        let expectation = TcpHeader {
+            source_port: 54321,
+            dest_port: 443,
+            sequence_no: 0x5d8a2310,
+            ack_no: 0,
+            data_offset: 20,
+            reserved: 0,
+            flag_ns: false,
+            flag_cwr: true,
+            flag_ece: true,
+            flag_urg: false,
+            flag_ack: false,
+            flag_psh: false,
+            flag_rst: false,
+            flag_syn: true,
+            flag_fin: false,
+            window: 64240,
+            checksum: 0x3f1b,
+            urgent_pointer: 0,
+            #[cfg(feature = "alloc")]
             options: None,
         };
 
         assert_eq!(parse_tcp_header(&bytes), IResult::Done(EMPTY_SLICE, expectation));
     }
+
+    #[test]
+    fn test_tcp_parse_ns_flag() {
+        let bytes = [0xc2, 0x1f, 0x00, 0x50,
+                     0x0f, 0xd8, 0x7f, 0x4c,
+                     0xeb, 0x2f, 0x05, 0xc8,
+                     0x51, 0x10, /* Data offset, NS and ACK */
+                     0x01, 0x00, 0x7c, 0x29, 0x00, 0x00];
+
+        if let IResult::Done(_, header) = parse_tcp_header(&bytes) {
+            assert!(header.flag_ns);
+            assert!(header.flag_ack);
+            assert!(!header.flag_cwr);
+            assert_eq!(header.reserved, 0);
+        } else {
+            panic!();
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_tcp_parse_keeps_unknown_options() {
+        let bytes = [0xc2, 0x1f, 0x00, 0x50,
+                     0x0f, 0xd8, 0x7f, 0x4c,
+                     0xeb, 0x2f, 0x05, 0xc8,
+                     0x70, 0x02, /* Data offset 7 and SYN */
+                     0x01, 0x00, 0x7c, 0x29, 0x00, 0x00,
+                     0x02, 0x04, 0x05, 0xb4, /* Maximum segment size */
+                     0xfd, 0x04, 0xab, 0xcd /* Experimental option */];
+
+        if let IResult::Done(remaining, header) = parse_tcp_header(&bytes) {
+            assert_eq!(remaining, EMPTY_SLICE);
+            assert_eq!(header.data_offset, 28);
+            assert_eq!(header.options, Some(vec![
+                TcpOption::MaximumSegmentSize(1460),
+                TcpOption::Unknown { kind: 253, data: vec![0xab, 0xcd] },
+            ]));
+        } else {
+            panic!();
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_tcp_parse_option_kinds() {
+        assert_eq!(tcp_parse_option(&[0x00]), IResult::Done(EMPTY_SLICE, TcpOption::EndOfOptions));
+        assert_eq!(tcp_parse_option(&[0x01]), IResult::Done(EMPTY_SLICE, TcpOption::NoOperation));
+        assert_eq!(tcp_parse_option(&[0x03, 0x03, 0x07]),
+                   IResult::Done(EMPTY_SLICE, TcpOption::WindowScale(7)));
+        assert_eq!(tcp_parse_option(&[0x04, 0x02]),
+                   IResult::Done(EMPTY_SLICE, TcpOption::SackPermitted));
+        assert_eq!(tcp_parse_option(&[0xfd, 0x02]),
+                   IResult::Done(EMPTY_SLICE, TcpOption::Unknown { kind: 253, data: vec![] }));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_tcp_parse_fast_open_cookie() {
+        assert_eq!(tcp_parse_option(&[0x22, 0x02]),
+                   IResult::Done(EMPTY_SLICE, TcpOption::FastOpenCookie(vec![])));
+        let cookie = [0x22, 0x0a, 0x9f, 0x3c, 0x51, 0x07, 0xe2, 0x8b, 0x46, 0xd0];
+        assert_eq!(tcp_parse_option(&cookie),
+                   IResult::Done(EMPTY_SLICE, TcpOption::FastOpenCookie(cookie[2..].to_vec())));
+        assert_eq!(TcpOption::FastOpenCookie(cookie[2..].to_vec()).wire_len(), 10);
+        for bad in &[&[0x22, 0x04, 0x00, 0x00][..], &[0x22, 0x13][..]] {
+            match tcp_parse_option(bad) {
+                IResult::Error(_) => {}
+                other => panic!("expected an error, got {:?}", other),
+            }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_tcp_parse_mptcp_options() {
+        let bytes = [0xc3, 0x50, 0x00, 0x50, 0x00, 0x00, 0x00, 0x01,
+                     0x00, 0x00, 0x00, 0x00, 0x80, 0x02, 0x72, 0x10,
+                     0x00, 0x00, 0x00, 0x00, /* SYN, data offset 32 */
+                     0x1e, 0x0c, 0x00, 0x81, /* MP_CAPABLE, version 0, checksum required */
+                     0x3b, 0x2a, 0x9d, 0x41, 0x07, 0xe6, 0x5c, 0x10, /* Sender's key */];
+        if let IResult::Done(remaining, header) = parse_tcp_header(&bytes) {
+            assert_eq!(remaining, EMPTY_SLICE);
+            assert!(header.flag_syn);
+            let option = TcpOption::Mptcp(MptcpSubtype::MpCapable {
+                version: 0,
+                flags: 0x81,
+                sender_key: Some(0x3b2a9d4107e65c10),
+                receiver_key: None,
+            });
+            assert_eq!(option.wire_len(), 12);
+            assert_eq!(header.options, Some(vec![option]));
+        } else {
+            panic!();
+        }
+
+        assert_eq!(tcp_parse_option(&[0x1e, 0x0c, 0x11, 0x01, 0x8c, 0x7d, 0x1f, 0x0a,
+                                      0x5e, 0x44, 0x27, 0x9b]),
+                   IResult::Done(EMPTY_SLICE, TcpOption::Mptcp(MptcpSubtype::MpJoinSyn {
+                       backup: true,
+                       address_id: 1,
+                       token: 0x8c7d1f0a,
+                       nonce: 0x5e44279b,
+                   })));
+        assert_eq!(tcp_parse_option(&[0x1e, 0x04, 0x20, 0x00]),
+                   IResult::Done(EMPTY_SLICE, TcpOption::Mptcp(MptcpSubtype::Other {
+                       subtype: 2,
+                       data: vec![0x20, 0x00],
+                   })));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_tcp_parse_syn_ack_options() {
+        let bytes = [0x00, 0x50, 0xc2, 0x1f, /* Ports */
+                     0x9a, 0x4b, 0x11, 0x02, /* Seq no */
+                     0x0f, 0xd8, 0x7f, 0x4d, /* Ack no */
+                     0xa0, 0x12, /* Data offset 10, SYN and ACK */
+                     0xfe, 0x88, 0x51, 0x7e, 0x00, 0x00,
+                     0x02, 0x04, 0x05, 0xb4, /* Maximum segment size */
+                     0x04, 0x02, /* SACK permitted */
+                     0x08, 0x0a, 0x2c, 0x0b, 0x3e, 0x91, 0x00, 0x1d, 0x4f, 0x80, /* Timestamps */
+                     0x01, /* No operation */
+                     0x03, 0x03, 0x07 /* Window scale */];
+
+        if let IResult::Done(remaining, header) = parse_tcp_header(&bytes) {
+            assert_eq!(remaining, EMPTY_SLICE);
+            assert!(header.flag_syn && header.flag_ack);
+            assert_eq!(header.options, Some(vec![
+                TcpOption::MaximumSegmentSize(1460),
+                TcpOption::SackPermitted,
+                TcpOption::Timestamp { tsval: 0x2c0b3e91, tsecr: 0x001d4f80 },
+                TcpOption::NoOperation,
+                TcpOption::WindowScale(7),
+            ]));
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn test_tcp_parse_options_into_fixed_buffer() {
+        let bytes = [0x00, 0x50, 0xc2, 0x1f, 0x9a, 0x4b, 0x11, 0x02,
+                     0x0f, 0xd8, 0x7f, 0x4d, 0xa0, 0x12, 0xfe, 0x88, 0x51, 0x7e, 0x00, 0x00,
+                     0x02, 0x04, 0x05, 0xb4, /* Maximum segment size */
+                     0x04, 0x02, /* SACK permitted */
+                     0x08, 0x0a, 0x2c, 0x0b, 0x3e, 0x91, 0x00, 0x1d, 0x4f, 0x80, /* Timestamps */
+                     0x01, /* No operation */
+                     0x03, 0x03, 0x07 /* Window scale */];
+        let mut options = [TcpOptionRef::NoOperation; 8];
+        if let IResult::Done(remaining, (_header, count)) = parse_tcp_header_into(&bytes, &mut options) {
+            assert_eq!(remaining, EMPTY_SLICE);
+            #[cfg(feature = "alloc")]
+            assert_eq!(_header.options, None);
+            assert_eq!(count, 5);
+            assert_eq!(options[..count], [
+                TcpOptionRef::MaximumSegmentSize(1460),
+                TcpOptionRef::SackPermitted,
+                TcpOptionRef::Timestamp { tsval: 0x2c0b3e91, tsecr: 0x001d4f80 },
+                TcpOptionRef::NoOperation,
+                TcpOptionRef::WindowScale(7),
+            ]);
+        } else {
+            panic!();
+        }
+
+        let mut too_few = [TcpOptionRef::NoOperation; 4];
+        match parse_tcp_header_into(&bytes, &mut too_few) {
+            IResult::Error(Err::Position(ErrorKind::Count, _)) => {}
+            other => panic!("expected a count error, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_tcp_parse_option_rejects_bad_timestamp_length() {
+        let bytes = [0x08, 0x06, 0x2c, 0x0b, 0x3e, 0x91];
+        match tcp_parse_option(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_tcp_parse_sack_blocks() {
+        let bytes = [0x00, 0x50, 0xc2, 0x1f,
+                     0x9a, 0x4b, 0x11, 0x02,
+                     0x0f, 0xd8, 0x7f, 0x4d,
+                     0xa0, 0x10, /* Data offset 10 and ACK */
+                     0x01, 0xf5, 0x00, 0x00, 0x00, 0x00,
+                     0x01, 0x01, /* No operation */
+                     0x05, 0x12, /* SACK with two blocks */
+                     0x0f, 0xd8, 0x89, 0x10, 0x0f, 0xd8, 0x8e, 0xc0,
+                     0x0f, 0xd8, 0x94, 0x70, 0x0f, 0xd8, 0x9a, 0x20,
+                     0x48, 0x54 /* Data */];
+
+        if let IResult::Done(remaining, header) = parse_tcp_header(&bytes) {
+            assert_eq!(remaining, b"HT");
+            assert_eq!(header.options, Some(vec![
+                TcpOption::NoOperation,
+                TcpOption::NoOperation,
+                TcpOption::Sack {
+                    blocks: vec![(0x0fd88910, 0x0fd88ec0), (0x0fd89470, 0x0fd89a20)],
+                },
+            ]));
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn sack_blocks_ignore_partial_block() {
+        let blocks = [0x0f, 0xd8, 0x89, 0x10, 0x0f, 0xd8, 0x8e, 0xc0, 0x00, 0x00, 0x00, 0x00];
+        let option = TcpOptionRef::Sack(&blocks);
+        assert!(option.sack_blocks().eq([(0x0fd88910, 0x0fd88ec0)].iter().cloned()));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_tcp_parse_option_rejects_bad_sack_length() {
+        for bytes in &[&[0x05, 0x02][..], &[0x05, 0x0c, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0][..]] {
+            match tcp_parse_option(bytes) {
+                IResult::Error(_) => {}
+                other => panic!("expected an error, got {:?}", other),
+            }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_tcp_parse_options_without_end_of_options() {
+        let bytes = [0xc2, 0x1f, 0x00, 0x50,
+                     0x0f, 0xd8, 0x7f, 0x4c,
+                     0x00, 0x00, 0x00, 0x00,
+                     0x60, 0x02, /* Data offset 6 and SYN */
+                     0xfa, 0xf0, 0x7c, 0x29, 0x00, 0x00,
+                     0x02, 0x04, 0x05, 0xb4 /* Maximum segment size */];
+
+        if let IResult::Done(remaining, header) = parse_tcp_header(&bytes) {
+            assert_eq!(remaining, EMPTY_SLICE);
+            assert_eq!(header.data_offset, 24);
+            assert_eq!(header.options, Some(vec![TcpOption::MaximumSegmentSize(1460)]));
+        } else {
+            panic!();
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_tcp_parse_options_rejects_option_past_end() {
+        let bytes = [0x02, 0x04, 0x05, 0xb4, /* Maximum segment size */
+                     0xfd, 0x20, 0xab, 0xcd /* Option claiming 32 bytes */];
+        match tcp_parse_options(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+
+        let mut segment = vec![0xc2, 0x1f, 0x00, 0x50,
+                               0x0f, 0xd8, 0x7f, 0x4c,
+                               0xeb, 0x2f, 0x05, 0xc8,
+                               0x70, 0x02, /* Data offset 7, SYN */
+                               0xfa, 0xf0, 0x7c, 0x29, 0x00, 0x00];
+        segment.extend_from_slice(&bytes);
+        match parse_tcp_header_strict(&segment) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tcp_parse_rejects_short_data_offset() {
+        let bytes = [0xc2, 0x1f, 0x00, 0x50,
+                     0x0f, 0xd8, 0x7f, 0x4c,
+                     0xeb, 0x2f, 0x05, 0xc8,
+                     0x40, 0x10, /* Data offset 4 */
+                     0x01, 0x00, 0x7c, 0x29, 0x00, 0x00];
+        match parse_tcp_header(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tcp_header_len_and_validity() {
+        let bytes = [0xc2, 0x1f, 0x00, 0x50,
+                     0x0f, 0xd8, 0x7f, 0x4c,
+                     0xeb, 0x2f, 0x05, 0xc8,
+                     0x50, 0x10, /* Data offset 5 */
+                     0x01, 0x00, 0x7c, 0x29, 0x00, 0x00];
+        if let IResult::Done(_, header) = parse_tcp_header(&bytes) {
+            assert_eq!(header.header_len_bytes(), 20);
+            assert!(header.is_valid());
+            assert_eq!(header.offset_reserved_byte(), 0x50);
+        } else {
+            panic!();
+        }
+
+        let mut bytes = bytes.to_vec();
+        bytes[12] = 0x81; /* Data offset 8, NS */
+        bytes.extend_from_slice(&[0x01, 0x01, 0x08, 0x0a, 0x00, 0x00, 0x00, 0x01,
+                                  0x00, 0x00, 0x00, 0x02]);
+        if let IResult::Done(_, header) = parse_tcp_header(&bytes) {
+            assert_eq!(header.header_len_bytes(), 32);
+            assert!(header.is_valid());
+            assert_eq!(header.offset_reserved_byte(), 0x81);
+        } else {
+            panic!();
+        }
+
+        let header = TcpHeader { data_offset: 16, ..TcpHeader::default() };
+        assert!(!header.is_valid());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_tcp_parse_strict_rejects_malformed_options() {
+        let bytes = [0xc2, 0x1f, 0x00, 0x50,
+                     0x0f, 0xd8, 0x7f, 0x4c,
+                     0xeb, 0x2f, 0x05, 0xc8,
+                     0x70, 0x02, /* Data offset 7, SYN */
+                     0xfa, 0xf0, 0x7c, 0x29, 0x00, 0x00,
+                     0x02, 0x04, 0x05, 0xb4, /* Maximum segment size */
+                     0x08, 0x06, 0x00, 0x00 /* Timestamps with length 6 */];
+        match parse_tcp_header(&bytes) {
+            IResult::Done(rest, header) => {
+                assert_eq!(rest, EMPTY_SLICE);
+                assert_eq!(header.options, None);
+            }
+            other => panic!("expected a header, got {:?}", other),
+        }
+        match parse_tcp_header_strict(&bytes) {
+            IResult::Error(_) => {}
+            other => panic!("expected an error, got {:?}", other),
+        }
+
+        let mut bytes = bytes;
+        bytes[24..].copy_from_slice(&[0x01, 0x01, 0x01, 0x00]);
+        match parse_tcp_header_strict(&bytes) {
+            IResult::Done(_, header) => assert_eq!(header.options.map(|o| o.len()), Some(5)),
+            other => panic!("expected a header, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tcp_parse_truncated_options() {
+        let bytes = [0xc2, 0x1f, 0x00, 0x50,
+                     0x0f, 0xd8, 0x7f, 0x4c,
+                     0xeb, 0x2f, 0x05, 0xc8,
+                     0xf0, 0x10, /* Data offset 15 */
+                     0x01, 0x00, 0x7c, 0x29, 0x00, 0x00,
+                     0x01, 0x01];
+        assert_eq!(parse_tcp_header(&bytes), IResult::Incomplete(Needed::Size(60)));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_tcp_options_iter_matches_parsed_options() {
+        let bytes = [0x00, 0x50, 0xc2, 0x1f,
+                     0x9a, 0x4b, 0x11, 0x02,
+                     0x0f, 0xd8, 0x7f, 0x4d,
+                     0xa0, 0x12,
+                     0xfe, 0x88, 0x51, 0x7e, 0x00, 0x00,
+                     0x02, 0x04, 0x05, 0xb4,
+                     0x04, 0x02,
+                     0x08, 0x0a, 0x2c, 0x0b, 0x3e, 0x91, 0x00, 0x1d, 0x4f, 0x80,
+                     0x01,
+                     0x03, 0x03, 0x07,
+                     0x47, 0x45, 0x54 /* Data */];
+
+        let eager = match parse_tcp_header(&bytes) {
+            IResult::Done(_, header) => header.options.unwrap(),
+            _ => panic!(),
+        };
+        if let IResult::Done(remaining, (header, raw_options)) = parse_tcp_header_raw(&bytes) {
+            assert_eq!(remaining, b"GET");
+            assert_eq!(header.options, None);
+            assert_eq!(raw_options.len(), 20);
+            let lazy: Vec<TcpOption> = header.options_iter(raw_options).map(TcpOption::from).collect();
+            assert_eq!(lazy, eager);
+            assert_eq!(header.options_iter(raw_options).nth(2),
+                       Some(TcpOptionRef::Timestamp { tsval: 0x2c0b3e91, tsecr: 0x001d4f80 }));
+        } else {
+            panic!();
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_tcp_header_builder_syn_with_mss() {
+        let header = TcpHeaderBuilder::new()
+            .source_port(49695)
+            .dest_port(80)
+            .seq(0x0fd87f4c)
+            .syn(true)
+            .window(64240)
+            .mss(1460)
+            .build();
+
+        assert_eq!(header.data_offset / 4, 6);
+        assert!(header.flag_syn);
+        assert!(!header.flag_ack);
+        assert_eq!(header.checksum, 0);
+        assert_eq!(header.options, Some(vec![TcpOption::MaximumSegmentSize(1460)]));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_tcp_header_builder_pads_options() {
+        let header = TcpHeaderBuilder::new()
+            .ack(1)
+            .option(TcpOption::WindowScale(7))
+            .build();
+        assert_eq!(header.data_offset, 24);
+        assert!(header.flag_ack);
+
+        let header = TcpHeaderBuilder::new().build();
+        assert_eq!(header.data_offset, 20);
+        assert_eq!(header.options, None);
+    }
+    #[test]
+    fn test_tcp_verify_checksum() {
+        use ipv4::parse_ipv4_header;
+
+        let mut bytes = [0x45, 0x00, 0x00, 0x38, 0x76, 0xf4, 0x40, 0x00, 0x40, 0x06, 0x80, 0xd9,
+                         0xc0, 0xa8, 0x00, 0x6c, 0xd0, 0x61, 0xb1, 0x7c, /* IPv4 */
+                         0xb0, 0xc2, 0x00, 0x50, 0xb0, 0xee, 0x32, 0xa6, 0x04, 0x39, 0xae, 0xe6,
+                         0x50, 0x18, 0x00, 0xe5, 0x76, 0x92, 0x00, 0x00, /* TCP */
+                         0x47, 0x45, 0x54, 0x20, 0x2f, 0x69, 0x6e, 0x64, 0x65, 0x78, 0x2e, 0x68,
+                         0x74, 0x6d, 0x6c, 0x0a];
+        let (segment, ip) = match parse_ipv4_header(&bytes) {
+            IResult::Done(segment, ip) => (segment, ip),
+            other => panic!("expected a header, got {:?}", other),
+        };
+        let pseudo_header = ip.pseudo_header(segment.len() as u16);
+        let tcp = match parse_tcp_header(segment) {
+            IResult::Done(_, tcp) => tcp,
+            other => panic!("expected a header, got {:?}", other),
+        };
+        assert!(tcp.verify_checksum(&pseudo_header, segment));
+
+        bytes[45] ^= 0x01;
+        assert!(!tcp.verify_checksum(&pseudo_header, &bytes[20..]));
+    }
 }
@@ -0,0 +1,218 @@
+//! Handles parsing of TLS record and handshake headers
+//!
+//! Only the cleartext framing is decoded: enough to recognise TLS traffic
+//! and read the server name a client asks for in its ClientHello.
+
+use alloc::string::String;
+
+use nom::{IResult, be_u8, be_u16};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TlsContentType {
+    ChangeCipherSpec,
+    Alert,
+    Handshake,
+    ApplicationData,
+    Heartbeat,
+    Other(u8),
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct TlsRecordHeader {
+    pub content_type: TlsContentType,
+    /// The legacy record version, e.g. `0x0303` for TLS 1.2 and 1.3
+    pub version: u16,
+    /// Length of the record payload following this header
+    pub length: u16,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TlsHandshakeType {
+    HelloRequest,
+    ClientHello,
+    ServerHello,
+    NewSessionTicket,
+    EncryptedExtensions,
+    Certificate,
+    ServerKeyExchange,
+    CertificateRequest,
+    ServerHelloDone,
+    CertificateVerify,
+    ClientKeyExchange,
+    Finished,
+    Other(u8),
+}
+#[derive(Debug, PartialEq, Eq)]
+pub struct TlsHandshakeHeader {
+    pub handshake_type: TlsHandshakeType,
+    /// Length of the handshake message body, 24 bits on the wire
+    pub length: u32,
+}
+
+/// Extension number of Server Name Indication (RFC 6066)
+const EXTENSION_SERVER_NAME: u16 = 0;
+const SERVER_NAME_HOST_NAME: u8 = 0;
+
+impl From<u8> for TlsContentType {
+    fn from(raw: u8) -> Self {
+        match raw {
+            20 => TlsContentType::ChangeCipherSpec,
+            21 => TlsContentType::Alert,
+            22 => TlsContentType::Handshake,
+            23 => TlsContentType::ApplicationData,
+            24 => TlsContentType::Heartbeat,
+            other => TlsContentType::Other(other),
+        }
+    }
+}
+
+impl From<u8> for TlsHandshakeType {
+    fn from(raw: u8) -> Self {
+        match raw {
+            0 => TlsHandshakeType::HelloRequest,
+            1 => TlsHandshakeType::ClientHello,
+            2 => TlsHandshakeType::ServerHello,
+            4 => TlsHandshakeType::NewSessionTicket,
+            8 => TlsHandshakeType::EncryptedExtensions,
+            11 => TlsHandshakeType::Certificate,
+            12 => TlsHandshakeType::ServerKeyExchange,
+            13 => TlsHandshakeType::CertificateRequest,
+            14 => TlsHandshakeType::ServerHelloDone,
+            15 => TlsHandshakeType::CertificateVerify,
+            16 => TlsHandshakeType::ClientKeyExchange,
+            20 => TlsHandshakeType::Finished,
+            other => TlsHandshakeType::Other(other),
+        }
+    }
+}
+
+named!(be_u24<&[u8], u32>, map!(take!(3),
+    |b: &[u8]| ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32));
+
+named!(tls_record_header<&[u8], TlsRecordHeader>, chain!(
+    content_type: be_u8 ~
+    version: be_u16 ~
+    length: be_u16,
+    || TlsRecordHeader { content_type: content_type.into(), version, length }
+));
+
+named!(tls_handshake_header<&[u8], TlsHandshakeHeader>, chain!(
+    handshake_type: be_u8 ~
+    length: be_u24,
+    || TlsHandshakeHeader { handshake_type: handshake_type.into(), length }
+));
+
+named!(u8_length_data<&[u8], &[u8]>, chain!(len: be_u8 ~ data: take!(len), || data));
+named!(u16_length_data<&[u8], &[u8]>, chain!(len: be_u16 ~ data: take!(len), || data));
+
+named!(extension<&[u8], (u16, &[u8])>, pair!(be_u16, u16_length_data));
+
+/// The host name in the body of a server_name extension, if it has one
+fn server_name(i: &[u8]) -> Option<String> {
+    let mut names = match u16_length_data(i) {
+        IResult::Done(_, names) => names,
+        _ => return None,
+    };
+    while let IResult::Done(rest, (name_type, name)) = pair!(names, be_u8, u16_length_data) {
+        if name_type == SERVER_NAME_HOST_NAME {
+            return core::str::from_utf8(name).ok().map(String::from);
+        }
+        names = rest;
+    }
+    None
+}
+
+pub fn parse_tls_record_header(i: &[u8]) -> IResult<&[u8], TlsRecordHeader> {
+    tls_record_header(i)
+}
+
+pub fn parse_tls_handshake_header(i: &[u8]) -> IResult<&[u8], TlsHandshakeHeader> {
+    tls_handshake_header(i)
+}
+
+/// Reads the Server Name Indication from the body of a ClientHello, i.e.
+/// the input following its handshake header. Gives `None` when the client
+/// sent no host name.
+pub fn parse_client_hello_sni(i: &[u8]) -> IResult<&[u8], Option<String>> {
+    let (left, mut extensions) = try_parse!(i, chain!(
+        be_u16 ~ /* Client version */
+        take!(32) ~ /* Random */
+        u8_length_data ~ /* Session ID */
+        u16_length_data ~ /* Cipher suites */
+        u8_length_data ~ /* Compression methods */
+        extensions: u16_length_data,
+        || extensions
+    ));
+    while !extensions.is_empty() {
+        let (rest, (extension_type, data)) = try_parse!(extensions, extension);
+        if extension_type == EXTENSION_SERVER_NAME {
+            return IResult::Done(left, server_name(data));
+        }
+        extensions = rest;
+    }
+    IResult::Done(left, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nom::IResult;
+
+    const CLIENT_HELLO: &[u8] = &[
+        0x16, 0x03, 0x01, 0x00, 0x54, /* Handshake record, TLS 1.0, 84 bytes */
+        0x01, 0x00, 0x00, 0x50, /* ClientHello, 80 bytes */
+        0x03, 0x03, /* TLS 1.2 */
+        0x5e, 0x1f, 0x0a, 0x8c, 0x33, 0x4b, 0x97, 0x10, 0xd2, 0x0e, 0x6a, 0x41, 0x19, 0x7c, 0x2b, 0xf0,
+        0x88, 0x01, 0xaf, 0x4d, 0x62, 0x3e, 0xc5, 0x90, 0x7b, 0x14, 0xe9, 0x56, 0x08, 0xdc, 0x21, 0x3a,
+        0x00, /* No session ID */
+        0x00, 0x04, 0x13, 0x01, 0xc0, 0x2f, /* Two cipher suites */
+        0x01, 0x00, /* Null compression */
+        0x00, 0x23, /* 35 bytes of extensions */
+        0xff, 0x01, 0x00, 0x01, 0x00, /* renegotiation_info */
+        0x00, 0x00, 0x00, 0x10, /* server_name, 16 bytes */
+        0x00, 0x0e, 0x00, 0x00, 0x0b, b'e', b'x', b'a', b'm', b'p', b'l', b'e', b'.', b'c', b'o', b'm',
+        0x00, 0x0b, 0x00, 0x02, 0x01, 0x00, /* ec_point_formats */
+        0x00, 0x17, 0x00, 0x00, /* extended_master_secret */
+    ];
+
+    #[test]
+    fn client_hello_sni_works() {
+        let (rest, record) = match parse_tls_record_header(CLIENT_HELLO) {
+            IResult::Done(rest, record) => (rest, record),
+            _ => panic!(),
+        };
+        assert_eq!(record, TlsRecordHeader {
+            content_type: TlsContentType::Handshake,
+            version: 0x0301,
+            length: 84,
+        });
+        assert_eq!(rest.len(), 84);
+
+        let (body, handshake) = match parse_tls_handshake_header(rest) {
+            IResult::Done(body, handshake) => (body, handshake),
+            _ => panic!(),
+        };
+        assert_eq!(handshake, TlsHandshakeHeader {
+            handshake_type: TlsHandshakeType::ClientHello,
+            length: 80,
+        });
+        assert_eq!(parse_client_hello_sni(body),
+                   IResult::Done(&[][..], Some(String::from("example.com"))));
+    }
+
+    #[test]
+    fn client_hello_without_sni_is_none() {
+        let mut body = CLIENT_HELLO[9..].to_vec();
+        // Rename server_name to an unassigned extension number
+        body[50] = 0x7f;
+        assert_eq!(parse_client_hello_sni(&body), IResult::Done(&[][..], None));
+    }
+
+    #[test]
+    fn unknown_content_type_is_other() {
+        let bytes = [0x63, 0x03, 0x03, 0x00, 0x00];
+        if let IResult::Done(_, record) = parse_tls_record_header(&bytes) {
+            assert_eq!(record.content_type, TlsContentType::Other(0x63));
+        } else {
+            panic!();
+        }
+    }
+}
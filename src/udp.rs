@@ -0,0 +1,251 @@
+//! Handles parsing of UDP and UDP-Lite headers
+
+use core::fmt;
+
+use nom::{IResult, be_u16};
+
+use checksum::ChecksumBuilder;
+use error::{Layer, PktError};
+
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct UdpHeader {
+    pub source_port: u16,
+    pub dest_port: u16,
+    /// Length of the header and payload in bytes
+    pub length: u16,
+    pub checksum: u16,
+}
+
+/// A UDP-Lite header (RFC 3828), which runs over IP as
+/// `IPProtocol::UDPLITE`. The length field of UDP is replaced by the number
+/// of bytes the checksum covers; the datagram length comes from IP.
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct UdpLiteHeader {
+    pub source_port: u16,
+    pub dest_port: u16,
+    /// Bytes covered by the checksum, counted from the start of the header;
+    /// 0 means the whole datagram
+    pub checksum_coverage: u16,
+    pub checksum: u16,
+}
+
+const UDP_HEADER_LEN: u16 = 8;
+
+impl UdpHeader {
+    /// Length of the payload according to the length field
+    pub fn payload_len(&self) -> usize {
+        self.length.saturating_sub(UDP_HEADER_LEN) as usize
+    }
+
+    /// Checks the length field against `remaining`, the bytes following the
+    /// header. Extra trailing bytes are accepted, since link layers pad short
+    /// frames.
+    pub fn validate(&self, remaining: &[u8]) -> Result<(), PktError> {
+        if self.length < UDP_HEADER_LEN {
+            return Err(PktError::InvalidHeader {
+                layer: Layer::Udp,
+                reason: "length field shorter than the header",
+            });
+        }
+        if remaining.len() < self.payload_len() {
+            return Err(PktError::Incomplete(self.payload_len()));
+        }
+        Ok(())
+    }
+
+    /// Checks the checksum over `pseudo_header`, from
+    /// `IPv4Header::pseudo_header`, and the `length` bytes of `datagram`,
+    /// which starts with this header. A checksum of 0 means none was
+    /// computed and is accepted.
+    pub fn verify_checksum_ipv4(&self, pseudo_header: &[u8; 12], datagram: &[u8]) -> bool {
+        self.checksum == 0 || self.checksum_matches(pseudo_header, datagram)
+    }
+
+    /// Like `verify_checksum_ipv4`, over the pseudo-header from
+    /// `IPv6Header::pseudo_header`. The checksum is mandatory over IPv6, so a
+    /// checksum of 0 is rejected.
+    pub fn verify_checksum_ipv6(&self, pseudo_header: &[u8; 40], datagram: &[u8]) -> bool {
+        self.checksum_matches(pseudo_header, datagram)
+    }
+
+    fn checksum_matches(&self, pseudo_header: &[u8], datagram: &[u8]) -> bool {
+        match datagram.get(..self.length as usize) {
+            Some(covered) if self.length >= UDP_HEADER_LEN => {
+                ChecksumBuilder::new().write(pseudo_header).write(covered).sum() == 0xffff
+            }
+            _ => false,
+        }
+    }
+}
+
+impl UdpLiteHeader {
+    /// The bytes of `datagram`, which starts with this header, that the
+    /// checksum covers. A coverage shorter than the header or longer than
+    /// the datagram is invalid and yields `None`.
+    pub fn covered<'a>(&self, datagram: &'a [u8]) -> Option<&'a [u8]> {
+        match self.checksum_coverage as usize {
+            0 => Some(datagram),
+            len if len < UDP_HEADER_LEN as usize || len > datagram.len() => None,
+            len => Some(&datagram[..len]),
+        }
+    }
+
+    /// Checks the checksum over `pseudo_header` and the covered bytes of
+    /// `datagram`, which starts with this header. The pseudo-header is the
+    /// one UDP uses, with the length taken from IP.
+    pub fn verify_checksum(&self, pseudo_header: &[u8], datagram: &[u8]) -> bool {
+        match self.covered(datagram) {
+            Some(covered) => ChecksumBuilder::new().write(pseudo_header).write(covered).sum() == 0xffff,
+            None => false,
+        }
+    }
+}
+
+impl fmt::Display for UdpHeader {
+    /// A one-line summary, e.g. `UDP 50000 -> 53 len=12`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "UDP {} -> {} len={}", self.source_port, self.dest_port, self.length)
+    }
+}
+
+named!(udp_parse<&[u8], UdpHeader>, chain!(
+    source_port: be_u16 ~
+    dest_port: be_u16 ~
+    length: be_u16 ~
+    checksum: be_u16,
+    || UdpHeader { source_port, dest_port, length, checksum }
+));
+
+named!(udplite_parse<&[u8], UdpLiteHeader>, chain!(
+    source_port: be_u16 ~
+    dest_port: be_u16 ~
+    checksum_coverage: be_u16 ~
+    checksum: be_u16,
+    || UdpLiteHeader { source_port, dest_port, checksum_coverage, checksum }
+));
+
+pub fn parse_udp_header(i: &[u8]) -> IResult<&[u8], UdpHeader> {
+    udp_parse(i)
+}
+
+pub fn parse_udplite_header(i: &[u8]) -> IResult<&[u8], UdpLiteHeader> {
+    udplite_parse(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_udplite_header, udp_parse, UdpHeader, UdpLiteHeader};
+    use checksum::ChecksumBuilder;
+    use error::{Layer, PktError};
+    use nom::IResult;
+
+    #[test]
+    fn udp_header_works() {
+        let bytes = [0xc3, 0x50, /* Source port */
+                     0x00, 0x35, /* Destination port */
+                     0x00, 0x0c, /* Length */
+                     0x8b, 0x4e, /* Checksum */
+                     0xde, 0xad, 0xbe, 0xef /* Payload */];
+        let expectation = UdpHeader {
+            source_port: 50000,
+            dest_port: 53,
+            length: 12,
+            checksum: 0x8b4e,
+        };
+        assert_eq!(expectation.to_string(), "UDP 50000 -> 53 len=12");
+        assert_eq!(udp_parse(&bytes), IResult::Done(&bytes[8..], expectation));
+    }
+
+    #[test]
+    fn udp_validate_accepts_consistent_length() {
+        let header = UdpHeader { source_port: 50000, dest_port: 53, length: 12, checksum: 0 };
+        assert_eq!(header.payload_len(), 4);
+        assert_eq!(header.validate(&[0xde, 0xad, 0xbe, 0xef]), Ok(()));
+        assert_eq!(header.validate(&[0xde, 0xad, 0xbe, 0xef, 0x00, 0x00]), Ok(()));
+        assert_eq!(header.validate(&[0xde, 0xad]), Err(PktError::Incomplete(4)));
+    }
+
+    #[test]
+    fn udp_validate_rejects_under_length_field() {
+        let header = UdpHeader { source_port: 50000, dest_port: 53, length: 4, checksum: 0 };
+        assert_eq!(header.payload_len(), 0);
+        assert_eq!(header.validate(&[]),
+                   Err(PktError::InvalidHeader {
+                       layer: Layer::Udp,
+                       reason: "length field shorter than the header",
+                   }));
+    }
+
+    #[test]
+    fn udplite_partial_coverage_checksum() {
+        let pseudo_header = [0x0a, 0x00, 0x00, 0x01, 0x0a, 0x00, 0x00, 0x02,
+                             0x00, 0x88, 0x00, 0x10 /* UDP-Lite, length 16 */];
+        let mut datagram = [0x13, 0x88, /* Source port */
+                            0x13, 0x89, /* Destination port */
+                            0x00, 0x0c, /* Checksum coverage */
+                            0x00, 0x00, /* Checksum */
+                            0x01, 0x02, 0x03, 0x04, /* Covered payload */
+                            0xff, 0xee, 0xdd, 0xcc /* Uncovered payload */];
+        let checksum = ChecksumBuilder::new().write(&pseudo_header).write(&datagram[..12]).finish();
+        datagram[6..8].copy_from_slice(&checksum.to_be_bytes());
+
+        let header = match parse_udplite_header(&datagram) {
+            IResult::Done(rest, header) => {
+                assert_eq!(rest, &datagram[8..]);
+                header
+            }
+            other => panic!("expected a header, got {:?}", other),
+        };
+        assert_eq!(header, UdpLiteHeader {
+            source_port: 5000,
+            dest_port: 5001,
+            checksum_coverage: 12,
+            checksum,
+        });
+        assert_eq!(header.covered(&datagram), Some(&datagram[..12]));
+        assert!(header.verify_checksum(&pseudo_header, &datagram));
+
+        // Damage outside the coverage goes unnoticed, inside it does not
+        datagram[15] ^= 0xff;
+        assert!(header.verify_checksum(&pseudo_header, &datagram));
+        datagram[9] ^= 0xff;
+        assert!(!header.verify_checksum(&pseudo_header, &datagram));
+    }
+
+    #[test]
+    fn udplite_coverage_outside_the_datagram_is_invalid() {
+        let header = UdpLiteHeader { source_port: 1, dest_port: 2, checksum_coverage: 4, checksum: 0 };
+        assert_eq!(header.covered(&[0; 16]), None);
+        let header = UdpLiteHeader { checksum_coverage: 20, ..header };
+        assert_eq!(header.covered(&[0; 16]), None);
+        let header = UdpLiteHeader { checksum_coverage: 0, ..header };
+        assert_eq!(header.covered(&[0; 16]), Some(&[0; 16][..]));
+    }
+    #[test]
+    fn udp_verify_checksum() {
+        let pseudo_header = [0x0a, 0x00, 0x00, 0x01, 0x0a, 0x00, 0x00, 0x02,
+                             0x00, 0x11, 0x00, 0x0c /* UDP, length 12 */];
+        let mut datagram = [0xc3, 0x50, 0x00, 0x35, /* Ports */
+                            0x00, 0x0c, 0x00, 0x00, /* Length, checksum */
+                            0xde, 0xad, 0xbe, 0xef,
+                            0x00, 0x00 /* Link-layer padding */];
+        let checksum = ChecksumBuilder::new().write(&pseudo_header).write(&datagram[..12]).finish();
+        datagram[6..8].copy_from_slice(&checksum.to_be_bytes());
+        let header = match udp_parse(&datagram) {
+            IResult::Done(_, header) => header,
+            other => panic!("expected a header, got {:?}", other),
+        };
+        assert!(header.verify_checksum_ipv4(&pseudo_header, &datagram));
+
+        datagram[9] ^= 0xff;
+        assert!(!header.verify_checksum_ipv4(&pseudo_header, &datagram));
+        assert!(!header.verify_checksum_ipv4(&pseudo_header, &datagram[..10]));
+
+        // A zero checksum is only optional over IPv4
+        let header = UdpHeader { checksum: 0, ..header };
+        assert!(header.verify_checksum_ipv4(&pseudo_header, &datagram));
+        assert!(!header.verify_checksum_ipv6(&[0; 40], &datagram));
+    }
+}
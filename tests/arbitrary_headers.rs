@@ -0,0 +1,61 @@
+#![cfg(feature = "arbitrary")]
+
+extern crate arbitrary;
+extern crate nom;
+extern crate pktparse;
+
+mod tests {
+    use arbitrary::{Arbitrary, Unstructured};
+    use nom::IResult::Done;
+    use pktparse::ipv4::{self, IPv4Header};
+    use pktparse::tcp::{self, TcpHeader};
+    use pktparse::udp::{self, UdpHeader};
+
+    fn seed_bytes(seed: u8) -> Vec<u8> {
+        (0..256u16).map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed)).collect()
+    }
+
+    fn tcp_to_bytes(h: &TcpHeader) -> Vec<u8> {
+        let flags = [h.flag_cwr, h.flag_ece, h.flag_urg, h.flag_ack,
+                     h.flag_psh, h.flag_rst, h.flag_syn, h.flag_fin]
+            .iter()
+            .fold(0u8, |acc, &set| (acc << 1) | set as u8);
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&h.source_port.to_be_bytes());
+        bytes.extend_from_slice(&h.dest_port.to_be_bytes());
+        bytes.extend_from_slice(&h.sequence_no.to_be_bytes());
+        bytes.extend_from_slice(&h.ack_no.to_be_bytes());
+        bytes.extend_from_slice(&[((h.data_offset / 4) << 4) | h.flag_ns as u8, flags]);
+        bytes.extend_from_slice(&h.window.to_be_bytes());
+        bytes.extend_from_slice(&h.checksum.to_be_bytes());
+        bytes.extend_from_slice(&h.urgent_pointer.to_be_bytes());
+        bytes
+    }
+
+    fn udp_to_bytes(h: &UdpHeader) -> Vec<u8> {
+        [h.source_port, h.dest_port, h.length, h.checksum]
+            .iter()
+            .flat_map(|field| field.to_be_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn arbitrary_headers_survive_a_wire_round_trip() {
+        for seed in 0..32 {
+            let bytes = seed_bytes(seed);
+            let mut u = Unstructured::new(&bytes);
+
+            let ip = IPv4Header::arbitrary(&mut u).unwrap();
+            let wire = ip.to_bytes();
+            assert_eq!(ipv4::parse_ipv4_header(&wire), Done(&[][..], ip));
+
+            let tcp_hdr = TcpHeader::arbitrary(&mut u).unwrap();
+            let wire = tcp_to_bytes(&tcp_hdr);
+            assert_eq!(tcp::parse_tcp_header(&wire), Done(&[][..], tcp_hdr));
+
+            let udp_hdr = UdpHeader::arbitrary(&mut u).unwrap();
+            let wire = udp_to_bytes(&udp_hdr);
+            assert_eq!(udp::parse_udp_header(&wire), Done(&[][..], udp_hdr));
+        }
+    }
+}
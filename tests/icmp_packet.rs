@@ -1,4 +1,5 @@
-#[macro_use]
+#![cfg(feature = "alloc")]
+
 extern crate nom;
 extern crate pktparse;
 
@@ -43,19 +44,20 @@ mod tests {
             protocol: IPv4Protocol::ICMP,
             chksum: 0x22ed,
             source_addr: IPv4Address([10, 10, 1, 135]),
-            dest_addr: IPv4Address([10, 10, 1, 180])
+            dest_addr: IPv4Address([10, 10, 1, 180]),
+            options: vec![],
         };
         let parsed_eth_frame = ethernet::parse_ethernet_frame(&bytes);
         if let Done(remaining_data, eth_frame) = parsed_eth_frame {
             assert_eq!(eth_frame, eth_expectation);
-            let parsed_ip_hdr = ipv4::parse_ipv4_header(&remaining_data);
+            let parsed_ip_hdr = ipv4::parse_ipv4_header(remaining_data);
             if let Done(_remaining_data, ip_hdr) = parsed_ip_hdr {
                 assert_eq!(ip_hdr, ip_expectation);
             } else {
-                assert!(false);
+                panic!();
             }
         } else {
-            assert!(false);
+            panic!();
         }
     }
 }
@@ -0,0 +1,89 @@
+#![cfg(feature = "alloc")]
+
+extern crate nom;
+extern crate pktparse;
+
+mod tests {
+    use nom::IResult::Done;
+    use pktparse::cursor;
+    use pktparse::ethernet::EtherType;
+    use pktparse::ip::IPProtocol;
+    use pktparse::packet::{self, IpHeader, TransportHeader};
+
+    #[test]
+    fn parse_ethernet_ipv4_tcp_packet() {
+        let bytes = [
+           0x00, 0x23, 0x54, 0x07, 0x93, 0x6c, 0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b, 0x08, 0x00,
+           0x45, 0x00, 0x00, 0x38, 0x76, 0xf4, 0x40, 0x00, 0x40, 0x06, 0x80, 0xd9, 0xc0, 0xa8, 0x00,
+           0x6c, 0xd0, 0x61, 0xb1, 0x7c, 0xb0, 0xc2, 0x00, 0x50, 0xb0, 0xee, 0x32, 0xa6, 0x04, 0x39,
+           0xae, 0xe6, 0x50, 0x18, 0x00, 0xe5, 0x76, 0x92, 0x00, 0x00, 0x47, 0x45, 0x54, 0x20, 0x2f,
+           0x69, 0x6e, 0x64, 0x65, 0x78, 0x2e, 0x68, 0x74, 0x6d, 0x6c, 0x0a];
+
+        if let Done(remaining, headers) = packet::parse_packet(&bytes) {
+            assert!(headers.is_tcp());
+            assert_eq!(headers.ip_protocol(), Some(IPProtocol::TCP));
+            assert_eq!(headers.payload, b"GET /index.html\n");
+            assert_eq!(headers.tcp_payload(), Some(&b"GET /index.html\n"[..]));
+            assert_eq!(headers.ethernet.unwrap().ethertype, EtherType::IPv4);
+            assert_eq!(headers.vlan, None);
+            match headers.ip {
+                Some(IpHeader::V4(ref ip)) => assert_eq!(ip.protocol, IPProtocol::TCP),
+                ref other => panic!("expected IPv4, got {:?}", other),
+            }
+            match headers.transport {
+                Some(TransportHeader::Tcp(ref tcp)) => {
+                    assert_eq!(tcp.source_port, 45250);
+                    assert_eq!(tcp.dest_port, 80);
+                }
+                ref other => panic!("expected TCP, got {:?}", other),
+            }
+            assert_eq!(remaining, b"GET /index.html\x0a");
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    fn parse_ethernet_ipv4_tcp_through_one_cursor() {
+        let bytes = [
+           0x00, 0x23, 0x54, 0x07, 0x93, 0x6c, 0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b, 0x08, 0x00,
+           0x45, 0x00, 0x00, 0x38, 0x76, 0xf4, 0x40, 0x00, 0x40, 0x06, 0x80, 0xd9, 0xc0, 0xa8, 0x00,
+           0x6c, 0xd0, 0x61, 0xb1, 0x7c, 0xb0, 0xc2, 0x00, 0x50, 0xb0, 0xee, 0x32, 0xa6, 0x04, 0x39,
+           0xae, 0xe6, 0x50, 0x18, 0x00, 0xe5, 0x76, 0x92, 0x00, 0x00, 0x47, 0x45, 0x54, 0x20, 0x2f,
+           0x69, 0x6e, 0x64, 0x65, 0x78, 0x2e, 0x68, 0x74, 0x6d, 0x6c, 0x0a];
+
+        let mut buf = &bytes[..];
+        let eth = cursor::parse_ethernet_frame(&mut buf).unwrap();
+        let ip = cursor::parse_ipv4_header(&mut buf).unwrap();
+        let tcp = cursor::parse_tcp_header(&mut buf).unwrap();
+        assert_eq!(eth.ethertype, EtherType::IPv4);
+        assert_eq!(ip.protocol, IPProtocol::TCP);
+        assert_eq!(tcp.dest_port, 80);
+        assert_eq!(buf, b"GET /index.html\n");
+
+        // A failed parse leaves the cursor in place
+        assert!(cursor::parse_ipv4_header(&mut buf).is_err());
+        assert_eq!(buf, b"GET /index.html\n");
+    }
+
+    #[test]
+    fn owned_headers_outlive_the_buffer() {
+        let owned = {
+            let bytes = vec![
+               0x00, 0x23, 0x54, 0x07, 0x93, 0x6c, 0x00, 0x1b, 0x21, 0x0f, 0x91, 0x9b, 0x08, 0x00,
+               0x45, 0x00, 0x00, 0x38, 0x76, 0xf4, 0x40, 0x00, 0x40, 0x06, 0x80, 0xd9, 0xc0, 0xa8, 0x00,
+               0x6c, 0xd0, 0x61, 0xb1, 0x7c, 0xb0, 0xc2, 0x00, 0x50, 0xb0, 0xee, 0x32, 0xa6, 0x04, 0x39,
+               0xae, 0xe6, 0x50, 0x18, 0x00, 0xe5, 0x76, 0x92, 0x00, 0x00, 0x47, 0x45, 0x54, 0x20, 0x2f,
+               0x69, 0x6e, 0x64, 0x65, 0x78, 0x2e, 0x68, 0x74, 0x6d, 0x6c, 0x0a];
+            match packet::parse_packet(&bytes) {
+                Done(_, headers) => headers.into_owned(),
+                other => panic!("expected headers, got {:?}", other),
+            }
+        };
+        assert_eq!(owned.payload, b"GET /index.html\n");
+        match owned.transport {
+            Some(TransportHeader::Tcp(ref tcp)) => assert_eq!(tcp.dest_port, 80),
+            ref other => panic!("expected TCP, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+extern crate nom;
+extern crate pktparse;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Counts the allocations made on the current thread, so the test harness's
+/// own threads do not disturb the count
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+mod tests {
+    use super::ALLOCATIONS;
+    use nom::IResult::Done;
+    use pktparse::tcp::{self, TcpOptionRef};
+
+    #[test]
+    fn options_iter_does_not_allocate() {
+        let bytes = [0x00, 0x50, 0xc2, 0x1f,
+                     0x9a, 0x4b, 0x11, 0x02,
+                     0x0f, 0xd8, 0x7f, 0x4d,
+                     0xf0, 0x10, /* Data offset 15 and ACK */
+                     0xfe, 0x88, 0x51, 0x7e, 0x00, 0x00,
+                     0x01, 0x01, /* No operation */
+                     0x05, 0x0a, 0x0f, 0xd8, 0x89, 0x10, 0x0f, 0xd8, 0x8e, 0xc0, /* SACK */
+                     0x08, 0x0a, 0x2c, 0x0b, 0x3e, 0x91, 0x00, 0x1d, 0x4f, 0x80, /* Timestamp */
+                     0x22, 0x06, 0xde, 0xad, 0xbe, 0xef, /* Fast Open cookie */
+                     0xfd, 0x04, 0x12, 0x34, /* Experimental */
+                     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00 /* End of options and padding */];
+
+        let before = ALLOCATIONS.with(|count| count.get());
+        let (header, raw_options) = match tcp::parse_tcp_header_raw(&bytes) {
+            Done(_, parsed) => parsed,
+            _ => panic!(),
+        };
+        let mut options = 0;
+        let mut sack_edges = 0;
+        for option in header.options_iter(raw_options) {
+            options += 1;
+            for (left, right) in option.sack_blocks() {
+                sack_edges += right - left;
+            }
+            if let TcpOptionRef::FastOpenCookie(cookie) = option {
+                assert_eq!(cookie, &[0xde, 0xad, 0xbe, 0xef]);
+            }
+        }
+        let after = ALLOCATIONS.with(|count| count.get());
+
+        assert_eq!(options, 7);
+        assert_eq!(sack_edges, 0x0fd88ec0 - 0x0fd88910);
+        assert_eq!(after, before);
+    }
+}